@@ -0,0 +1,142 @@
+//! Connecting to servers in the protocol version they speak.
+
+use blocky_net::{connection::Connection, error::ProtocolError};
+
+use crate::ping::{ping, PingError};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConnectError {
+    #[error("could not ping the server: {0}")]
+    Ping(#[from] PingError),
+    #[error("protocol error: {0}")]
+    Protocol(#[from] ProtocolError),
+    #[error("the server runs {name:?} with protocol version {protocol}, which isn't supported")]
+    UnsupportedVersion { name: String, protocol: i32 },
+}
+
+/// Pings the server for the version it runs and opens a connection set up
+/// to speak it, ready for the handshake.
+pub async fn connect(host: &str, port: u16) -> Result<Connection, ConnectError> {
+    let status = ping(host, port).await?.status;
+
+    let Some(version) = status.supported_version() else {
+        let version = status.version.unwrap_or_default();
+        return Err(ConnectError::UnsupportedVersion {
+            name: version.name,
+            protocol: version.protocol,
+        });
+    };
+
+    let mut connection = Connection::connect((host, port)).await?;
+    connection.set_version(version);
+    Ok(connection)
+}
+
+#[cfg(test)]
+mod tests {
+    use blocky_net::{
+        frame::{Frame, FrameDecoder, FrameEncoder},
+        version::ProtocolVersion,
+    };
+    use blocky_protocol::{
+        handshake::{Handshake, Intent},
+        status::{PingRequest, PongResponse, ServerStatus, StatusResponse},
+    };
+    use serde_json::json;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::*;
+
+    /// Reads frames from the client until there are `count` of them.
+    async fn read_frames(stream: &mut TcpStream, count: usize) -> Vec<Frame> {
+        let mut decoder = FrameDecoder::new();
+        let mut frames = Vec::new();
+
+        while frames.len() < count {
+            while let Some(frame) = decoder.try_next_frame().unwrap() {
+                frames.push(frame);
+            }
+
+            if frames.len() < count {
+                let mut buf = [0; 256];
+                let len = stream.read(&mut buf).await.unwrap();
+                assert_ne!(len, 0, "client closed the connection");
+                decoder.queue_slice(&buf[..len]);
+            }
+        }
+
+        frames
+    }
+
+    /// Answers a status ping, saying the server runs `protocol`.
+    async fn serve_status(listener: &TcpListener, protocol: i32) {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        read_frames(&mut stream, 2).await;
+
+        let status: ServerStatus =
+            serde_json::from_value(json!({"version": {"name": "test", "protocol": protocol}}))
+                .unwrap();
+        let mut encoder = FrameEncoder::new();
+        encoder.append_packet(&StatusResponse { status }).unwrap();
+        stream.write_all(&encoder.take()).await.unwrap();
+
+        let ping = read_frames(&mut stream, 1).await.remove(0);
+        let time = ping
+            .decode::<PingRequest>(ProtocolVersion::LATEST)
+            .unwrap()
+            .time;
+        encoder.append_packet(&PongResponse { time }).unwrap();
+        stream.write_all(&encoder.take()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_connect_version() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let server = tokio::spawn(async move {
+            serve_status(&listener, 766).await;
+
+            let (mut stream, _) = listener.accept().await.unwrap();
+            read_frames(&mut stream, 1).await.remove(0)
+        });
+
+        let mut connection = connect("127.0.0.1", port).await.unwrap();
+        assert_eq!(connection.version(), ProtocolVersion::V1_20_6);
+
+        connection
+            .send(&Handshake {
+                protocol_version: connection.version().0,
+                server_address: "127.0.0.1".to_string(),
+                server_port: port,
+                next_state: Intent::Login,
+            })
+            .await
+            .unwrap();
+
+        let handshake = server
+            .await
+            .unwrap()
+            .decode::<Handshake>(ProtocolVersion::LATEST)
+            .unwrap();
+        assert_eq!(handshake.protocol_version, 766);
+    }
+
+    #[tokio::test]
+    async fn test_connect_unsupported_version() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let server = tokio::spawn(async move { serve_status(&listener, 47).await });
+
+        let err = connect("127.0.0.1", port).await.err().unwrap();
+        assert!(matches!(
+            err,
+            ConnectError::UnsupportedVersion { protocol: 47, .. }
+        ));
+        server.await.unwrap();
+    }
+}
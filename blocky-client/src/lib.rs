@@ -1,3 +1,6 @@
+pub mod connect;
+pub mod ping;
+
 // pub mod events;
 
 // use std::{
@@ -0,0 +1,502 @@
+//! Pinging servers for what they show in the server list.
+
+use std::{
+    future::Future,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use blocky_net::{connection::Connection, error::ProtocolError};
+use blocky_protocol::{
+    handshake::{Handshake, Intent},
+    status::{
+        ClientboundStatusPacket, PingRequest, ServerStatus, StatusPlayers, StatusRequest,
+        StatusVersion,
+    },
+    text::TextComponent,
+    PROTOCOL_VERSION,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+/// The protocol version sent in the legacy ping, the one of 1.6.4.
+const LEGACY_PROTOCOL_VERSION: u8 = 78;
+
+/// The most characters a legacy kick message may have.
+const LEGACY_MAX_LEN: usize = 256;
+
+/// How long [`ping`] waits for an answer to either of its attempts.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, thiserror::Error)]
+pub enum PingError {
+    #[error("protocol error: {0}")]
+    Protocol(#[from] ProtocolError),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("the server sent a pong before the status")]
+    UnexpectedPong,
+    #[error("the server sent a status instead of a pong")]
+    UnexpectedStatus,
+    #[error("the server answered the ping with {received} instead of {sent}")]
+    PongMismatch { sent: u64, received: u64 },
+    #[error("the server didn't answer within {0:?}")]
+    Timeout(Duration),
+    #[error("invalid legacy ping response: {0}")]
+    InvalidLegacyResponse(String),
+}
+
+/// What a server answered to a ping.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ping {
+    pub status: ServerStatus,
+    /// How long the server took to answer the ping, or the legacy ping as a
+    /// whole for servers from before 1.7.
+    pub latency: Duration,
+}
+
+/// Pings a server, falling back to the legacy ping if the server doesn't
+/// understand the one introduced in 1.7.
+///
+/// `host` and `port` are also sent to the server, as some servers answer
+/// differently depending on the address they were reached through. Gives up
+/// once [`DEFAULT_TIMEOUT`] has passed.
+pub async fn ping(host: &str, port: u16) -> Result<Ping, PingError> {
+    ping_with_timeout(host, port, DEFAULT_TIMEOUT).await
+}
+
+/// Pings a server like [`ping`] does, giving up once `timeout` has passed.
+/// The modern ping gets half of it, so a server that never answers it still
+/// leaves time for the legacy ping.
+pub async fn ping_with_timeout(
+    host: &str,
+    port: u16,
+    timeout: Duration,
+) -> Result<Ping, PingError> {
+    let deadline = Instant::now() + timeout;
+
+    let err = match ping_modern(host, port, timeout / 2).await {
+        Ok(ping) => return Ok(ping),
+        // some old servers wait for more of the handshake than they got
+        // rather than answering it, so this is worth a retry too
+        Err(PingError::Timeout(_)) => PingError::Timeout(timeout),
+        Err(err) => err,
+    };
+
+    // old servers tend to close the connection or send garbage, so any error
+    // is worth a retry, but the first one is the more useful
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    ping_legacy(host, port, remaining).await.map_err(|_| err)
+}
+
+/// Pings a server the way clients have since 1.7, giving up if it hasn't
+/// answered within `timeout`.
+pub async fn ping_modern(host: &str, port: u16, timeout: Duration) -> Result<Ping, PingError> {
+    with_timeout(timeout, modern(host, port)).await
+}
+
+/// Pings a server the way clients did before 1.7, which servers still answer
+/// to, giving up if it hasn't answered within `timeout`. Only the version,
+/// player counts and description are known this way.
+pub async fn ping_legacy(host: &str, port: u16, timeout: Duration) -> Result<Ping, PingError> {
+    with_timeout(timeout, legacy(host, port)).await
+}
+
+async fn with_timeout<T>(
+    timeout: Duration,
+    future: impl Future<Output = Result<T, PingError>>,
+) -> Result<T, PingError> {
+    tokio::time::timeout(timeout, future)
+        .await
+        .map_err(|_| PingError::Timeout(timeout))?
+}
+
+async fn modern(host: &str, port: u16) -> Result<Ping, PingError> {
+    let mut connection = Connection::connect((host, port)).await?;
+
+    connection
+        .send(&Handshake {
+            protocol_version: PROTOCOL_VERSION,
+            server_address: host.to_string(),
+            server_port: port,
+            next_state: Intent::Status,
+        })
+        .await?;
+    connection.send(&StatusRequest).await?;
+
+    let status = match connection.recv::<ClientboundStatusPacket>().await? {
+        ClientboundStatusPacket::StatusResponse(response) => response.status,
+        ClientboundStatusPacket::PongResponse(_) => return Err(PingError::UnexpectedPong),
+    };
+
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    let start = Instant::now();
+    connection.send(&PingRequest { time }).await?;
+
+    match connection.recv::<ClientboundStatusPacket>().await? {
+        ClientboundStatusPacket::PongResponse(pong) if pong.time == time => Ok(Ping {
+            status,
+            latency: start.elapsed(),
+        }),
+        ClientboundStatusPacket::PongResponse(pong) => Err(PingError::PongMismatch {
+            sent: time,
+            received: pong.time,
+        }),
+        ClientboundStatusPacket::StatusResponse(_) => Err(PingError::UnexpectedStatus),
+    }
+}
+
+async fn legacy(host: &str, port: u16) -> Result<Ping, PingError> {
+    let mut stream = TcpStream::connect((host, port)).await?;
+
+    let start = Instant::now();
+    stream.write_all(&legacy_request(host, port)).await?;
+
+    let mut header = [0; 3];
+    stream.read_exact(&mut header).await?;
+    if header[0] != 0xFF {
+        return Err(PingError::InvalidLegacyResponse(format!(
+            "expected a kick packet but got {:#04x}",
+            header[0]
+        )));
+    }
+
+    let len = u16::from_be_bytes([header[1], header[2]]) as usize;
+    if len > LEGACY_MAX_LEN {
+        return Err(PingError::InvalidLegacyResponse(format!(
+            "message of {len} characters is too long"
+        )));
+    }
+
+    let mut body = vec![0; len * 2];
+    stream.read_exact(&mut body).await?;
+    let latency = start.elapsed();
+
+    let units = body
+        .chunks_exact(2)
+        .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+        .collect::<Vec<_>>();
+    let message = String::from_utf16(&units)
+        .map_err(|_| PingError::InvalidLegacyResponse("message isn't UTF-16".to_string()))?;
+
+    Ok(Ping {
+        status: parse_legacy_status(&message)?,
+        latency,
+    })
+}
+
+/// The ping sent by 1.6 clients, which older servers read as far as they
+/// understand it.
+fn legacy_request(host: &str, port: u16) -> Vec<u8> {
+    let host = host.encode_utf16().collect::<Vec<_>>();
+    let channel = "MC|PingHost".encode_utf16().collect::<Vec<_>>();
+
+    let mut buf = vec![0xFE, 0x01, 0xFA];
+    buf.extend_from_slice(&(channel.len() as u16).to_be_bytes());
+    buf.extend(channel.iter().flat_map(|unit| unit.to_be_bytes()));
+    buf.extend_from_slice(&(7 + host.len() as u16 * 2).to_be_bytes());
+    buf.push(LEGACY_PROTOCOL_VERSION);
+    buf.extend_from_slice(&(host.len() as u16).to_be_bytes());
+    buf.extend(host.iter().flat_map(|unit| unit.to_be_bytes()));
+    buf.extend_from_slice(&(port as i32).to_be_bytes());
+    buf
+}
+
+/// Reads the kick message a server answers the legacy ping with, either
+/// `§1\0protocol\0version\0motd\0online\0max` from 1.4 on or
+/// `motd§online§max` before that.
+fn parse_legacy_status(message: &str) -> Result<ServerStatus, PingError> {
+    let invalid = || PingError::InvalidLegacyResponse(message.to_string());
+    let number = |s: &str| s.parse::<i32>().map_err(|_| invalid());
+
+    let (version, motd, online, max) = match message.strip_prefix("§1\0") {
+        Some(rest) => {
+            let fields = rest.split('\0').collect::<Vec<_>>();
+            let [protocol, name, motd, online, max] = fields[..] else {
+                return Err(invalid());
+            };
+
+            let version = StatusVersion {
+                name: name.to_string(),
+                protocol: number(protocol)?,
+            };
+            (Some(version), motd, online, max)
+        }
+        None => {
+            // the motd may itself contain `§` for formatting
+            let mut fields = message.rsplitn(3, '§');
+            let (Some(max), Some(online), Some(motd)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                return Err(invalid());
+            };
+
+            (None, motd, online, max)
+        }
+    };
+
+    Ok(ServerStatus {
+        version,
+        players: Some(StatusPlayers {
+            max: number(max)?,
+            online: number(online)?,
+            sample: Vec::new(),
+        }),
+        description: TextComponent::text(motd),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use blocky_net::{
+        frame::{Frame, FrameDecoder, FrameEncoder},
+        packet::Packet,
+        version::ProtocolVersion,
+    };
+    use blocky_protocol::status::{PongResponse, StatusResponse};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    async fn read_frame(stream: &mut TcpStream, decoder: &mut FrameDecoder) -> Frame {
+        loop {
+            if let Some(frame) = decoder.try_next_frame().unwrap() {
+                return frame;
+            }
+
+            let mut buf = [0; 256];
+            let len = stream.read(&mut buf).await.unwrap();
+            assert_ne!(len, 0, "client closed the connection");
+            decoder.queue_slice(&buf[..len]);
+        }
+    }
+
+    async fn write_packet<P: Packet>(stream: &mut TcpStream, packet: &P) {
+        let mut encoder = FrameEncoder::new();
+        encoder.append_packet(packet).unwrap();
+        stream.write_all(&encoder.take()).await.unwrap();
+    }
+
+    fn legacy_kick(message: &str) -> Vec<u8> {
+        let units = message.encode_utf16().collect::<Vec<_>>();
+        let mut buf = vec![0xFF];
+        buf.extend_from_slice(&(units.len() as u16).to_be_bytes());
+        buf.extend(units.iter().flat_map(|unit| unit.to_be_bytes()));
+        buf
+    }
+
+    /// Answers a legacy ping like a 1.6 server, and anything else by kicking
+    /// the client, which is all such a server makes of a modern handshake.
+    async fn serve_legacy(listener: &TcpListener, message: &str) -> Vec<u8> {
+        let (mut stream, _) = listener.accept().await.unwrap();
+
+        let mut first = [0; 1];
+        stream.read_exact(&mut first).await.unwrap();
+        if first[0] != 0xFE {
+            stream
+                .write_all(&legacy_kick("Outdated client!"))
+                .await
+                .unwrap();
+            return first.to_vec();
+        }
+
+        let expected = legacy_request("127.0.0.1", 25565).len();
+        let mut request = vec![0xFE; expected];
+        stream.read_exact(&mut request[1..]).await.unwrap();
+        stream.write_all(&legacy_kick(message)).await.unwrap();
+        request
+    }
+
+    #[tokio::test]
+    async fn test_ping() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut decoder = FrameDecoder::new();
+
+            let handshake = read_frame(&mut stream, &mut decoder).await;
+            let request = read_frame(&mut stream, &mut decoder).await;
+            assert_eq!((request.id, request.body.len()), (0x00, 0));
+
+            let status = serde_json::from_str(
+                r#"{"version": {"name": "1.21", "protocol": 767}, "description": "hi"}"#,
+            )
+            .unwrap();
+            write_packet(&mut stream, &StatusResponse { status }).await;
+
+            let ping = read_frame(&mut stream, &mut decoder).await;
+            let time = ping
+                .decode::<PingRequest>(ProtocolVersion::LATEST)
+                .unwrap()
+                .time;
+            write_packet(&mut stream, &PongResponse { time }).await;
+
+            handshake
+                .decode::<Handshake>(ProtocolVersion::LATEST)
+                .unwrap()
+        });
+
+        let ping = ping("127.0.0.1", port).await.unwrap();
+        assert_eq!(ping.status.protocol_version(), Some(767));
+        assert_eq!(ping.status.description.to_plain(), "hi");
+
+        let handshake = server.await.unwrap();
+        assert_eq!(handshake.protocol_version, PROTOCOL_VERSION);
+        assert_eq!(handshake.server_address, "127.0.0.1");
+        assert_eq!(handshake.server_port, port);
+        assert_eq!(handshake.next_state, Intent::Status);
+    }
+
+    #[tokio::test]
+    async fn test_ping_legacy_fallback() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let server = tokio::spawn(async move {
+            let modern = serve_legacy(&listener, "").await;
+            let legacy = serve_legacy(
+                &listener,
+                &["§1", "78", "1.6.4", "A §aserver", "3", "20"].join("\0"),
+            )
+            .await;
+            (modern, legacy)
+        });
+
+        let ping = ping("127.0.0.1", port).await.unwrap();
+        let version = ping.status.version.unwrap();
+        assert_eq!((version.name.as_str(), version.protocol), ("1.6.4", 78));
+        let players = ping.status.players.unwrap();
+        assert_eq!((players.online, players.max), (3, 20));
+        assert_eq!(ping.status.description.to_plain(), "A §aserver");
+
+        let (modern, legacy) = server.await.unwrap();
+        assert_ne!(modern[0], 0xFE);
+        assert_eq!(legacy, legacy_request("127.0.0.1", port));
+    }
+
+    #[tokio::test]
+    async fn test_ping_legacy_after_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        // reads the modern handshake but never answers it, like a server
+        // waiting for the rest of a packet it doesn't know
+        let server = tokio::spawn(async move {
+            let (mut modern, _) = listener.accept().await.unwrap();
+            let mut buf = [0; 256];
+            assert_ne!(modern.read(&mut buf).await.unwrap(), 0);
+
+            serve_legacy(&listener, "A server§1§20").await
+        });
+
+        let timeout = Duration::from_millis(400);
+        let ping = ping_with_timeout("127.0.0.1", port, timeout).await.unwrap();
+        assert_eq!(ping.status.description.to_plain(), "A server");
+        let players = ping.status.players.unwrap();
+        assert_eq!((players.online, players.max), (1, 20));
+
+        assert_eq!(server.await.unwrap(), legacy_request("127.0.0.1", port));
+    }
+
+    #[tokio::test]
+    async fn test_ping_deadline() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        // accepts both attempts but answers neither
+        let server = tokio::spawn(async move {
+            let (_modern, _) = listener.accept().await.unwrap();
+            let (_legacy, _) = listener.accept().await.unwrap();
+            tokio::time::sleep(Duration::from_secs(60)).await;
+        });
+
+        let timeout = Duration::from_millis(200);
+        let start = Instant::now();
+        let err = ping_with_timeout("127.0.0.1", port, timeout)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PingError::Timeout(t) if t == timeout));
+        assert!(start.elapsed() < timeout * 2);
+        server.abort();
+    }
+
+    /// Answers the status request and then the ping with `pong(time)`.
+    async fn serve_status(listener: &TcpListener, pong: impl Fn(u64) -> u64) {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut decoder = FrameDecoder::new();
+
+        read_frame(&mut stream, &mut decoder).await;
+        read_frame(&mut stream, &mut decoder).await;
+        let status = serde_json::from_str(r#"{"description": "hi"}"#).unwrap();
+        write_packet(&mut stream, &StatusResponse { status }).await;
+
+        let ping = read_frame(&mut stream, &mut decoder).await;
+        let time = ping
+            .decode::<PingRequest>(ProtocolVersion::LATEST)
+            .unwrap()
+            .time;
+        write_packet(&mut stream, &PongResponse { time: pong(time) }).await;
+    }
+
+    #[tokio::test]
+    async fn test_ping_pong_mismatch() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move { serve_status(&listener, |time| time + 1).await });
+
+        let err = ping_modern("127.0.0.1", port, DEFAULT_TIMEOUT)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PingError::PongMismatch { sent, received } if received == sent + 1));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_ping_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        // accepts the connection but never answers
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            drop(stream);
+        });
+
+        let timeout = Duration::from_millis(100);
+        let err = ping_modern("127.0.0.1", port, timeout).await.unwrap_err();
+        assert!(matches!(err, PingError::Timeout(t) if t == timeout));
+        server.abort();
+    }
+
+    #[test]
+    fn test_legacy_request() {
+        let request = legacy_request("a", 25565);
+        assert_eq!(&request[..5], [0xFE, 0x01, 0xFA, 0x00, 0x0B]);
+        assert_eq!(
+            &request[27..],
+            [0x00, 0x09, 78, 0x00, 0x01, 0x00, b'a', 0x00, 0x00, 0x63, 0xDD]
+        );
+    }
+
+    #[test]
+    fn test_parse_legacy_status() {
+        // before 1.4, without a version and with formatting in the motd
+        let status = parse_legacy_status("§cOld §lserver§0§10").unwrap();
+        assert_eq!(status.version, None);
+        assert_eq!(status.description.to_plain(), "§cOld §lserver");
+        let players = status.players.unwrap();
+        assert_eq!((players.online, players.max), (0, 10));
+
+        assert!(parse_legacy_status(&["§1", "78", "1.6.4", "motd", "3"].join("\0")).is_err());
+        assert!(parse_legacy_status("motd§many§20").is_err());
+        assert!(parse_legacy_status("motd").is_err());
+    }
+}
@@ -0,0 +1,63 @@
+use syn::{
+    parse_quote, visit_mut::VisitMut, Error, Expr, ExprField, ExprPath, Ident, Member, Result,
+};
+
+/// Rewrites `self.field` in a condition into the local `field` is bound to,
+/// dereferencing it if `by_ref` as the fields are bound by reference while
+/// encoding. Only the fields in `earlier` may be referred to, as later ones
+/// haven't been decoded yet when the condition is checked.
+pub fn bind_fields(expr: &Expr, earlier: &[(Member, Ident)], by_ref: bool) -> Result<Expr> {
+    let mut binder = Binder {
+        earlier,
+        by_ref,
+        error: None,
+    };
+
+    let mut expr = expr.clone();
+    binder.visit_expr_mut(&mut expr);
+
+    match binder.error {
+        Some(err) => Err(err),
+        None => Ok(expr),
+    }
+}
+
+struct Binder<'a> {
+    earlier: &'a [(Member, Ident)],
+    by_ref: bool,
+    error: Option<Error>,
+}
+
+impl VisitMut for Binder<'_> {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        if let Expr::Field(ExprField { base, member, .. }) = expr {
+            if is_self(base) {
+                let binding = self
+                    .earlier
+                    .iter()
+                    .find(|(earlier, _)| earlier == member)
+                    .map(|(_, binding)| binding);
+
+                match binding {
+                    Some(binding) if self.by_ref => *expr = parse_quote!((*#binding)),
+                    Some(binding) => *expr = parse_quote!(#binding),
+                    None => {
+                        let err = Error::new_spanned(
+                            &*expr,
+                            "conditions can only refer to fields before the one they are on",
+                        );
+                        self.error.get_or_insert(err);
+                    }
+                }
+
+                return;
+            }
+        }
+
+        syn::visit_mut::visit_expr_mut(self, expr);
+    }
+}
+
+fn is_self(expr: &Expr) -> bool {
+    matches!(expr, Expr::Path(ExprPath { path, .. }) if path.is_ident("self"))
+}
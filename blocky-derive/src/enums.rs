@@ -0,0 +1,272 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    parse_quote, DataEnum, DeriveInput, Error, Expr, ExprLit, Lifetime, Lit, Meta, Result, Type,
+    Variant,
+};
+
+use crate::field::{FieldCodec, FieldList};
+
+/// How the variant of an enum is written before its fields, as set with
+/// `#[discriminant(...)]` on the enum.
+struct Discriminant {
+    /// The type the values of the variants have.
+    ty: Type,
+    codec: FieldCodec,
+    /// Whether the variants are told apart by a resource location such as
+    /// `minecraft:dust` rather than a number.
+    identifier: bool,
+}
+
+impl Discriminant {
+    fn from_input(input: &DeriveInput) -> Result<Self> {
+        let Some(attr) = input
+            .attrs
+            .iter()
+            .find(|attr| attr.path().is_ident("discriminant"))
+        else {
+            return Ok(Self::number(
+                parse_quote!(i32),
+                FieldCodec::with(quote!(blocky_net::codec::AsVarInt)),
+            ));
+        };
+
+        let ty: Type = attr.parse_args()?;
+        let Type::Path(path) = &ty else {
+            return Err(Error::new_spanned(ty, "expected a discriminant type"));
+        };
+
+        let ident = path.path.get_ident().map(|ident| ident.to_string());
+        Ok(match ident.as_deref() {
+            Some("VarInt") => Self::number(
+                parse_quote!(i32),
+                FieldCodec::with(quote!(blocky_net::codec::AsVarInt)),
+            ),
+            Some("VarLong") => Self::number(
+                parse_quote!(i64),
+                FieldCodec::with(quote!(blocky_net::codec::AsVarLong)),
+            ),
+            Some("identifier") => Self {
+                ty: parse_quote!(&str),
+                codec: FieldCodec::inherent(),
+                identifier: true,
+            },
+            _ => Self::number(ty, FieldCodec::inherent()),
+        })
+    }
+
+    fn number(ty: Type, codec: FieldCodec) -> Self {
+        Self {
+            ty,
+            codec,
+            identifier: false,
+        }
+    }
+}
+
+/// The enum being derived, each variant with the value of its discriminant.
+pub struct EnumCodec<'a> {
+    name: String,
+    discriminant: Discriminant,
+    variants: Vec<(&'a Variant, TokenStream, FieldList<'a>)>,
+}
+
+impl<'a> EnumCodec<'a> {
+    pub fn new(input: &'a DeriveInput, data: &'a DataEnum) -> Result<Self> {
+        if data.variants.is_empty() {
+            return Err(Error::new_spanned(
+                input,
+                "enums without variants cannot be encoded",
+            ));
+        }
+
+        let name = input.ident.to_string();
+        let discriminant = Discriminant::from_input(input)?;
+
+        let mut variants = Vec::with_capacity(data.variants.len());
+        let mut previous: Option<TokenStream> = None;
+
+        for variant in &data.variants {
+            let value = if discriminant.identifier {
+                let value = match variant_value(variant)? {
+                    Some(Expr::Lit(ExprLit {
+                        lit: Lit::Str(value),
+                        ..
+                    })) => value.value(),
+                    Some(value) => return Err(Error::new_spanned(value, "expected an identifier")),
+                    None => snake_case(&variant.ident.to_string()),
+                };
+
+                let value = if value.contains(':') {
+                    value
+                } else {
+                    format!("minecraft:{value}")
+                };
+                quote!(#value)
+            } else {
+                // like Rust, a variant without a value follows the one before it
+                let value = match variant_value(variant)? {
+                    Some(value) => quote!(#value),
+                    None => match &previous {
+                        Some(previous) => quote!(#previous + 1),
+                        None => quote!(0),
+                    },
+                };
+
+                previous = Some(value.clone());
+                value
+            };
+
+            let fields = FieldList::new(
+                &variant.fields,
+                format!("{name}::{}", variant.ident),
+                &variant.attrs,
+            )?;
+            variants.push((variant, value, fields));
+        }
+
+        Ok(Self {
+            name,
+            discriminant,
+            variants,
+        })
+    }
+
+    /// The statement binding the discriminant of a variant to `discriminant`.
+    fn bind_value(&self, value: &TokenStream) -> TokenStream {
+        let ty = &self.discriminant.ty;
+        quote!(let discriminant: #ty = #value;)
+    }
+
+    pub fn byte_len(&self) -> TokenStream {
+        let Discriminant { ty, codec, .. } = &self.discriminant;
+        let arms = self.variants.iter().map(|(variant, value, fields)| {
+            let ident = &variant.ident;
+            let pattern = fields.pattern(quote!(Self::#ident));
+            let bind = self.bind_value(value);
+            let discriminant_len = codec.byte_len(ty, &quote!(&discriminant));
+            let fields_len = fields.byte_len();
+
+            quote! {
+                #pattern => {
+                    #bind
+                    #discriminant_len + #fields_len
+                }
+            }
+        });
+
+        quote! {
+            match self {
+                #(#arms)*
+            }
+        }
+    }
+
+    /// The match encoding the variant of `self`, see [`FieldList::encode`]
+    /// for `versioned`.
+    pub fn encode(&self, versioned: bool) -> TokenStream {
+        let Discriminant { ty, codec, .. } = &self.discriminant;
+        let arms = self.variants.iter().map(|(variant, value, fields)| {
+            let ident = &variant.ident;
+            let pattern = fields.pattern(quote!(Self::#ident));
+            let bind = self.bind_value(value);
+            let encode_discriminant = codec.encode(ty, &quote!(&discriminant), false);
+            let encode_fields = fields.encode(versioned);
+
+            quote! {
+                #pattern => {
+                    #bind
+                    #encode_discriminant?;
+                    #encode_fields
+                }
+            }
+        });
+
+        quote! {
+            match self {
+                #(#arms)*
+            }
+            Ok(())
+        }
+    }
+
+    /// The match decoding whichever variant the discriminant says, see
+    /// [`FieldList::decode`] for `versioned`.
+    pub fn decode(&self, lifetime: Option<&Lifetime>, versioned: bool) -> TokenStream {
+        let name = &self.name;
+        let invalid = quote! {
+            discriminant => Err(blocky_net::error::ProtocolError::InvalidDiscriminant {
+                ty: #name,
+                value: discriminant.to_string(),
+            })
+        };
+
+        if self.discriminant.identifier {
+            let arms = self.variants.iter().map(|(variant, value, fields)| {
+                let ident = &variant.ident;
+                let construct = fields.decode(quote!(Self::#ident), lifetime, versioned);
+                quote!(#value => Ok(#construct),)
+            });
+
+            return quote! {
+                let discriminant = blocky_net::codec::decode_identifier(buf)?;
+                match discriminant.as_str() {
+                    #(#arms)*
+                    #invalid
+                }
+            };
+        }
+
+        let Discriminant { ty, codec, .. } = &self.discriminant;
+        let decode = codec.decode_owned(ty, lifetime.is_some());
+        let arms = self.variants.iter().map(|(variant, value, fields)| {
+            let ident = &variant.ident;
+            let construct = fields.decode(quote!(Self::#ident), lifetime, versioned);
+            quote!(discriminant if discriminant == #value => Ok(#construct),)
+        });
+
+        quote! {
+            let discriminant: #ty = #decode?;
+            match discriminant {
+                #(#arms)*
+                #invalid
+            }
+        }
+    }
+}
+
+/// The value of a variant from `#[discriminant = ...]` or `Variant = ...`.
+fn variant_value(variant: &Variant) -> Result<Option<Expr>> {
+    for attr in &variant.attrs {
+        if !attr.path().is_ident("discriminant") {
+            continue;
+        }
+
+        return match &attr.meta {
+            Meta::NameValue(meta) => Ok(Some(meta.value.clone())),
+            _ => Err(Error::new_spanned(attr, "expected `#[discriminant = ...]`")),
+        };
+    }
+
+    Ok(variant
+        .discriminant
+        .as_ref()
+        .map(|(_, value)| value.clone()))
+}
+
+fn snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i != 0 {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+
+    snake
+}
@@ -0,0 +1,678 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    parse_quote, Attribute, Error, Expr, ExprLit, Field, Fields, GenericArgument, Ident, Lifetime,
+    Lit, Member, Meta, PathArguments, Result, Type,
+};
+
+use crate::{condition, version};
+
+/// How a field is encoded, as parsed from its attributes.
+pub struct FieldCodec {
+    /// The type implementing `EncodeAs` and `DecodeAs` for the field, or
+    /// `None` when it uses its own `Encoder` and `Decoder`.
+    codec: Option<TokenStream>,
+    /// Whether the field takes every remaining byte.
+    rest: bool,
+}
+
+impl FieldCodec {
+    /// Encodes with the type's own `Encoder` and `Decoder`.
+    pub fn inherent() -> Self {
+        Self {
+            codec: None,
+            rest: false,
+        }
+    }
+
+    /// Encodes with the given codec type.
+    pub fn with(codec: TokenStream) -> Self {
+        Self {
+            codec: Some(codec),
+            rest: false,
+        }
+    }
+
+    /// Parses the codec attributes of a field. The codec of a conditional
+    /// field encodes the value inside its `Option`, as the condition takes the
+    /// place of the boolean an `Option` is usually prefixed with.
+    fn from_field(field: &Field, conditional: bool) -> Result<Self> {
+        let mut value: Option<(String, TokenStream)> = None;
+        let mut prefix: Option<Type> = None;
+        let mut rest = false;
+
+        for attr in &field.attrs {
+            let Some(ident) = attr.path().get_ident() else {
+                continue;
+            };
+
+            let name = ident.to_string();
+            let codec = match name.as_str() {
+                "varint" => {
+                    attr.meta.require_path_only()?;
+                    quote!(blocky_net::codec::AsVarInt)
+                }
+                "varlong" => {
+                    attr.meta.require_path_only()?;
+                    quote!(blocky_net::codec::AsVarLong)
+                }
+                "max_len" => {
+                    let max: Expr = match &attr.meta {
+                        Meta::NameValue(meta) => meta.value.clone(),
+                        _ => return Err(Error::new_spanned(attr, "expected `#[max_len = N]`")),
+                    };
+                    quote!(blocky_net::codec::MaxLen<{ #max }>)
+                }
+                "json" => {
+                    attr.meta.require_path_only()?;
+                    quote!(blocky_net::codec::Json)
+                }
+                "prefixed" => {
+                    if prefix.is_some() {
+                        return Err(Error::new_spanned(attr, "duplicate `prefixed` attribute"));
+                    }
+                    prefix = Some(attr.parse_args()?);
+                    continue;
+                }
+                "rest" => {
+                    attr.meta.require_path_only()?;
+                    rest = true;
+                    continue;
+                }
+                _ => continue,
+            };
+
+            if let Some((existing, _)) = &value {
+                return Err(Error::new_spanned(
+                    attr,
+                    format!("`{name}` cannot be combined with `{existing}`"),
+                ));
+            }
+
+            value = Some((name, codec));
+        }
+
+        if rest {
+            if value.is_some() || prefix.is_some() {
+                return Err(Error::new_spanned(
+                    field,
+                    "`rest` cannot be combined with other codec attributes",
+                ));
+            }
+
+            return Ok(Self {
+                codec: Some(quote!(blocky_net::codec::Rest)),
+                rest,
+            });
+        }
+
+        // attributes on an `Option` apply to the value inside it
+        let inner = generic_arg(&field.ty, "Option");
+        let ty = inner.unwrap_or(&field.ty);
+
+        let codec = match (prefix, value) {
+            (Some(prefix), None) if is_bytes(ty) => {
+                Some(quote!(blocky_net::codec::PrefixedBytes<#prefix>))
+            }
+            (Some(prefix), Some((_, element))) => {
+                Some(quote!(blocky_net::codec::Prefixed<#prefix, #element>))
+            }
+            (Some(prefix), None) => Some(quote!(blocky_net::codec::Prefixed<#prefix>)),
+            (None, value) => value.map(|(_, codec)| codec),
+        };
+
+        let codec = match (codec, inner) {
+            (Some(codec), Some(_)) if !conditional => {
+                Some(quote!(blocky_net::codec::Optional<#codec>))
+            }
+            (codec, _) => codec,
+        };
+
+        Ok(Self { codec, rest })
+    }
+
+    /// The expression for how many bytes `value` takes up.
+    pub fn byte_len(&self, ty: &Type, value: &TokenStream) -> TokenStream {
+        match &self.codec {
+            Some(codec) => quote!(<#codec as blocky_net::codec::EncodeAs<#ty>>::byte_len(#value)),
+            None => quote!(blocky_net::encoder::Encoder::byte_len(#value)),
+        }
+    }
+
+    /// The expression encoding `value` into `buf`, for the `__for_version` in
+    /// scope if `versioned` is set.
+    pub fn encode(&self, ty: &Type, value: &TokenStream, versioned: bool) -> TokenStream {
+        let (encode, version) = match versioned {
+            true => (quote!(encode_for), Some(quote!(__for_version,))),
+            false => (quote!(encode), None),
+        };
+
+        match &self.codec {
+            Some(codec) => {
+                quote!(<#codec as blocky_net::codec::EncodeAs<#ty>>::#encode(#value, #version buf))
+            }
+            None => quote!(blocky_net::encoder::Encoder::#encode(#value, #version buf)),
+        }
+    }
+
+    /// The expression decoding the field from `buf`, which is a slice the
+    /// field may borrow from if `lifetime` is given and a `CountingReader`
+    /// otherwise. A slice is decoded for the `__for_version` in scope if
+    /// `versioned` is set, while the reader carries its own version.
+    pub fn decode(&self, ty: &Type, lifetime: Option<&Lifetime>, versioned: bool) -> TokenStream {
+        let Some(lifetime) = lifetime else {
+            return self.decode_owned(ty, false);
+        };
+
+        // the rest of a slice can be borrowed rather than copied
+        if self.rest && matches!(ty, Type::Reference(_)) {
+            return quote!(blocky_net::borrowed::decode_rest(buf));
+        }
+
+        match (&self.codec, versioned) {
+            (Some(codec), true) => {
+                quote!(<#codec as blocky_net::codec::DecodeAs<#ty>>::decode_for(__for_version, buf))
+            }
+            (Some(_), false) => self.decode_owned(ty, true),
+            (None, true) => match generic_arg(ty, "Option") {
+                Some(inner) => quote! {
+                    blocky_net::borrowed::decode_option_for::<#inner>(__for_version, buf)
+                },
+                None => quote! {
+                    <#ty as blocky_net::borrowed::BorrowDecoder<#lifetime>>::decode_borrowed_for(__for_version, buf)
+                },
+            },
+            (None, false) => match generic_arg(ty, "Option") {
+                Some(inner) => quote! {
+                    blocky_net::borrowed::decode_option::<#inner>(buf)
+                },
+                None => quote! {
+                    <#ty as blocky_net::borrowed::BorrowDecoder<#lifetime>>::decode_borrowed(buf)
+                },
+            },
+        }
+    }
+
+    /// The expression decoding a value that doesn't borrow from `buf`, such
+    /// as a bitmask or discriminant, from either kind of `buf`.
+    pub fn decode_owned(&self, ty: &Type, borrowed: bool) -> TokenStream {
+        let decode = match borrowed {
+            true => quote!(decode),
+            false => quote!(decode_counted),
+        };
+
+        match &self.codec {
+            Some(codec) => quote!(<#codec as blocky_net::codec::DecodeAs<#ty>>::#decode(buf)),
+            None => quote!(<#ty as blocky_net::decoder::Decoder>::#decode(buf)),
+        }
+    }
+}
+
+/// The type a number given in an attribute, such as `#[bitmask(VarInt)]`, is
+/// held in and how it is encoded.
+pub fn number_codec(ty: Type) -> (Type, FieldCodec) {
+    let ident = match &ty {
+        Type::Path(path) => path.path.get_ident().map(|ident| ident.to_string()),
+        _ => None,
+    };
+
+    match ident.as_deref() {
+        Some("VarInt") => (
+            parse_quote!(i32),
+            FieldCodec::with(quote!(blocky_net::codec::AsVarInt)),
+        ),
+        Some("VarLong") => (
+            parse_quote!(i64),
+            FieldCodec::with(quote!(blocky_net::codec::AsVarLong)),
+        ),
+        _ => (ty, FieldCodec::inherent()),
+    }
+}
+
+/// When a field that is only there some of the time is present.
+enum Condition {
+    /// `#[present_if = "..."]`, an expression over the fields before it. It
+    /// is rewritten to use the locals the fields are bound to, which are
+    /// references while encoding and values while decoding.
+    PresentIf { encode: Expr, decode: Expr },
+    /// `#[versions(...)]`, the check whether `__version` is in its range.
+    /// `__version` is the `Option` of the version being encoded or decoded,
+    /// which the field fails without.
+    Versions(Expr),
+    /// `#[bit = N]`, a bit of the mask written before a `#[bitmask(...)]`
+    /// struct.
+    Bit(Expr),
+}
+
+impl Condition {
+    fn from_field(field: &Field) -> Result<Option<Self>> {
+        let mut condition = None;
+
+        for attr in &field.attrs {
+            let parsed = if attr.path().is_ident("present_if") {
+                let expr = attr_expr(attr)?;
+                Self::PresentIf {
+                    encode: expr.clone(),
+                    decode: expr,
+                }
+            } else if attr.path().is_ident("bit") {
+                Self::Bit(attr_expr(attr)?)
+            } else if attr.path().is_ident("versions") {
+                Self::Versions(version::contains(attr, parse_quote!(__version))?)
+            } else {
+                continue;
+            };
+
+            if condition.replace(parsed).is_some() {
+                return Err(Error::new_spanned(
+                    attr,
+                    "a field can only have one of `present_if`, `bit` and `versions`",
+                ));
+            }
+        }
+
+        Ok(condition)
+    }
+}
+
+/// The value of a `#[name = value]` attribute, parsing it as an expression if
+/// it is a string.
+fn attr_expr(attr: &Attribute) -> Result<Expr> {
+    let Meta::NameValue(meta) = &attr.meta else {
+        return Err(Error::new_spanned(attr, "expected a value"));
+    };
+
+    match &meta.value {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(value),
+            ..
+        }) => value.parse(),
+        value => Ok(value.clone()),
+    }
+}
+
+/// A single field of a struct or enum variant.
+struct FieldInfo<'a> {
+    field: &'a Field,
+    /// The local variable the field is bound to.
+    binding: Ident,
+    codec: FieldCodec,
+    /// The type the codec works on, the value inside the `Option` of a
+    /// conditional field.
+    ty: Type,
+    condition: Option<Condition>,
+}
+
+/// The fields of a struct or enum variant along with how each is encoded,
+/// generating code that works on the fields bound to local variables.
+pub struct FieldList<'a> {
+    fields: &'a Fields,
+    infos: Vec<FieldInfo<'a>>,
+    /// The name errors are reported under, such as `Handshake` or
+    /// `Particle::Dust`.
+    name: String,
+    /// The type of the mask written before the fields of a
+    /// `#[bitmask(...)]` struct, saying which `#[bit = N]` fields follow.
+    bitmask: Option<(Type, FieldCodec)>,
+    /// Whether this is a `#[transparent]` newtype, encoded exactly like its
+    /// only field with errors reported as if they happened in it.
+    transparent: bool,
+}
+
+impl<'a> FieldList<'a> {
+    /// Parses the codec of every field, making sure `#[rest]` is only used
+    /// on the last one. `attrs` are those of the struct or variant itself.
+    pub fn new(fields: &'a Fields, name: String, attrs: &[Attribute]) -> Result<Self> {
+        let bitmask = match attrs.iter().find(|attr| attr.path().is_ident("bitmask")) {
+            Some(attr) => Some(number_codec(attr.parse_args()?)),
+            None => None,
+        };
+
+        let transparent = match attrs
+            .iter()
+            .find(|attr| attr.path().is_ident("transparent"))
+        {
+            Some(attr) => {
+                attr.meta.require_path_only()?;
+                if fields.len() != 1 || bitmask.is_some() {
+                    return Err(Error::new_spanned(
+                        attr,
+                        "`transparent` can only be used on a struct with a single field",
+                    ));
+                }
+                true
+            }
+            None => false,
+        };
+
+        let mut infos: Vec<FieldInfo> = Vec::with_capacity(fields.len());
+
+        for (i, field) in fields.iter().enumerate() {
+            let binding = match &field.ident {
+                Some(ident) => ident.clone(),
+                None => format_ident!("__field{}", i),
+            };
+
+            let mut condition = Condition::from_field(field)?;
+            let codec = FieldCodec::from_field(field, condition.is_some())?;
+
+            if codec.rest && i != fields.len() - 1 {
+                return Err(Error::new_spanned(
+                    field,
+                    "`rest` can only be used on the last field",
+                ));
+            }
+
+            let ty = match &condition {
+                Some(_) => match generic_arg(&field.ty, "Option") {
+                    Some(inner) => inner.clone(),
+                    None => {
+                        return Err(Error::new_spanned(
+                            &field.ty,
+                            "conditional fields must be an `Option`",
+                        ))
+                    }
+                },
+                None => field.ty.clone(),
+            };
+
+            match &mut condition {
+                Some(Condition::PresentIf { encode, decode }) => {
+                    let earlier = infos
+                        .iter()
+                        .enumerate()
+                        .map(|(j, info)| (member(info.field, j), info.binding.clone()))
+                        .collect::<Vec<_>>();
+
+                    *encode = condition::bind_fields(encode, &earlier, true)?;
+                    *decode = condition::bind_fields(decode, &earlier, false)?;
+                }
+                Some(Condition::Bit(_)) if bitmask.is_none() => {
+                    return Err(Error::new_spanned(
+                        field,
+                        "`bit` can only be used in a struct or variant with `#[bitmask(...)]`",
+                    ));
+                }
+                _ => {}
+            }
+
+            infos.push(FieldInfo {
+                field,
+                binding,
+                codec,
+                ty,
+                condition,
+            });
+        }
+
+        Ok(Self {
+            fields,
+            infos,
+            name,
+            bitmask,
+            transparent,
+        })
+    }
+
+    /// The pattern binding every field of `path` by reference, which is also
+    /// the expression constructing it from the bound fields.
+    pub fn pattern(&self, path: TokenStream) -> TokenStream {
+        let bindings = self.infos.iter().map(|info| &info.binding);
+
+        match self.fields {
+            Fields::Named(_) => quote!(#path { #(#bindings),* }),
+            Fields::Unnamed(_) => quote!(#path(#(#bindings),*)),
+            Fields::Unit => path,
+        }
+    }
+
+    /// The statement computing the mask of a `#[bitmask(...)]` struct from
+    /// which of its `#[bit = N]` fields are set.
+    fn mask(&self) -> Option<TokenStream> {
+        let (ty, _) = self.bitmask.as_ref()?;
+        let bits = self.infos.iter().filter_map(|info| match &info.condition {
+            Some(Condition::Bit(bit)) => {
+                let binding = &info.binding;
+                Some(quote!(| if #binding.is_some() { 1 << (#bit) } else { 0 }))
+            }
+            _ => None,
+        });
+
+        Some(quote!(let __mask: #ty = 0 #(#bits)*;))
+    }
+
+    /// The expression summing up the length of the bound fields.
+    pub fn byte_len(&self) -> TokenStream {
+        let byte_lens = self.infos.iter().map(|info| {
+            let binding = &info.binding;
+            match &info.condition {
+                Some(_) => {
+                    let len = info.codec.byte_len(&info.ty, &quote!(value));
+                    quote!(#binding.as_ref().map_or(0, |value| #len))
+                }
+                None => info.codec.byte_len(&info.ty, &quote!(#binding)),
+            }
+        });
+
+        let mask = self.mask();
+        let mask_len = self
+            .bitmask
+            .as_ref()
+            .map(|(ty, codec)| codec.byte_len(ty, &quote!(&__mask)))
+            .map(|len| quote!(#len +));
+
+        quote! {{
+            #mask
+            #mask_len #(#byte_lens +)* 0
+        }}
+    }
+
+    /// The statements encoding the bound fields into `buf`, for the
+    /// `__for_version` in scope if `versioned` is set. `__version` has to be bound
+    /// to the same version, or `None` without one.
+    pub fn encode(&self, versioned: bool) -> TokenStream {
+        let encodes = self.infos.iter().enumerate().map(|(i, info)| {
+            let context = self.context(info.field, i);
+            let binding = &info.binding;
+
+            match &info.condition {
+                Some(Condition::PresentIf {
+                    encode: condition, ..
+                }) => {
+                    let encode = info.codec.encode(&info.ty, &quote!(value), versioned);
+
+                    quote! {
+                        match (#condition, #binding) {
+                            (true, Some(value)) => #encode,
+                            (false, None) => Ok(()),
+                            _ => Err(blocky_net::error::ProtocolError::PresenceMismatch),
+                        }
+                        #context?;
+                    }
+                }
+                Some(Condition::Versions(condition)) => {
+                    let encode = info.codec.encode(&info.ty, &quote!(value), versioned);
+
+                    quote! {
+                        match (__version.map(|__version| #condition), #binding) {
+                            (Some(true), Some(value)) => #encode,
+                            (Some(false), None) => Ok(()),
+                            (None, _) => Err(blocky_net::error::ProtocolError::VersionRequired),
+                            _ => Err(blocky_net::error::ProtocolError::PresenceMismatch),
+                        }
+                        #context?;
+                    }
+                }
+                Some(Condition::Bit(_)) => {
+                    let encode = info.codec.encode(&info.ty, &quote!(value), versioned);
+                    quote! {
+                        if let Some(value) = #binding {
+                            #encode #context?;
+                        }
+                    }
+                }
+                None => {
+                    let encode = info.codec.encode(&info.ty, &quote!(#binding), versioned);
+                    quote! {
+                        #encode #context?;
+                    }
+                }
+            }
+        });
+
+        let mask = self.bitmask.as_ref().map(|(ty, codec)| {
+            let mask = self.mask();
+            let encode = codec.encode(ty, &quote!(&__mask), false);
+            quote! {
+                #mask
+                #encode?;
+            }
+        });
+
+        quote! {
+            #mask
+            #(#encodes)*
+        }
+    }
+
+    /// The call adding the field to the path of errors from it, unless this
+    /// is a transparent newtype.
+    fn context(&self, field: &Field, index: usize) -> Option<TokenStream> {
+        if self.transparent {
+            return None;
+        }
+
+        let name = &self.name;
+        let field_str = field_name(field, index);
+        Some(quote!(.map_err(|e| e.in_field(#name, #field_str))))
+    }
+
+    /// The expression decoding a field, adding the field and the offset it
+    /// starts at to errors from it, unless this is a transparent newtype.
+    fn decode_in_context(
+        &self,
+        decode: TokenStream,
+        field: &Field,
+        index: usize,
+        lifetime: Option<&Lifetime>,
+    ) -> TokenStream {
+        if self.transparent {
+            return quote!(#decode?);
+        }
+
+        // see `decoder_impl` for how `buf` is counted
+        let position = match lifetime {
+            Some(_) => quote!(__start - buf.len()),
+            None => quote!(buf.position() - __start),
+        };
+
+        let name = &self.name;
+        let field_str = field_name(field, index);
+        quote! {{
+            let __offset = #position;
+            #decode.map_err(|e| e.in_field(#name, #field_str).at_offset(__offset))?
+        }}
+    }
+
+    /// The expression constructing `path` from fields decoded from `buf`,
+    /// see [`FieldCodec::decode`] for `versioned`. `__version` has to be
+    /// bound to the version being decoded, or `None` without one.
+    pub fn decode(
+        &self,
+        path: TokenStream,
+        lifetime: Option<&Lifetime>,
+        versioned: bool,
+    ) -> TokenStream {
+        let decodes = self.infos.iter().enumerate().map(|(i, info)| {
+            let binding = &info.binding;
+            let field_ty = &info.field.ty;
+            let decode = info.codec.decode(&info.ty, lifetime, versioned);
+            let decode = self.decode_in_context(decode, info.field, i, lifetime);
+
+            let condition = match &info.condition {
+                Some(Condition::PresentIf {
+                    decode: condition, ..
+                }) => quote!(#condition),
+                Some(Condition::Versions(condition)) => {
+                    let name = &self.name;
+                    let field_str = field_name(info.field, i);
+
+                    return quote! {
+                        let #binding: #field_ty = match __version.map(|__version| #condition) {
+                            Some(true) => Some(#decode),
+                            Some(false) => None,
+                            None => {
+                                return Err(blocky_net::error::ProtocolError::VersionRequired
+                                    .in_field(#name, #field_str))
+                            }
+                        };
+                    };
+                }
+                Some(Condition::Bit(bit)) => quote!(__mask & (1 << (#bit)) != 0),
+                None => return quote!(let #binding: #field_ty = #decode;),
+            };
+
+            quote! {
+                let #binding: #field_ty = if #condition {
+                    Some(#decode)
+                } else {
+                    None
+                };
+            }
+        });
+
+        let mask = self.bitmask.as_ref().map(|(ty, codec)| {
+            let decode = codec.decode_owned(ty, lifetime.is_some());
+            quote!(let __mask: #ty = #decode?;)
+        });
+
+        let construct = self.pattern(path);
+        quote! {{
+            #mask
+            #(#decodes)*
+            #construct
+        }}
+    }
+}
+
+/// How `self` refers to a field, by name or by index.
+fn member(field: &Field, index: usize) -> Member {
+    match &field.ident {
+        Some(ident) => Member::Named(ident.clone()),
+        None => Member::Unnamed(index.into()),
+    }
+}
+
+/// The name of a field as it shows up in error paths, its index if it has
+/// no name.
+fn field_name(field: &Field, index: usize) -> String {
+    match &field.ident {
+        Some(ident) => ident.to_string(),
+        None => index.to_string(),
+    }
+}
+
+/// The type argument of `ty` if it is the generic type `name`, such as the
+/// `T` of `Option<T>`.
+fn generic_arg<'a>(ty: &'a Type, name: &str) -> Option<&'a Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+
+    let segment = path.path.segments.last()?;
+    if segment.ident != name {
+        return None;
+    }
+
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first()? {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn is_bytes(ty: &Type) -> bool {
+    matches!(generic_arg(ty, "Vec"), Some(Type::Path(path)) if path.path.is_ident("u8"))
+}
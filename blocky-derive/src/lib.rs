@@ -1,8 +1,49 @@
+mod condition;
+mod enums;
+mod field;
+mod version;
+
+use std::collections::HashMap;
+
+use darling::FromDeriveInput;
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, Data, DeriveInput, Error};
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Error, Generics, Ident};
 
-#[proc_macro_derive(Packet)]
+use crate::{enums::EnumCodec, field::FieldList};
+
+#[derive(FromDeriveInput)]
+#[darling(attributes(packet))]
+struct PacketOpts {
+    id: i32,
+    state: Ident,
+    flow: Ident,
+    #[darling(default)]
+    next_state: Option<Ident>,
+    /// The ids the packet has in versions where it differs from `id`, keyed
+    /// by the name of the version.
+    #[darling(default)]
+    ids: HashMap<Ident, i32>,
+}
+
+#[proc_macro_derive(
+    Packet,
+    attributes(
+        packet,
+        discriminant,
+        bitmask,
+        transparent,
+        bit,
+        present_if,
+        varint,
+        varlong,
+        prefixed,
+        max_len,
+        rest,
+        json,
+        versions
+    )
+)]
 pub fn packet_derive(input: TokenStream) -> TokenStream {
     let encoder_stream = proc_macro2::TokenStream::from(encoder_derive(input.clone()));
     let decoder_stream = proc_macro2::TokenStream::from(decoder_derive(input.clone()));
@@ -11,84 +52,311 @@ pub fn packet_derive(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let name = &input.ident;
 
+    if input.generics.lifetimes().next().is_some() {
+        return Error::new_spanned(
+            &input.generics,
+            "Packet cannot be derived for borrowed structs, derive Encoder and Decoder instead",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    let opts = match PacketOpts::from_derive_input(&input) {
+        Ok(opts) => opts,
+        Err(err) => return err.write_errors().into(),
+    };
+
+    let id = opts.id;
+    let state = opts.state;
+    let flow = opts.flow;
+
+    // the packet only exists in the versions of `#[versions(...)]` on it
+    let in_versions = match version::find(&input.attrs) {
+        Some(attr) => match version::contains(attr, parse_quote!(version)) {
+            Ok(contains) => Some(quote! {
+                if !#contains {
+                    return None;
+                }
+            }),
+            Err(err) => return err.to_compile_error().into(),
+        },
+        None => None,
+    };
+
+    let mut ids = opts.ids.into_iter().collect::<Vec<_>>();
+    ids.sort_by_key(|(version, _)| version.to_string());
+    let ids = ids.into_iter().map(|(version, id)| {
+        quote! {
+            if version == blocky_net::version::ProtocolVersion::#version {
+                return Some(#id);
+            }
+        }
+    });
+
+    let next_state = opts.next_state.map(|next_state| {
+        quote! {
+            fn next_state(&self) -> Option<blocky_net::packet::ConnectionState> {
+                Some(blocky_net::packet::ConnectionState::#next_state)
+            }
+        }
+    });
+
+    let generics = add_bounds(
+        &input.generics,
+        quote!(blocky_net::encoder::Encoder + blocky_net::decoder::Decoder),
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
     // Generate code based on the packet id and flow
     let expanded = quote! {
         #encoder_stream
         #decoder_stream
 
-        impl blocky_net::packet::Packet for #name {}
+        impl #impl_generics blocky_net::packet::Packet for #name #ty_generics #where_clause {
+            const ID: i32 = #id;
+            const STATE: blocky_net::packet::ConnectionState = blocky_net::packet::ConnectionState::#state;
+            const FLOW: blocky_net::packet::PacketFlow = blocky_net::packet::PacketFlow::#flow;
+
+            fn id_for(version: blocky_net::version::ProtocolVersion) -> Option<i32> {
+                #in_versions
+                #(#ids)*
+                Some(#id)
+            }
+
+            #next_state
+        }
     };
 
     // Convert the quote into a TokenStream
     TokenStream::from(expanded)
 }
 
-#[proc_macro_derive(Encoder)]
+#[proc_macro_derive(
+    Encoder,
+    attributes(
+        discriminant,
+        bitmask,
+        transparent,
+        bit,
+        present_if,
+        varint,
+        varlong,
+        prefixed,
+        max_len,
+        rest,
+        json,
+        versions
+    )
+)]
 pub fn encoder_derive(input: TokenStream) -> TokenStream {
     // Parse the input token stream as a syntax tree
     let input = parse_macro_input!(input as DeriveInput);
 
+    match encoder_impl(&input) {
+        Ok(expanded) => expanded.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn encoder_impl(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
     let name = &input.ident;
 
-    let fields = match &input.data {
-        Data::Struct(data_struct) => &data_struct.fields,
-        _ => {
-            return Error::new_spanned(&input, "Encoder derive only supports structs")
-                .to_compile_error()
-                .into()
+    let (byte_len, encode, encode_for) = match &input.data {
+        Data::Struct(data_struct) => {
+            let fields = FieldList::new(&data_struct.fields, name.to_string(), &input.attrs)?;
+            let pattern = fields.pattern(quote!(Self));
+            let byte_len = fields.byte_len();
+            let [encode, encode_for] = [false, true].map(|versioned| {
+                let encode = fields.encode(versioned);
+                quote! {
+                    let #pattern = self;
+                    #encode
+                    Ok(())
+                }
+            });
+
+            (
+                quote! {
+                    let #pattern = self;
+                    #byte_len
+                },
+                encode,
+                encode_for,
+            )
+        }
+        Data::Enum(data_enum) => {
+            let variants = EnumCodec::new(input, data_enum)?;
+            (
+                variants.byte_len(),
+                variants.encode(false),
+                variants.encode(true),
+            )
+        }
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                input,
+                "Encoder derive does not support unions",
+            ))
         }
     };
 
-    let field_names = fields.iter().map(|f| &f.ident).collect::<Vec<_>>();
+    let generics = add_bounds(&input.generics, quote!(blocky_net::encoder::Encoder));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
-    // Generate code based on the packet id and flow
-    let expanded = quote! {
-        impl blocky_net::encoder::Encoder for #name {
+    Ok(quote! {
+        impl #impl_generics blocky_net::encoder::Encoder for #name #ty_generics #where_clause {
             fn byte_len(&self) -> usize {
-                #(self.#field_names.byte_len() +)*
-                0
+                #byte_len
             }
 
-            fn encode<T: std::io::Write>(&self, buf: &mut T) -> anyhow::Result<()> {
-                #(self.#field_names.encode(buf)?;)*
-                Ok(())
+            fn encode<__W: std::io::Write>(&self, buf: &mut __W) -> blocky_net::error::Result<()> {
+                let __version: Option<blocky_net::version::ProtocolVersion> = None;
+                #encode
             }
-        }
-    };
 
-    // Convert the quote into a TokenStream
-    TokenStream::from(expanded)
+            fn encode_for<__W: std::io::Write>(
+                &self,
+                __for_version: blocky_net::version::ProtocolVersion,
+                buf: &mut __W,
+            ) -> blocky_net::error::Result<()> {
+                let __version = Some(__for_version);
+                #encode_for
+            }
+        }
+    })
 }
 
-#[proc_macro_derive(Decoder)]
+#[proc_macro_derive(
+    Decoder,
+    attributes(
+        discriminant,
+        bitmask,
+        transparent,
+        bit,
+        present_if,
+        varint,
+        varlong,
+        prefixed,
+        max_len,
+        rest,
+        json,
+        versions
+    )
+)]
 pub fn decoder_derive(input: TokenStream) -> TokenStream {
     // Parse the input token stream as a syntax tree
     let input = parse_macro_input!(input as DeriveInput);
 
+    match decoder_impl(&input) {
+        Ok(expanded) => expanded.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn decoder_impl(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
     let name = &input.ident;
 
-    let fields = match &input.data {
-        Data::Struct(data_struct) => &data_struct.fields,
-        _ => {
-            return Error::new_spanned(&input, "Decoder derive only supports structs")
-                .to_compile_error()
-                .into()
+    // a struct borrowing from its input can only be decoded from a slice
+    let mut lifetimes = input.generics.lifetimes();
+    let lifetime = lifetimes.next().map(|param| &param.lifetime);
+    if let Some(extra) = lifetimes.next() {
+        return Err(Error::new_spanned(
+            extra,
+            "Decoder can only be derived for structs with at most one lifetime",
+        ));
+    }
+
+    // slices are decoded for a version passed along with them, while the
+    // counting reader carries its own, so only slices need a second body
+    let [decode, decode_for] = match &input.data {
+        Data::Struct(data_struct) => {
+            let fields = FieldList::new(&data_struct.fields, name.to_string(), &input.attrs)?;
+            [false, true].map(|versioned| {
+                let construct = fields.decode(quote!(Self), lifetime, versioned);
+                quote!(Ok(#construct))
+            })
+        }
+        Data::Enum(data_enum) => {
+            let variants = EnumCodec::new(input, data_enum)?;
+            [false, true].map(|versioned| variants.decode(lifetime, versioned))
+        }
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                input,
+                "Decoder derive does not support unions",
+            ))
         }
     };
 
-    let field_names = fields.iter().map(|f| &f.ident).collect::<Vec<_>>();
+    if let Some(lifetime) = lifetime {
+        let generics = add_bounds(
+            &input.generics,
+            quote!(blocky_net::borrowed::BorrowDecoder<#lifetime>),
+        );
+        let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
-    // Generate code based on the packet id and flow
-    let expanded = quote! {
-        impl blocky_net::decoder::Decoder for #name {
-            fn decode<T: std::io::Read>(buf: &mut T) -> anyhow::Result<Self> {
-                Ok(Self {
-                    #(#field_names: blocky_net::decoder::Decoder::decode(buf)?,)*
-                })
+        // the offsets of fields are counted from how much of the slice is left
+        return Ok(quote! {
+            impl #impl_generics blocky_net::borrowed::BorrowDecoder<#lifetime> for #name #ty_generics #where_clause {
+                fn decode_borrowed(buf: &mut &#lifetime [u8]) -> blocky_net::error::Result<Self> {
+                    let __start = buf.len();
+                    let __version: Option<blocky_net::version::ProtocolVersion> = None;
+                    #decode
+                }
+
+                fn decode_borrowed_for(
+                    __for_version: blocky_net::version::ProtocolVersion,
+                    buf: &mut &#lifetime [u8],
+                ) -> blocky_net::error::Result<Self> {
+                    let __start = buf.len();
+                    let __version = Some(__for_version);
+                    #decode_for
+                }
+            }
+        });
+    }
+
+    let generics = add_bounds(&input.generics, quote!(blocky_net::decoder::Decoder));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    // the offsets of fields are counted by a reader wrapping `buf`, made
+    // once by the outermost struct and handed down to the structs inside it
+    Ok(quote! {
+        impl #impl_generics blocky_net::decoder::Decoder for #name #ty_generics #where_clause {
+            fn decode<__R: std::io::Read>(buf: &mut __R) -> blocky_net::error::Result<Self> {
+                Self::decode_counted(&mut blocky_net::decoder::CountingReader::new(buf))
+            }
+
+            fn decode_for<__R: std::io::Read>(
+                __for_version: blocky_net::version::ProtocolVersion,
+                buf: &mut __R,
+            ) -> blocky_net::error::Result<Self> {
+                Self::decode_counted(&mut blocky_net::decoder::CountingReader::for_version(buf, __for_version))
+            }
+
+            fn decode_counted<__R: std::io::Read>(
+                buf: &mut blocky_net::decoder::CountingReader<__R>,
+            ) -> blocky_net::error::Result<Self> {
+                let __start = buf.position();
+                let __version = buf.version();
+                #decode
             }
         }
-    };
+    })
+}
 
-    // Convert the quote into a TokenStream
-    TokenStream::from(expanded)
+/// Requires every type parameter of `generics` to implement `bound`.
+fn add_bounds(generics: &Generics, bound: proc_macro2::TokenStream) -> Generics {
+    let mut generics = generics.clone();
+    let params = generics
+        .type_params()
+        .map(|param| param.ident.clone())
+        .collect::<Vec<_>>();
+
+    let where_clause = generics.make_where_clause();
+    for param in params {
+        where_clause.predicates.push(parse_quote!(#param: #bound));
+    }
+
+    generics
 }
@@ -0,0 +1,37 @@
+use syn::{parse_quote, Attribute, Error, Expr, ExprLit, ExprPath, ExprRange, Lit, Result};
+
+/// The expression checking whether `version` is in the range of a
+/// `#[versions(...)]` attribute, such as `V1_20_6..` or `..V1_21`. The ends
+/// of the range are the names of `ProtocolVersion` constants or protocol
+/// numbers.
+pub fn contains(attr: &Attribute, version: Expr) -> Result<Expr> {
+    let mut range: ExprRange = attr.parse_args()?;
+
+    for end in [&mut range.start, &mut range.end].into_iter().flatten() {
+        **end = version_expr(end)?;
+    }
+
+    Ok(parse_quote!((#range).contains(&#version)))
+}
+
+fn version_expr(expr: &Expr) -> Result<Expr> {
+    match expr {
+        Expr::Path(ExprPath { path, .. }) => match path.get_ident() {
+            Some(ident) => Ok(parse_quote!(blocky_net::version::ProtocolVersion::#ident)),
+            None => Ok(expr.clone()),
+        },
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(protocol),
+            ..
+        }) => Ok(parse_quote!(blocky_net::version::ProtocolVersion(#protocol))),
+        _ => Err(Error::new_spanned(
+            expr,
+            "expected a version such as `V1_20_6` or a protocol number",
+        )),
+    }
+}
+
+/// Finds the `#[versions(...)]` attribute in `attrs`, if there is one.
+pub fn find(attrs: &[Attribute]) -> Option<&Attribute> {
+    attrs.iter().find(|attr| attr.path().is_ident("versions"))
+}
@@ -0,0 +1,424 @@
+use std::io::{BufRead, Read, Write};
+
+use flate2::{
+    bufread::{GzDecoder, ZlibDecoder},
+    write::GzEncoder,
+    Compression,
+};
+
+use crate::{NbtCompound, NbtError, NbtList, NbtTag, Result};
+
+/// How deeply lists and compounds may be nested, the same limit the game
+/// uses.
+pub const MAX_DEPTH: usize = 512;
+
+/// Reads a named tag, the form the root of an NBT file is in.
+pub fn read_named<R: Read>(reader: &mut R) -> Result<(String, NbtTag)> {
+    let id = read_u8(reader)?;
+    if id == 0 {
+        return Err(NbtError::UnexpectedEnd);
+    }
+
+    let name = read_string(reader)?;
+    let tag = read_payload(id, reader, 0)?;
+    Ok((name, tag))
+}
+
+/// Writes a named tag, the form the root of an NBT file is in.
+pub fn write_named<W: Write>(writer: &mut W, name: &str, tag: &NbtTag) -> Result<()> {
+    writer.write_all(&[tag.id()])?;
+    write_string(writer, name)?;
+    write_payload(writer, tag)
+}
+
+/// Reads a named tag from a file that may be gzip or zlib compressed, as
+/// `level.dat` and player data are.
+pub fn read_file<R: BufRead>(mut reader: R) -> Result<(String, NbtTag)> {
+    match reader.fill_buf()? {
+        [0x1F, 0x8B, ..] => read_named(&mut GzDecoder::new(reader)),
+        [0x78, ..] => read_named(&mut ZlibDecoder::new(reader)),
+        _ => read_named(&mut reader),
+    }
+}
+
+/// Writes a named tag compressed with gzip, the way the game saves files
+/// such as `level.dat`.
+pub fn write_file<W: Write>(writer: W, name: &str, tag: &NbtTag) -> Result<()> {
+    let mut encoder = GzEncoder::new(writer, Compression::default());
+    write_named(&mut encoder, name, tag)?;
+    encoder.finish()?;
+    Ok(())
+}
+
+/// Reads the payload of a tag with the given id, `depth` being how many
+/// lists and compounds it is inside of.
+pub(crate) fn read_payload<R: Read>(id: u8, reader: &mut R, depth: usize) -> Result<NbtTag> {
+    if depth > MAX_DEPTH {
+        return Err(NbtError::TooDeep);
+    }
+
+    // Only lists and compounds recurse, so everything else is read in a
+    // function of its own to keep the frames of the recursion small, as
+    // otherwise debug builds run out of stack well before `MAX_DEPTH`.
+    match id {
+        9 => read_list(reader, depth).map(NbtTag::List),
+        10 => read_compound(reader, depth).map(NbtTag::Compound),
+        id => read_value(id, reader),
+    }
+}
+
+fn read_list<R: Read>(reader: &mut R, depth: usize) -> Result<NbtList> {
+    let element = read_u8(reader)?;
+    let len = read_length(reader)?;
+    if element == 0 && len > 0 {
+        return Err(NbtError::EndList(len));
+    }
+
+    let mut list = NbtList::new();
+    for _ in 0..len {
+        list.push(read_payload(element, reader, depth + 1)?);
+    }
+
+    Ok(list)
+}
+
+pub(crate) fn read_compound<R: Read>(reader: &mut R, depth: usize) -> Result<NbtCompound> {
+    let mut compound = NbtCompound::new();
+    loop {
+        let id = read_u8(reader)?;
+        if id == 0 {
+            return Ok(compound);
+        }
+
+        let name = read_string(reader)?;
+        let tag = read_payload(id, reader, depth + 1)?;
+        compound.insert(name, tag);
+    }
+}
+
+fn read_value<R: Read>(id: u8, reader: &mut R) -> Result<NbtTag> {
+    Ok(match id {
+        1 => NbtTag::Byte(read_u8(reader)? as i8),
+        2 => NbtTag::Short(i16::from_be_bytes(read_array(reader)?)),
+        3 => NbtTag::Int(i32::from_be_bytes(read_array(reader)?)),
+        4 => NbtTag::Long(i64::from_be_bytes(read_array(reader)?)),
+        5 => NbtTag::Float(f32::from_be_bytes(read_array(reader)?)),
+        6 => NbtTag::Double(f64::from_be_bytes(read_array(reader)?)),
+        7 => {
+            let len = read_length(reader)?;
+            let bytes = read_bytes(reader, len)?;
+            NbtTag::ByteArray(bytes.into_iter().map(|byte| byte as i8).collect())
+        }
+        8 => NbtTag::String(read_string(reader)?),
+        11 => {
+            let len = read_length(reader)?;
+            NbtTag::IntArray(read_numbers(reader, len, i32::from_be_bytes)?)
+        }
+        12 => {
+            let len = read_length(reader)?;
+            NbtTag::LongArray(read_numbers(reader, len, i64::from_be_bytes)?)
+        }
+        id => return Err(NbtError::UnknownTag(id)),
+    })
+}
+
+/// Writes the payload of `tag`, everything after its id and name.
+pub(crate) fn write_payload<W: Write>(writer: &mut W, tag: &NbtTag) -> Result<()> {
+    match tag {
+        NbtTag::Byte(value) => writer.write_all(&value.to_be_bytes())?,
+        NbtTag::Short(value) => writer.write_all(&value.to_be_bytes())?,
+        NbtTag::Int(value) => writer.write_all(&value.to_be_bytes())?,
+        NbtTag::Long(value) => writer.write_all(&value.to_be_bytes())?,
+        NbtTag::Float(value) => writer.write_all(&value.to_be_bytes())?,
+        NbtTag::Double(value) => writer.write_all(&value.to_be_bytes())?,
+        NbtTag::ByteArray(bytes) => {
+            write_length(writer, bytes.len())?;
+            let bytes = bytes.iter().map(|&byte| byte as u8).collect::<Vec<_>>();
+            writer.write_all(&bytes)?;
+        }
+        NbtTag::String(s) => write_string(writer, s)?,
+        NbtTag::List(list) => {
+            // an empty list is written as a list of TAG_End
+            let id = list.first().map_or(0, NbtTag::id);
+            if let Some(other) = list.iter().find(|tag| tag.id() != id) {
+                return Err(NbtError::MixedList {
+                    first: list[0].pretty_name(),
+                    other: other.pretty_name(),
+                });
+            }
+
+            writer.write_all(&[id])?;
+            write_length(writer, list.len())?;
+            for tag in list.iter() {
+                write_payload(writer, tag)?;
+            }
+        }
+        NbtTag::Compound(compound) => write_compound(writer, compound)?,
+        NbtTag::IntArray(values) => {
+            write_length(writer, values.len())?;
+            for value in values {
+                writer.write_all(&value.to_be_bytes())?;
+            }
+        }
+        NbtTag::LongArray(values) => {
+            write_length(writer, values.len())?;
+            for value in values {
+                writer.write_all(&value.to_be_bytes())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes the entries of `compound` followed by the `TAG_End` closing it.
+pub(crate) fn write_compound<W: Write>(writer: &mut W, compound: &NbtCompound) -> Result<()> {
+    for (name, tag) in compound.iter() {
+        writer.write_all(&[tag.id()])?;
+        write_string(writer, name)?;
+        write_payload(writer, tag)?;
+    }
+
+    writer.write_all(&[0])?;
+    Ok(())
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> Result<u8> {
+    Ok(read_array::<R, 1>(reader)?[0])
+}
+
+fn read_array<R: Read, const N: usize>(reader: &mut R) -> Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_length<R: Read>(reader: &mut R) -> Result<usize> {
+    let len = i32::from_be_bytes(read_array(reader)?);
+    usize::try_from(len).map_err(|_| NbtError::NegativeLength(len))
+}
+
+/// Reads exactly `len` bytes, growing the buffer only as they arrive so a
+/// bogus length can't make it allocate up front.
+fn read_bytes<R: Read>(reader: &mut R, len: usize) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    if reader.take(len as u64).read_to_end(&mut bytes)? != len {
+        return Err(NbtError::UnexpectedEof);
+    }
+
+    Ok(bytes)
+}
+
+/// Reads `len` numbers of `N` bytes each, without trusting `len` for the
+/// initial allocation either.
+fn read_numbers<R: Read, T, const N: usize>(
+    reader: &mut R,
+    len: usize,
+    from_bytes: fn([u8; N]) -> T,
+) -> Result<Vec<T>> {
+    let mut values = Vec::with_capacity(len.min(1024));
+    for _ in 0..len {
+        values.push(from_bytes(read_array(reader)?));
+    }
+
+    Ok(values)
+}
+
+fn read_string<R: Read>(reader: &mut R) -> Result<String> {
+    let len = u16::from_be_bytes(read_array(reader)?);
+    let bytes = read_bytes(reader, len as usize)?;
+
+    simd_cesu8::mutf8::decode(&bytes)
+        .map(|s| s.into_owned())
+        .map_err(|_| NbtError::InvalidString)
+}
+
+fn write_length<W: Write>(writer: &mut W, len: usize) -> Result<()> {
+    let len = i32::try_from(len).map_err(|_| NbtError::LengthTooLong(len))?;
+    writer.write_all(&len.to_be_bytes())?;
+    Ok(())
+}
+
+fn write_string<W: Write>(writer: &mut W, s: &str) -> Result<()> {
+    let bytes = simd_cesu8::mutf8::encode(s);
+    let len = u16::try_from(bytes.len()).map_err(|_| NbtError::LengthTooLong(bytes.len()))?;
+
+    writer.write_all(&len.to_be_bytes())?;
+    writer.write_all(&bytes)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use flate2::read::GzDecoder;
+
+    use super::*;
+
+    // Synthetic files written by `tests/fixtures/generate.py` in the layout
+    // of a 1.21 world's files, not saves made by the game.
+    const LEVEL: &[u8] = include_bytes!("../tests/fixtures/synthetic_level.dat");
+    const PLAYER: &[u8] = include_bytes!("../tests/fixtures/synthetic_player.dat");
+
+    fn gunzip(bytes: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        GzDecoder::new(bytes).read_to_end(&mut out).unwrap();
+        out
+    }
+
+    fn compound(tag: &NbtTag) -> &NbtCompound {
+        match tag {
+            NbtTag::Compound(compound) => compound,
+            tag => panic!("expected a compound, found {}", tag.pretty_name()),
+        }
+    }
+
+    #[test]
+    fn test_read_level() {
+        let (name, tag) = read_file(LEVEL).unwrap();
+        assert_eq!(name, "");
+
+        let data = compound(&compound(&tag)["Data"]);
+        assert_eq!(data["DataVersion"], NbtTag::Int(3953));
+        assert_eq!(data["LevelName"], NbtTag::from("New World"));
+        assert_eq!(data["ScheduledEvents"], NbtTag::List(NbtList::new()));
+
+        let version = compound(&data["Version"]);
+        assert_eq!(version["Name"], NbtTag::from("1.21"));
+        assert_eq!(version["Snapshot"], NbtTag::Byte(0));
+
+        let settings = compound(&data["WorldGenSettings"]);
+        assert_eq!(settings["seed"], NbtTag::Long(-4172144997902289642));
+    }
+
+    #[test]
+    fn test_read_player() {
+        let (_, tag) = read_file(PLAYER).unwrap();
+        let player = compound(&tag);
+
+        assert_eq!(
+            player["Pos"],
+            NbtTag::List(vec![8.5.into(), 64.0.into(), (-3.25).into()].into())
+        );
+        assert_eq!(
+            player["UUID"],
+            NbtTag::IntArray(vec![-1436914047, 1262961372, -1659706045, 1164578213])
+        );
+
+        let NbtTag::List(inventory) = &player["Inventory"] else {
+            panic!("expected a list");
+        };
+        let components = compound(&compound(&inventory[0])["components"]);
+        assert_eq!(
+            components["minecraft:custom_name"],
+            NbtTag::from(r#"{"text":"Épée 😀"}"#)
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for file in [LEVEL, PLAYER] {
+            let (name, tag) = read_file(file).unwrap();
+
+            let mut bytes = Vec::new();
+            write_named(&mut bytes, &name, &tag).unwrap();
+            // without preserve_order the keys come back out in another order
+            #[cfg(feature = "preserve_order")]
+            assert_eq!(bytes, gunzip(file));
+
+            assert_eq!(read_named(&mut bytes.as_slice()).unwrap(), (name, tag));
+        }
+    }
+
+    #[test]
+    fn test_write_file() {
+        let (name, tag) = read_file(LEVEL).unwrap();
+
+        let mut bytes = Vec::new();
+        write_file(&mut bytes, &name, &tag).unwrap();
+        assert_eq!(&bytes[..2], [0x1F, 0x8B]);
+        assert_eq!(read_file(bytes.as_slice()).unwrap(), (name, tag));
+    }
+
+    #[test]
+    fn test_read_uncompressed() {
+        let bytes = gunzip(PLAYER);
+        assert_eq!(
+            read_file(bytes.as_slice()).unwrap(),
+            read_file(PLAYER).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_mutf8() {
+        let mut bytes = Vec::new();
+        write_named(&mut bytes, "\0", &"😀".into()).unwrap();
+        assert_eq!(
+            bytes,
+            [8, 0, 2, 0xC0, 0x80, 0, 6, 0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80]
+        );
+
+        let (name, tag) = read_named(&mut bytes.as_slice()).unwrap();
+        assert_eq!(name, "\0");
+        assert_eq!(tag, NbtTag::from("😀"));
+
+        let err = read_named(&mut [8, 0, 0, 0, 1, 0xFF].as_slice()).unwrap_err();
+        assert!(matches!(err, NbtError::InvalidString));
+    }
+
+    #[test]
+    fn test_too_deep() {
+        // lists of lists, one more level than allowed
+        let mut bytes = vec![9, 0, 0];
+        for _ in 0..=MAX_DEPTH {
+            bytes.extend([9, 0, 0, 0, 1]);
+        }
+        bytes.extend([0, 0, 0, 0, 0]);
+
+        let err = read_named(&mut bytes.as_slice()).unwrap_err();
+        assert!(matches!(err, NbtError::TooDeep));
+
+        // and compounds of compounds
+        let mut bytes = vec![10, 0, 0];
+        for _ in 0..=MAX_DEPTH {
+            bytes.extend([10, 0, 0]);
+        }
+
+        let err = read_named(&mut bytes.as_slice()).unwrap_err();
+        assert!(matches!(err, NbtError::TooDeep));
+    }
+
+    #[test]
+    fn test_invalid() {
+        let read = |bytes: &[u8]| read_named(&mut &bytes[..]).unwrap_err();
+
+        assert!(matches!(read(&[0]), NbtError::UnexpectedEnd));
+        assert!(matches!(read(&[13, 0, 0]), NbtError::UnknownTag(13)));
+        assert!(matches!(read(&[3, 0, 0, 0, 0]), NbtError::UnexpectedEof));
+        assert!(matches!(
+            read(&[7, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF]),
+            NbtError::NegativeLength(-1)
+        ));
+        assert!(matches!(
+            read(&[9, 0, 0, 0, 0, 0, 0, 2]),
+            NbtError::EndList(2)
+        ));
+        // a huge length with nothing behind it
+        assert!(matches!(
+            read(&[11, 0, 0, 0x7F, 0xFF, 0xFF, 0xFF]),
+            NbtError::UnexpectedEof
+        ));
+    }
+
+    #[test]
+    fn test_mixed_list() {
+        let list = NbtTag::List(vec![1i32.into(), "two".into()].into());
+        let err = write_named(&mut Vec::new(), "", &list).unwrap_err();
+        assert!(matches!(
+            err,
+            NbtError::MixedList {
+                first: "TAG_Int",
+                other: "TAG_String"
+            }
+        ));
+    }
+}
@@ -0,0 +1,485 @@
+//! Turning NBT into Rust values with `serde`, the reverse of the mapping
+//! described in the `ser` module. Lists and all three kinds of array can be
+//! read into sequences, and keys missing from a compound are read as `None`.
+
+use serde::{
+    de::{
+        self,
+        value::{MapDeserializer, SeqDeserializer, StringDeserializer},
+        DeserializeOwned, EnumAccess, IntoDeserializer, VariantAccess, Visitor,
+    },
+    forward_to_deserialize_any,
+};
+
+use crate::{read_named, NbtError, NbtTag, Result};
+
+/// Reads a `T` out of `tag`.
+pub fn from_nbt<T: DeserializeOwned>(tag: impl Into<NbtTag>) -> Result<T> {
+    T::deserialize(tag.into())
+}
+
+/// Reads a `T` out of an uncompressed NBT file, ignoring the name of its
+/// root tag.
+pub fn from_bytes<T: DeserializeOwned>(mut bytes: &[u8]) -> Result<T> {
+    let (_, tag) = read_named(&mut bytes)?;
+    if !bytes.is_empty() {
+        return Err(NbtError::TrailingBytes(bytes.len()));
+    }
+
+    from_nbt(tag)
+}
+
+impl<'de> IntoDeserializer<'de, NbtError> for NbtTag {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// Has `visitor` visit the elements of a list or array, checking that it
+/// took all of them.
+fn visit_seq<'de, V, I>(elements: I, visitor: V) -> Result<V::Value>
+where
+    V: Visitor<'de>,
+    I: Iterator,
+    I::Item: IntoDeserializer<'de, NbtError>,
+{
+    let mut seq = SeqDeserializer::new(elements);
+    let value = visitor.visit_seq(&mut seq)?;
+    seq.end()?;
+    Ok(value)
+}
+
+impl<'de> de::Deserializer<'de> for NbtTag {
+    type Error = NbtError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Self::Byte(value) => visitor.visit_i8(value),
+            Self::Short(value) => visitor.visit_i16(value),
+            Self::Int(value) => visitor.visit_i32(value),
+            Self::Long(value) => visitor.visit_i64(value),
+            Self::Float(value) => visitor.visit_f32(value),
+            Self::Double(value) => visitor.visit_f64(value),
+            // the elements of arrays go through the tag so they can be read
+            // as booleans and unsigned numbers like lone tags can
+            Self::ByteArray(values) => visit_seq(values.into_iter().map(Self::Byte), visitor),
+            Self::String(s) => visitor.visit_string(s),
+            Self::List(list) => visit_seq(list.into_iter(), visitor),
+            Self::Compound(compound) => {
+                let mut map = MapDeserializer::new(compound.into_iter());
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+            Self::IntArray(values) => visit_seq(values.into_iter().map(Self::Int), visitor),
+            Self::LongArray(values) => visit_seq(values.into_iter().map(Self::Long), visitor),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Self::Byte(value) => visitor.visit_bool(value != 0),
+            tag => tag.deserialize_any(visitor),
+        }
+    }
+
+    // unsigned numbers are stored in the signed tag of the same size
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Self::Byte(value) => visitor.visit_u8(value as u8),
+            tag => tag.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Self::Short(value) => visitor.visit_u16(value as u16),
+            tag => tag.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Self::Int(value) => visitor.visit_u32(value as u32),
+            tag => tag.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Self::Long(value) => visitor.visit_u64(value as u64),
+            tag => tag.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Self::ByteArray(values) => {
+                visitor.visit_byte_buf(values.into_iter().map(|byte| byte as u8).collect())
+            }
+            tag => tag.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        // a tag that is there at all is Some, absent keys are None
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self {
+            Self::String(variant) => visitor.visit_enum(variant.into_deserializer()),
+            Self::Compound(compound) if compound.len() == 1 => {
+                let (variant, value) = compound.into_iter().next().expect("has one entry");
+                visitor.visit_enum(Enum { variant, value })
+            }
+            tag => Err(de::Error::invalid_type(
+                unexpected(&tag),
+                &"a string or a compound with one entry",
+            )),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u128 f32 f64 char str string
+        seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// How `tag` is described in errors about it being the wrong type.
+fn unexpected(tag: &NbtTag) -> de::Unexpected<'_> {
+    match tag {
+        NbtTag::Byte(value) => de::Unexpected::Signed(*value as i64),
+        NbtTag::Short(value) => de::Unexpected::Signed(*value as i64),
+        NbtTag::Int(value) => de::Unexpected::Signed(*value as i64),
+        NbtTag::Long(value) => de::Unexpected::Signed(*value),
+        NbtTag::Float(value) => de::Unexpected::Float(*value as f64),
+        NbtTag::Double(value) => de::Unexpected::Float(*value),
+        NbtTag::String(s) => de::Unexpected::Str(s),
+        NbtTag::ByteArray(_) | NbtTag::List(_) | NbtTag::IntArray(_) | NbtTag::LongArray(_) => {
+            de::Unexpected::Seq
+        }
+        NbtTag::Compound(_) => de::Unexpected::Map,
+    }
+}
+
+/// An enum variant with a value, written as a compound of the variant's
+/// name to the value.
+struct Enum {
+    variant: String,
+    value: NbtTag,
+}
+
+impl<'de> EnumAccess<'de> for Enum {
+    type Error = NbtError;
+    type Variant = Variant;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Variant)> {
+        let variant: StringDeserializer<NbtError> = self.variant.into_deserializer();
+        Ok((seed.deserialize(variant)?, Variant(self.value)))
+    }
+}
+
+/// The value of an enum variant.
+struct Variant(NbtTag);
+
+impl<'de> VariantAccess<'de> for Variant {
+    type Error = NbtError;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self.0)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_seq(self.0, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_map(self.0, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::{read_file, to_bytes, to_nbt, NbtCompound};
+
+    #[derive(Debug, Deserialize)]
+    struct Level {
+        #[serde(rename = "Data")]
+        data: LevelData,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct LevelData {
+        data_version: i32,
+        level_name: String,
+        version: Version,
+        game_rules: HashMap<String, String>,
+        world_gen_settings: WorldGenSettings,
+        data_packs: DataPacks,
+        player: Player,
+        #[serde(rename = "hardcore")]
+        hardcore: bool,
+        /// Only there once a wandering trader has spawned.
+        wandering_trader_id: Option<[i32; 4]>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct Version {
+        id: i32,
+        name: String,
+        snapshot: bool,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct WorldGenSettings {
+        seed: i64,
+        bonus_chest: bool,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct DataPacks {
+        enabled: Vec<String>,
+        disabled: Vec<String>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct Player {
+        pos: [f64; 3],
+        rotation: Vec<f32>,
+        #[serde(rename = "UUID")]
+        uuid: [i32; 4],
+        inventory: Vec<Item>,
+        ender_items: Vec<Item>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Item {
+        #[serde(rename = "Slot")]
+        slot: i8,
+        id: String,
+        count: i32,
+        #[serde(default)]
+        components: HashMap<String, Component>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(untagged)]
+    enum Component {
+        Int(i32),
+        String(String),
+    }
+
+    #[test]
+    fn test_level() {
+        let level = include_bytes!("../tests/fixtures/synthetic_level.dat");
+        let (_, tag) = read_file(&level[..]).unwrap();
+
+        let data = from_nbt::<Level>(tag).unwrap().data;
+        assert_eq!(data.data_version, 3953);
+        assert_eq!(data.level_name, "New World");
+        assert_eq!(
+            (data.version.id, data.version.name.as_str()),
+            (3953, "1.21")
+        );
+        assert!(!data.version.snapshot);
+        assert_eq!(data.game_rules["randomTickSpeed"], "3");
+        assert_eq!(data.world_gen_settings.seed, -4172144997902289642);
+        assert!(!data.world_gen_settings.bonus_chest);
+        assert_eq!(data.data_packs.enabled, ["vanilla"]);
+        assert_eq!(data.data_packs.disabled.len(), 2);
+        assert!(!data.hardcore);
+        assert_eq!(data.wandering_trader_id, None);
+
+        let player = data.player;
+        assert_eq!(player.pos, [8.5, 64.0, -3.25]);
+        assert_eq!(player.rotation, [-90.0, 12.5]);
+        assert_eq!(player.uuid[0], -1436914047);
+        assert!(player.ender_items.is_empty());
+
+        let sword = &player.inventory[0];
+        assert_eq!((sword.slot, sword.count), (0, 1));
+        assert_eq!(sword.id, "minecraft:diamond_sword");
+        assert_eq!(sword.components["minecraft:damage"], Component::Int(12));
+        assert!(player.inventory[1].components.is_empty());
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Everything {
+        byte: i8,
+        unsigned: u32,
+        flag: bool,
+        float: f32,
+        text: String,
+        bytes: Vec<i8>,
+        longs: Vec<i64>,
+        doubles: Vec<f64>,
+        nested: Vec<Vec<i32>>,
+        map: HashMap<String, i16>,
+        missing: Option<String>,
+        present: Option<String>,
+        unit: Shape,
+        newtype: Shape,
+        tuple: Shape,
+        structure: Shape,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Shape {
+        Point,
+        Circle(f64),
+        Line(i32, i32),
+        Rect { width: u16, height: u16 },
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let value = Everything {
+            byte: -5,
+            unsigned: u32::MAX,
+            flag: true,
+            float: 0.25,
+            text: "a\0😀".to_string(),
+            bytes: vec![1, -1],
+            longs: vec![i64::MIN],
+            doubles: vec![1.5],
+            nested: vec![vec![1], vec![]],
+            map: HashMap::from([("a".to_string(), 1)]),
+            missing: None,
+            present: Some("here".to_string()),
+            unit: Shape::Point,
+            newtype: Shape::Circle(2.0),
+            tuple: Shape::Line(1, 2),
+            structure: Shape::Rect {
+                width: 3,
+                height: 65535,
+            },
+        };
+
+        let tag = to_nbt(&value).unwrap();
+        assert_eq!(from_nbt::<Everything>(tag).unwrap(), value);
+
+        let bytes = to_bytes(&value).unwrap();
+        assert_eq!(from_bytes::<Everything>(&bytes).unwrap(), value);
+    }
+
+    #[test]
+    fn test_lists_and_arrays() {
+        // both lists and arrays of numbers read into vectors
+        let list = NbtTag::List(vec![1i32.into(), 2i32.into()].into());
+        assert_eq!(from_nbt::<Vec<i32>>(list).unwrap(), [1, 2]);
+        assert_eq!(
+            from_nbt::<Vec<i64>>(NbtTag::IntArray(vec![1, 2])).unwrap(),
+            [1, 2]
+        );
+        assert!(from_nbt::<[i32; 3]>(NbtTag::IntArray(vec![1, 2])).is_err());
+    }
+
+    #[test]
+    fn test_unsigned_arrays() {
+        // these are written as arrays, whose elements have to be read back
+        // the same way lone bytes, ints and longs are
+        let bytes = vec![0u8, 200, u8::MAX];
+        let tag = to_nbt(&bytes).unwrap();
+        assert_eq!(tag, NbtTag::ByteArray(vec![0, -56, -1]));
+        assert_eq!(from_nbt::<Vec<u8>>(tag).unwrap(), bytes);
+
+        let flags = vec![true, false, true];
+        let tag = to_nbt(&flags).unwrap();
+        assert_eq!(tag, NbtTag::ByteArray(vec![1, 0, 1]));
+        assert_eq!(from_nbt::<Vec<bool>>(tag).unwrap(), flags);
+
+        let ints = vec![1u32, 1 << 31, u32::MAX];
+        let tag = to_nbt(&ints).unwrap();
+        assert_eq!(tag, NbtTag::IntArray(vec![1, i32::MIN, -1]));
+        assert_eq!(from_nbt::<Vec<u32>>(tag).unwrap(), ints);
+
+        let longs = vec![u64::MAX];
+        assert_eq!(
+            from_nbt::<Vec<u64>>(to_nbt(&longs).unwrap()).unwrap(),
+            longs
+        );
+
+        let file = to_bytes(&HashMap::from([("uuid".to_string(), ints.clone())])).unwrap();
+        let read = from_bytes::<HashMap<String, Vec<u32>>>(&file).unwrap();
+        assert_eq!(read["uuid"], ints);
+    }
+
+    #[test]
+    fn test_invalid() {
+        #[derive(Debug, Deserialize)]
+        struct Named {
+            #[allow(dead_code)]
+            name: String,
+        }
+
+        let compound: NbtCompound = [("name".to_string(), NbtTag::Int(1))].into_iter().collect();
+        let err = from_nbt::<Named>(compound).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid type: integer `1`, expected a string"
+        );
+
+        let err = from_nbt::<Named>(NbtCompound::new()).unwrap_err();
+        assert_eq!(err.to_string(), "missing field `name`");
+
+        assert!(from_nbt::<bool>(NbtTag::from("yes")).is_err());
+        assert!(from_nbt::<Shape>(NbtTag::Int(1)).is_err());
+
+        let mut bytes = to_bytes(&HashMap::<String, i8>::new()).unwrap();
+        bytes.push(0);
+        assert!(matches!(
+            from_bytes::<HashMap<String, i8>>(&bytes),
+            Err(NbtError::TrailingBytes(1))
+        ));
+    }
+}
@@ -0,0 +1,119 @@
+use crate::binary::MAX_DEPTH;
+
+pub type Result<T> = std::result::Result<T, NbtError>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum NbtError {
+    /// The input ended before the tag was fully read.
+    #[error("unexpected end of input")]
+    UnexpectedEof,
+    #[error("I/O error: {0}")]
+    Io(std::io::Error),
+    #[error("unknown tag id {0}")]
+    UnknownTag(u8),
+    #[error("expected a named tag but found TAG_End")]
+    UnexpectedEnd,
+    #[error("negative length {0}")]
+    NegativeLength(i32),
+    #[error("length {0} is too long to be written")]
+    LengthTooLong(usize),
+    #[error("tags are nested more than {MAX_DEPTH} levels deep")]
+    TooDeep,
+    #[error("string is not valid modified UTF-8")]
+    InvalidString,
+    #[error("list has both {first} and {other} elements")]
+    MixedList {
+        first: &'static str,
+        other: &'static str,
+    },
+    #[error("list of TAG_End has {0} elements")]
+    EndList(usize),
+    #[error("{0} bytes were left after the root tag")]
+    TrailingBytes(usize),
+    #[error("{0}")]
+    Custom(String),
+}
+
+#[cfg(feature = "serde")]
+impl serde::ser::Error for NbtError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Self::Custom(msg.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::de::Error for NbtError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Self::Custom(msg.to_string())
+    }
+}
+
+impl From<std::io::Error> for NbtError {
+    fn from(value: std::io::Error) -> Self {
+        match value.kind() {
+            std::io::ErrorKind::UnexpectedEof => Self::UnexpectedEof,
+            _ => Self::Io(value),
+        }
+    }
+}
+
+/// An error parsing SNBT, with where in the input it happened.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{kind} at line {line}, column {column}")]
+pub struct SnbtError {
+    pub kind: SnbtErrorKind,
+    /// The byte offset into the input.
+    pub offset: usize,
+    /// The line, counting from 1.
+    pub line: usize,
+    /// The character within the line, counting from 1.
+    pub column: usize,
+}
+
+impl SnbtError {
+    pub(crate) fn new(kind: SnbtErrorKind, input: &str, offset: usize) -> Self {
+        let before = &input[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+        Self {
+            kind,
+            offset,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SnbtErrorKind {
+    #[error("expected {expected} but found {}", describe(.found))]
+    Expected {
+        expected: &'static str,
+        found: Option<char>,
+    },
+    #[error("{0} is out of range for its type")]
+    OutOfRange(String),
+    #[error("invalid escape sequence \\{0}")]
+    InvalidEscape(char),
+    #[error("invalid array type {0:?}")]
+    InvalidArrayType(char),
+    #[error("list has both {first} and {other} elements")]
+    MixedList {
+        first: &'static str,
+        other: &'static str,
+    },
+    #[error("{array} can't hold a {element}")]
+    ArrayElement {
+        array: &'static str,
+        element: &'static str,
+    },
+    #[error("tags are nested more than {MAX_DEPTH} levels deep")]
+    TooDeep,
+}
+
+fn describe(found: &Option<char>) -> String {
+    match found {
+        Some(c) => format!("{c:?}"),
+        None => "the end of the input".to_string(),
+    }
+}
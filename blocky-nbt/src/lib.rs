@@ -1,3 +1,20 @@
+mod binary;
+#[cfg(feature = "serde")]
+mod de;
+mod error;
+#[cfg(feature = "net")]
+mod net;
+#[cfg(feature = "serde")]
+mod ser;
+mod snbt;
 mod tag;
 
+pub use binary::*;
+#[cfg(feature = "serde")]
+pub use de::*;
+pub use error::*;
+#[cfg(feature = "net")]
+pub use net::*;
+#[cfg(feature = "serde")]
+pub use ser::*;
 pub use tag::*;
@@ -0,0 +1,191 @@
+//! NBT the way the protocol sends it, which since 1.20.2 leaves out the name
+//! of the root tag.
+
+use std::io::{Read, Write};
+
+use blocky_net::{
+    decoder::Decoder,
+    encoder::Encoder,
+    error::{ProtocolError, Result},
+};
+
+use crate::{
+    binary::{read_compound, read_payload, write_compound, write_payload},
+    NbtCompound, NbtError, NbtTag,
+};
+
+impl From<NbtError> for ProtocolError {
+    fn from(value: NbtError) -> Self {
+        match value {
+            NbtError::UnexpectedEof => Self::UnexpectedEof,
+            NbtError::Io(err) => Self::Io(err),
+            NbtError::NegativeLength(len) => Self::NegativeLength(len.into()),
+            err => Self::custom(format!("invalid NBT: {err}")),
+        }
+    }
+}
+
+impl Encoder for NbtTag {
+    fn byte_len(&self) -> usize {
+        1 + NbtTag::byte_len(self)
+    }
+
+    fn encode<W: Write>(&self, buf: &mut W) -> Result<()> {
+        self.id().encode(buf)?;
+        Ok(write_payload(buf, self)?)
+    }
+}
+
+impl Decoder for NbtTag {
+    fn decode<R: Read>(buf: &mut R) -> Result<Self> {
+        match u8::decode(buf)? {
+            0 => Err(ProtocolError::custom(
+                "expected an NBT tag but found TAG_End",
+            )),
+            id => Ok(read_payload(id, buf, 0)?),
+        }
+    }
+}
+
+impl Encoder for NbtCompound {
+    fn byte_len(&self) -> usize {
+        1 + NbtCompound::byte_len(self)
+    }
+
+    fn encode<W: Write>(&self, buf: &mut W) -> Result<()> {
+        10u8.encode(buf)?;
+        Ok(write_compound(buf, self)?)
+    }
+}
+
+impl Decoder for NbtCompound {
+    fn decode<R: Read>(buf: &mut R) -> Result<Self> {
+        match u8::decode(buf)? {
+            10 => Ok(read_compound(buf, 0)?),
+            0 => Err(ProtocolError::custom(
+                "expected TAG_Compound but found TAG_End",
+            )),
+            id => {
+                let tag = read_payload(id, buf, 0)?;
+                Err(ProtocolError::custom(format!(
+                    "expected TAG_Compound but found {}",
+                    tag.pretty_name()
+                )))
+            }
+        }
+    }
+}
+
+/// An NBT value the protocol may leave out, sending a lone `TAG_End` in its
+/// place, as it does for the data of block entities. `Option` can't be used
+/// for these as it is prefixed with a bool instead.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct OptionalNbt<T = NbtCompound>(pub Option<T>);
+
+impl<T> From<Option<T>> for OptionalNbt<T> {
+    fn from(value: Option<T>) -> Self {
+        Self(value)
+    }
+}
+
+impl<T: Encoder> Encoder for OptionalNbt<T> {
+    fn byte_len(&self) -> usize {
+        self.0.as_ref().map_or(1, T::byte_len)
+    }
+
+    fn encode<W: Write>(&self, buf: &mut W) -> Result<()> {
+        match &self.0 {
+            Some(value) => value.encode(buf),
+            None => 0u8.encode(buf),
+        }
+    }
+}
+
+impl<T: Decoder> Decoder for OptionalNbt<T> {
+    fn decode<R: Read>(buf: &mut R) -> Result<Self> {
+        let id = u8::decode(buf)?;
+        if id == 0 {
+            return Ok(Self(None));
+        }
+
+        // hand the id back to the value, which reads it as part of itself
+        T::decode(&mut [id].as_slice().chain(buf)).map(|value| Self(Some(value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compound() -> NbtCompound {
+        [
+            ("name".to_string(), NbtTag::from("a\0😀")),
+            (
+                "list".to_string(),
+                NbtTag::List(vec![1i32.into(), 2i32.into()].into()),
+            ),
+            ("bytes".to_string(), NbtTag::ByteArray(vec![-1, 2])),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    #[test]
+    fn test_tag_round_trip() {
+        let tag = NbtTag::Compound(compound());
+
+        let bytes = tag.to_bytes().unwrap();
+        assert_eq!(bytes.len(), Encoder::byte_len(&tag));
+        // no name follows the id of the root
+        assert_eq!(bytes[..4], [0x0A, 0x08, 0x00, 0x04]);
+        assert_eq!(NbtTag::from_bytes(&bytes).unwrap(), tag);
+
+        let tag = NbtTag::from("hi");
+        assert_eq!(tag.to_bytes().unwrap(), [0x08, 0x00, 0x02, b'h', b'i']);
+    }
+
+    #[test]
+    fn test_compound_round_trip() {
+        let compound = compound();
+
+        let bytes = compound.to_bytes().unwrap();
+        assert_eq!(bytes.len(), Encoder::byte_len(&compound));
+        assert_eq!(
+            bytes,
+            NbtTag::Compound(compound.clone()).to_bytes().unwrap()
+        );
+        assert_eq!(NbtCompound::from_bytes(&bytes).unwrap(), compound);
+    }
+
+    #[test]
+    fn test_optional() {
+        let none = OptionalNbt::<NbtCompound>(None);
+        assert_eq!(none.to_bytes().unwrap(), [0x00]);
+        assert_eq!(
+            OptionalNbt::<NbtCompound>::from_bytes(&[0x00]).unwrap(),
+            none
+        );
+
+        let some = OptionalNbt(Some(compound()));
+        let bytes = some.to_bytes().unwrap();
+        assert_eq!(bytes.len(), some.byte_len());
+        assert_eq!(OptionalNbt::from_bytes(&bytes).unwrap(), some);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(NbtTag::from_bytes(&[0x00]).is_err());
+        assert!(NbtCompound::from_bytes(&[0x08, 0x00, 0x00])
+            .unwrap_err()
+            .to_string()
+            .contains("found TAG_String"));
+        assert!(NbtTag::from_bytes(&[0x03, 0x00]).is_err_and(|e| e.is_unexpected_eof()));
+        assert!(matches!(
+            NbtTag::from_bytes(&[0x07, 0xFF, 0xFF, 0xFF, 0xFF])
+                .unwrap_err()
+                .root(),
+            ProtocolError::NegativeLength(-1)
+        ));
+        assert!(NbtTag::from_bytes(&[0x0D]).is_err());
+    }
+}
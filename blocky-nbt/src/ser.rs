@@ -0,0 +1,736 @@
+//! Turning Rust values into NBT with `serde`.
+//!
+//! Structs and maps become compounds and `None` fields are left out of
+//! them. Sequences become lists, except that a sequence of only bytes, ints
+//! or longs becomes the matching array, which is how the game stores values
+//! such as UUIDs, and an empty `Vec` of them is an empty array. Booleans are
+//! bytes, unsigned numbers are stored in the signed tag of the same size and
+//! enums are tagged the way `serde_json` tags them: unit variants as a string
+//! of their name and the others as a compound of their name to their value.
+
+use serde::{
+    ser::{self, Impossible},
+    Serialize,
+};
+
+use crate::{write_named, NbtCompound, NbtError, NbtList, NbtTag, Result};
+
+/// Turns `value` into a tag.
+pub fn to_nbt<T: Serialize + ?Sized>(value: &T) -> Result<NbtTag> {
+    value
+        .serialize(Serializer)?
+        .ok_or_else(|| NbtError::Custom("a value of None can't be written as NBT".to_string()))
+}
+
+/// Writes `value` as an uncompressed NBT file with a nameless root tag.
+pub fn to_bytes<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+    let tag = to_nbt(value)?;
+
+    let mut bytes = Vec::with_capacity(3 + tag.byte_len());
+    write_named(&mut bytes, "", &tag)?;
+    Ok(bytes)
+}
+
+/// Serializes into tags, or into nothing for `None`, so compounds can leave
+/// the key out.
+struct Serializer;
+
+impl ser::Serializer for Serializer {
+    type Ok = Option<NbtTag>;
+    type Error = NbtError;
+
+    type SerializeSeq = SerializeList;
+    type SerializeTuple = SerializeList;
+    type SerializeTupleStruct = SerializeList;
+    type SerializeTupleVariant = SerializeList;
+    type SerializeMap = SerializeCompound;
+    type SerializeStruct = SerializeCompound;
+    type SerializeStructVariant = SerializeCompound;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok> {
+        Ok(Some(NbtTag::from(v)))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok> {
+        Ok(Some(NbtTag::Byte(v)))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok> {
+        Ok(Some(NbtTag::Short(v)))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok> {
+        Ok(Some(NbtTag::Int(v)))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok> {
+        Ok(Some(NbtTag::Long(v)))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok> {
+        self.serialize_i8(v as i8)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok> {
+        self.serialize_i16(v as i16)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok> {
+        self.serialize_i32(v as i32)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok> {
+        Ok(Some(NbtTag::Float(v)))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok> {
+        Ok(Some(NbtTag::Double(v)))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok> {
+        Ok(Some(NbtTag::String(v.to_string())))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok> {
+        Ok(Some(NbtTag::from(v)))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok> {
+        let bytes = v.iter().map(|&byte| byte as i8).collect();
+        Ok(Some(NbtTag::ByteArray(bytes)))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok> {
+        Ok(Some(NbtTag::Compound(NbtCompound::new())))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok> {
+        let mut compound = NbtCompound::new();
+        if let Some(tag) = value.serialize(self)? {
+            compound.insert(variant.to_string(), tag);
+        }
+
+        Ok(Some(NbtTag::Compound(compound)))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Ok(SerializeList {
+            tags: Vec::with_capacity(len.unwrap_or(0)),
+            variant: None,
+        })
+    }
+
+    fn collect_seq<I>(self, iter: I) -> Result<Self::Ok>
+    where
+        I: IntoIterator,
+        I::Item: Serialize,
+    {
+        let iter = iter.into_iter();
+        let mut list = self.serialize_seq(iter.size_hint().1)?;
+        for value in iter {
+            list.push(&value)?;
+        }
+
+        match empty_array_of::<I::Item>() {
+            Some(empty) if list.tags.is_empty() => Ok(Some(empty)),
+            _ => list.finish(),
+        }
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Ok(SerializeList {
+            tags: Vec::with_capacity(len),
+            variant: Some(variant),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Ok(SerializeCompound {
+            compound: NbtCompound::new(),
+            key: None,
+            variant: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Ok(SerializeCompound {
+            compound: NbtCompound::new(),
+            key: None,
+            variant: Some(variant),
+        })
+    }
+}
+
+/// Wraps the value of an enum variant in a compound of the variant's name.
+fn wrap_variant(variant: Option<&'static str>, tag: NbtTag) -> NbtTag {
+    match variant {
+        Some(variant) => NbtTag::Compound([(variant.to_string(), tag)].into_iter().collect()),
+        None => tag,
+    }
+}
+
+struct SerializeList {
+    tags: Vec<NbtTag>,
+    variant: Option<&'static str>,
+}
+
+impl SerializeList {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let mut tag = value
+            .serialize(Serializer)?
+            .ok_or_else(|| NbtError::Custom("a list can't have an element of None".to_string()))?;
+
+        // an empty sequence doesn't know the type of its elements and so is
+        // an empty list, which becomes an empty array next to arrays
+        if let Some(first) = self.tags.first() {
+            if is_empty_list(&tag) {
+                tag = empty_array(first).unwrap_or(tag);
+            } else if self.tags.iter().all(is_empty_list) {
+                if let Some(empty) = empty_array(&tag) {
+                    self.tags.fill(empty);
+                }
+            }
+        }
+
+        if let Some(first) = self.tags.first().filter(|first| first.id() != tag.id()) {
+            return Err(NbtError::MixedList {
+                first: first.pretty_name(),
+                other: tag.pretty_name(),
+            });
+        }
+
+        self.tags.push(tag);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Option<NbtTag>> {
+        let tags = self.tags;
+
+        // every element has the same id as the first, which `push` checks
+        let tag = match tags.first().map(NbtTag::id) {
+            Some(1) => NbtTag::ByteArray(
+                tags.into_iter()
+                    .filter_map(|tag| match tag {
+                        NbtTag::Byte(value) => Some(value),
+                        _ => None,
+                    })
+                    .collect(),
+            ),
+            Some(3) => NbtTag::IntArray(
+                tags.into_iter()
+                    .filter_map(|tag| match tag {
+                        NbtTag::Int(value) => Some(value),
+                        _ => None,
+                    })
+                    .collect(),
+            ),
+            Some(4) => NbtTag::LongArray(
+                tags.into_iter()
+                    .filter_map(|tag| match tag {
+                        NbtTag::Long(value) => Some(value),
+                        _ => None,
+                    })
+                    .collect(),
+            ),
+            _ => NbtTag::List(NbtList::from(tags)),
+        };
+
+        Ok(Some(wrap_variant(self.variant, tag)))
+    }
+}
+
+fn is_empty_list(tag: &NbtTag) -> bool {
+    matches!(tag, NbtTag::List(list) if list.is_empty())
+}
+
+fn empty_array(tag: &NbtTag) -> Option<NbtTag> {
+    match tag {
+        NbtTag::ByteArray(_) => Some(NbtTag::ByteArray(Vec::new())),
+        NbtTag::IntArray(_) => Some(NbtTag::IntArray(Vec::new())),
+        NbtTag::LongArray(_) => Some(NbtTag::LongArray(Vec::new())),
+        _ => None,
+    }
+}
+
+/// The empty array a sequence of `T` is written as. Serde says nothing about
+/// the elements of an empty sequence, so this goes by the name of their type,
+/// which `Vec`s and slices pass to [`ser::Serializer::collect_seq`].
+fn empty_array_of<T: ?Sized>() -> Option<NbtTag> {
+    match std::any::type_name::<T>().trim_start_matches('&') {
+        "i8" | "u8" | "bool" => Some(NbtTag::ByteArray(Vec::new())),
+        "i32" | "u32" => Some(NbtTag::IntArray(Vec::new())),
+        "i64" | "u64" => Some(NbtTag::LongArray(Vec::new())),
+        _ => None,
+    }
+}
+
+impl ser::SerializeSeq for SerializeList {
+    type Ok = Option<NbtTag>;
+    type Error = NbtError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SerializeList {
+    type Ok = Option<NbtTag>;
+    type Error = NbtError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeList {
+    type Ok = Option<NbtTag>;
+    type Error = NbtError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeList {
+    type Ok = Option<NbtTag>;
+    type Error = NbtError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        self.finish()
+    }
+}
+
+struct SerializeCompound {
+    compound: NbtCompound,
+    /// The key of the entry whose value comes next.
+    key: Option<String>,
+    variant: Option<&'static str>,
+}
+
+impl SerializeCompound {
+    fn insert<T: Serialize + ?Sized>(&mut self, key: String, value: &T) -> Result<()> {
+        // fields of None are left out
+        if let Some(tag) = value.serialize(Serializer)? {
+            self.compound.insert(key, tag);
+        }
+
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Option<NbtTag>> {
+        let tag = NbtTag::Compound(self.compound);
+        Ok(Some(wrap_variant(self.variant, tag)))
+    }
+}
+
+impl ser::SerializeMap for SerializeCompound {
+    type Ok = Option<NbtTag>;
+    type Error = NbtError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        self.key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let key = self
+            .key
+            .take()
+            .expect("serialize_value called before serialize_key");
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for SerializeCompound {
+    type Ok = Option<NbtTag>;
+    type Error = NbtError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.insert(key.to_string(), value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeCompound {
+    type Ok = Option<NbtTag>;
+    type Error = NbtError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.insert(key.to_string(), value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        self.finish()
+    }
+}
+
+/// Serializes the keys of maps, which have to be strings or something
+/// written as one, such as a unit variant.
+struct KeySerializer;
+
+fn key_error() -> NbtError {
+    NbtError::Custom("the keys of a compound must be strings".to_string())
+}
+
+macro_rules! reject_keys {
+    ($($method:ident($($ty:ty),*)),* $(,)?) => {
+        $(
+            fn $method(self, $(_: $ty),*) -> Result<String> {
+                Err(key_error())
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = NbtError;
+
+    type SerializeSeq = Impossible<String, NbtError>;
+    type SerializeTuple = Impossible<String, NbtError>;
+    type SerializeTupleStruct = Impossible<String, NbtError>;
+    type SerializeTupleVariant = Impossible<String, NbtError>;
+    type SerializeMap = Impossible<String, NbtError>;
+    type SerializeStruct = Impossible<String, NbtError>;
+    type SerializeStructVariant = Impossible<String, NbtError>;
+
+    fn serialize_str(self, v: &str) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_char(self, v: char) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<String> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String> {
+        value.serialize(self)
+    }
+
+    reject_keys! {
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_bytes(&[u8]),
+        serialize_none(),
+        serialize_unit(),
+        serialize_unit_struct(&'static str),
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<String> {
+        Err(key_error())
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String> {
+        Err(key_error())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(key_error())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(key_error())
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(key_error())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(key_error())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::Serialize;
+
+    use super::*;
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct Item {
+        id: String,
+        count: u8,
+        damage: Option<i32>,
+        custom_name: Option<String>,
+        enchanted: bool,
+        lore: Vec<String>,
+        #[serde(rename = "UUID")]
+        uuid: [i32; 4],
+        kind: Kind,
+    }
+
+    #[derive(Serialize)]
+    enum Kind {
+        Tool,
+        Block(i64),
+        Armor { slot: i8 },
+    }
+
+    fn compound(tag: NbtTag) -> NbtCompound {
+        match tag {
+            NbtTag::Compound(compound) => compound,
+            tag => panic!("expected a compound, found {}", tag.pretty_name()),
+        }
+    }
+
+    #[test]
+    fn test_to_nbt() {
+        let item = Item {
+            id: "minecraft:diamond_sword".to_string(),
+            count: 200,
+            damage: Some(3),
+            custom_name: None,
+            enchanted: true,
+            lore: vec!["sharp".to_string()],
+            uuid: [1, 2, 3, 4],
+            kind: Kind::Tool,
+        };
+
+        let item = compound(to_nbt(&item).unwrap());
+        assert_eq!(item["Id"], NbtTag::from("minecraft:diamond_sword"));
+        assert_eq!(item["Count"], NbtTag::Byte(200u8 as i8));
+        assert_eq!(item["Damage"], NbtTag::Int(3));
+        assert!(!item.contains_key("CustomName"));
+        assert_eq!(item["Enchanted"], NbtTag::Byte(1));
+        assert_eq!(item["Lore"], NbtTag::List(vec!["sharp".into()].into()));
+        assert_eq!(item["UUID"], NbtTag::IntArray(vec![1, 2, 3, 4]));
+        assert_eq!(item["Kind"], NbtTag::from("Tool"));
+    }
+
+    #[test]
+    fn test_arrays() {
+        assert_eq!(
+            to_nbt(&vec![1i8, 2]).unwrap(),
+            NbtTag::ByteArray(vec![1, 2])
+        );
+        assert_eq!(to_nbt(&vec![1i64]).unwrap(), NbtTag::LongArray(vec![1]));
+        assert_eq!(
+            to_nbt(&vec![1i16]).unwrap(),
+            NbtTag::List(vec![1i16.into()].into())
+        );
+        assert_eq!(
+            to_nbt(&Vec::<i16>::new()).unwrap(),
+            NbtTag::List(NbtList::new())
+        );
+
+        // empty sequences next to arrays are empty arrays as well
+        let arrays = vec![NbtTag::IntArray(vec![]), NbtTag::IntArray(vec![1])];
+        assert_eq!(
+            to_nbt(&vec![vec![], vec![1i32]]).unwrap(),
+            NbtTag::List(arrays.clone().into())
+        );
+        assert_eq!(
+            to_nbt(&vec![vec![1i32], vec![]]).unwrap(),
+            NbtTag::List(arrays.into_iter().rev().collect())
+        );
+    }
+
+    #[test]
+    fn test_empty_arrays() {
+        let bytes = to_nbt(&Vec::<i8>::new()).unwrap();
+        assert_eq!(bytes, NbtTag::ByteArray(vec![]));
+        assert!(crate::from_nbt::<Vec<i8>>(bytes).unwrap().is_empty());
+
+        let ints = to_nbt(&Vec::<i32>::new()).unwrap();
+        assert_eq!(ints, NbtTag::IntArray(vec![]));
+        assert!(crate::from_nbt::<Vec<i32>>(ints).unwrap().is_empty());
+
+        let longs = to_nbt(&Vec::<u64>::new()).unwrap();
+        assert_eq!(longs, NbtTag::LongArray(vec![]));
+        assert!(crate::from_nbt::<Vec<u64>>(longs).unwrap().is_empty());
+
+        let bools: &[bool] = &[];
+        assert_eq!(to_nbt(bools).unwrap(), NbtTag::ByteArray(vec![]));
+    }
+
+    #[test]
+    fn test_enums() {
+        let block = compound(to_nbt(&Kind::Block(7)).unwrap());
+        assert_eq!(block["Block"], NbtTag::Long(7));
+
+        let armor = compound(to_nbt(&Kind::Armor { slot: 2 }).unwrap());
+        let slot = compound(armor["Armor"].clone());
+        assert_eq!(slot["slot"], NbtTag::Byte(2));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(to_nbt(&None::<i32>).is_err());
+        assert!(to_nbt(&vec![Some(1), None]).is_err());
+
+        let err = to_nbt(&BTreeMap::from([(1, 2)])).unwrap_err();
+        assert_eq!(err.to_string(), "the keys of a compound must be strings");
+
+        #[derive(Serialize)]
+        #[serde(untagged)]
+        enum Mixed {
+            Int(i32),
+            String(String),
+        }
+        let err = to_nbt(&vec![Mixed::Int(1), Mixed::String("a".into())]).unwrap_err();
+        assert!(matches!(err, NbtError::MixedList { .. }));
+    }
+
+    #[test]
+    fn test_to_bytes() {
+        let bytes = to_bytes(&BTreeMap::from([("a", 1i8)])).unwrap();
+        assert_eq!(bytes, [10, 0, 0, 1, 0, 1, b'a', 1, 0]);
+    }
+}
@@ -0,0 +1,819 @@
+//! SNBT, the text form of NBT used by commands, such as
+//! `{id:"minecraft:stone",count:3b}`.
+
+use std::{
+    fmt::{self, Write},
+    str::FromStr,
+};
+
+use crate::{NbtCompound, NbtList, NbtTag, SnbtError, SnbtErrorKind, MAX_DEPTH};
+
+impl NbtTag {
+    /// Parses a tag from SNBT.
+    pub fn from_snbt(s: &str) -> Result<Self, SnbtError> {
+        let mut parser = Parser::new(s);
+        let tag = parser.parse_value()?;
+        parser.finish()?;
+        Ok(tag)
+    }
+
+    /// Writes the tag as SNBT over several lines, indenting each level of
+    /// nesting with `indent`. `{:#}` does the same with four spaces.
+    pub fn to_snbt_pretty(&self, indent: &str) -> String {
+        let mut s = String::new();
+        write_tag(&mut s, self, Some(indent), 0).expect("writing to a string can't fail");
+        s
+    }
+}
+
+impl NbtCompound {
+    /// Parses a compound from SNBT, such as the data of an item or entity.
+    pub fn from_snbt(s: &str) -> Result<Self, SnbtError> {
+        let mut parser = Parser::new(s);
+        parser.skip_whitespace();
+        if parser.peek() != Some('{') {
+            return Err(parser.expected("'{'"));
+        }
+
+        let compound = parser.parse_compound()?;
+        parser.finish()?;
+        Ok(compound)
+    }
+
+    /// Writes the compound as SNBT over several lines, see
+    /// [`NbtTag::to_snbt_pretty`].
+    pub fn to_snbt_pretty(&self, indent: &str) -> String {
+        let mut s = String::new();
+        write_compound(&mut s, self, Some(indent), 0).expect("writing to a string can't fail");
+        s
+    }
+}
+
+impl FromStr for NbtTag {
+    type Err = SnbtError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_snbt(s)
+    }
+}
+
+impl FromStr for NbtCompound {
+    type Err = SnbtError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_snbt(s)
+    }
+}
+
+// `{}` writes SNBT on one line without spaces, the way the game does, and
+// `{:#}` over several lines indented with four spaces.
+
+impl fmt::Display for NbtTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_tag(f, self, f.alternate().then_some("    "), 0)
+    }
+}
+
+impl fmt::Display for NbtList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_list(f, self, f.alternate().then_some("    "), 0)
+    }
+}
+
+impl fmt::Display for NbtCompound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_compound(f, self, f.alternate().then_some("    "), 0)
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            input,
+            pos: 0,
+            depth: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.input[self.pos..].chars().nth(n)
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.next();
+        }
+    }
+
+    fn error_at(&self, offset: usize, kind: SnbtErrorKind) -> SnbtError {
+        SnbtError::new(kind, self.input, offset)
+    }
+
+    fn expected(&self, expected: &'static str) -> SnbtError {
+        let found = self.peek();
+        self.error_at(self.pos, SnbtErrorKind::Expected { expected, found })
+    }
+
+    /// Skips whitespace and then `c`, failing if it isn't there.
+    fn expect(&mut self, c: char, expected: &'static str) -> Result<(), SnbtError> {
+        self.skip_whitespace();
+        if self.peek() != Some(c) {
+            return Err(self.expected(expected));
+        }
+
+        self.next();
+        Ok(())
+    }
+
+    /// Checks that nothing but whitespace is left.
+    fn finish(&mut self) -> Result<(), SnbtError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(_) => Err(self.expected("the end of the input")),
+            None => Ok(()),
+        }
+    }
+
+    fn enter(&mut self) -> Result<(), SnbtError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.error_at(self.pos, SnbtErrorKind::TooDeep));
+        }
+
+        Ok(())
+    }
+
+    fn parse_value(&mut self) -> Result<NbtTag, SnbtError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.parse_compound().map(NbtTag::Compound),
+            Some('[') => self.parse_list_or_array(),
+            Some('"' | '\'') => self.parse_quoted().map(NbtTag::String),
+            _ => {
+                let start = self.pos;
+                let token = self.parse_unquoted();
+                if token.is_empty() {
+                    return Err(self.expected("a value"));
+                }
+
+                parse_token(token).map_err(|kind| self.error_at(start, kind))
+            }
+        }
+    }
+
+    fn parse_compound(&mut self) -> Result<NbtCompound, SnbtError> {
+        self.enter()?;
+        self.next();
+
+        let mut compound = NbtCompound::new();
+        loop {
+            self.skip_whitespace();
+            if self.peek() == Some('}') {
+                break;
+            }
+
+            let key = self.parse_key()?;
+            self.expect(':', "':'")?;
+            let value = self.parse_value()?;
+            compound.insert(key, value);
+
+            // a comma may trail the last entry
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => {
+                    self.next();
+                }
+                Some('}') => break,
+                _ => return Err(self.expected("',' or '}'")),
+            }
+        }
+
+        self.next();
+        self.depth -= 1;
+        Ok(compound)
+    }
+
+    fn parse_key(&mut self) -> Result<String, SnbtError> {
+        match self.peek() {
+            Some('"' | '\'') => self.parse_quoted(),
+            _ => match self.parse_unquoted() {
+                "" => Err(self.expected("a key")),
+                key => Ok(key.to_string()),
+            },
+        }
+    }
+
+    fn parse_list_or_array(&mut self) -> Result<NbtTag, SnbtError> {
+        // an array starts with its type right after the bracket, as in
+        // `[I;1,2]`, and anything else is a list
+        if self.peek_nth(2) == Some(';') {
+            if let Some(kind) = self.peek_nth(1).filter(|c| !matches!(c, '"' | '\'')) {
+                return self.parse_array(kind);
+            }
+        }
+
+        self.enter()?;
+        self.next();
+
+        let mut list = NbtList::new();
+        self.parse_elements(|parser, start, tag| {
+            if let Some(first) = list.first().filter(|first| first.id() != tag.id()) {
+                let kind = SnbtErrorKind::MixedList {
+                    first: first.pretty_name(),
+                    other: tag.pretty_name(),
+                };
+                return Err(parser.error_at(start, kind));
+            }
+
+            list.push(tag);
+            Ok(())
+        })?;
+
+        self.depth -= 1;
+        Ok(NbtTag::List(list))
+    }
+
+    fn parse_array(&mut self, kind: char) -> Result<NbtTag, SnbtError> {
+        let mut array = match kind {
+            'B' => NbtTag::ByteArray(Vec::new()),
+            'I' => NbtTag::IntArray(Vec::new()),
+            'L' => NbtTag::LongArray(Vec::new()),
+            kind => {
+                let offset = self.pos + 1;
+                return Err(self.error_at(offset, SnbtErrorKind::InvalidArrayType(kind)));
+            }
+        };
+
+        // the bracket, type and semicolon
+        self.pos += 3;
+
+        self.parse_elements(|parser, start, tag| {
+            match (&mut array, tag) {
+                (NbtTag::ByteArray(values), NbtTag::Byte(value)) => values.push(value),
+                (NbtTag::IntArray(values), NbtTag::Int(value)) => values.push(value),
+                (NbtTag::LongArray(values), NbtTag::Long(value)) => values.push(value),
+                (array, tag) => {
+                    let kind = SnbtErrorKind::ArrayElement {
+                        array: array.pretty_name(),
+                        element: tag.pretty_name(),
+                    };
+                    return Err(parser.error_at(start, kind));
+                }
+            }
+
+            Ok(())
+        })?;
+
+        Ok(array)
+    }
+
+    /// Parses the comma separated values of a list or array up to the
+    /// closing bracket, handing each to `push` with where it started.
+    fn parse_elements(
+        &mut self,
+        mut push: impl FnMut(&Self, usize, NbtTag) -> Result<(), SnbtError>,
+    ) -> Result<(), SnbtError> {
+        loop {
+            self.skip_whitespace();
+            if self.peek() == Some(']') {
+                break;
+            }
+
+            let start = self.pos;
+            let tag = self.parse_value()?;
+            push(self, start, tag)?;
+
+            // a comma may trail the last element
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => {
+                    self.next();
+                }
+                Some(']') => break,
+                _ => return Err(self.expected("',' or ']'")),
+            }
+        }
+
+        self.next();
+        Ok(())
+    }
+
+    fn parse_quoted(&mut self) -> Result<String, SnbtError> {
+        let quote = self.next().expect("called at a quote");
+
+        let mut s = String::new();
+        loop {
+            match self.next() {
+                Some(c) if c == quote => return Ok(s),
+                Some('\\') => {
+                    let offset = self.pos - 1;
+                    match self.next() {
+                        Some(c @ ('\\' | '"' | '\'')) => s.push(c),
+                        Some('n') => s.push('\n'),
+                        Some('t') => s.push('\t'),
+                        Some('r') => s.push('\r'),
+                        Some(c) => {
+                            return Err(self.error_at(offset, SnbtErrorKind::InvalidEscape(c)))
+                        }
+                        None => return Err(self.expected("an escaped character")),
+                    }
+                }
+                Some(c) => s.push(c),
+                None => return Err(self.expected("a closing quote")),
+            }
+        }
+    }
+
+    fn parse_unquoted(&mut self) -> &'a str {
+        let start = self.pos;
+        while self.peek().is_some_and(is_unquoted) {
+            self.next();
+        }
+
+        &self.input[start..self.pos]
+    }
+}
+
+/// Whether `c` can be part of a key or string without quotes.
+fn is_unquoted(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+')
+}
+
+/// Turns an unquoted value into a number if it looks like one, the way the
+/// game does, and into a string otherwise. On top of what the game reads,
+/// `NaNf`, `inff` and `-inff` and their doubles are read back as the
+/// numbers they're written for, since SNBT has no other way to write them.
+fn parse_token(token: &str) -> Result<NbtTag, SnbtErrorKind> {
+    let out_of_range = || SnbtErrorKind::OutOfRange(token.to_string());
+
+    match token {
+        "true" => return Ok(NbtTag::Byte(1)),
+        "false" => return Ok(NbtTag::Byte(0)),
+        _ => {}
+    }
+
+    let (body, suffix) = match token.char_indices().last() {
+        Some((i, c)) if "bBsSlLfFdD".contains(c) => (&token[..i], Some(c.to_ascii_lowercase())),
+        _ => (token, None),
+    };
+
+    let tag = match suffix {
+        Some('f') if is_non_finite(body) => return Ok(NbtTag::Float(body.parse().unwrap())),
+        Some('d') if is_non_finite(body) => return Ok(NbtTag::Double(body.parse().unwrap())),
+        Some('b') if is_integer(body) => body.parse().map(NbtTag::Byte).ok(),
+        Some('s') if is_integer(body) => body.parse().map(NbtTag::Short).ok(),
+        Some('l') if is_integer(body) => body.parse().map(NbtTag::Long).ok(),
+        Some('f') if is_decimal(body, false) => body.parse().map(NbtTag::Float).ok(),
+        Some('d') if is_decimal(body, false) => body.parse().map(NbtTag::Double).ok(),
+        None if is_integer(body) => body.parse().map(NbtTag::Int).ok(),
+        None if is_decimal(body, true) => body.parse().map(NbtTag::Double).ok(),
+        _ => return Ok(NbtTag::String(token.to_string())),
+    };
+
+    tag.filter(|tag| match tag {
+        NbtTag::Float(value) => value.is_finite(),
+        NbtTag::Double(value) => value.is_finite(),
+        _ => true,
+    })
+    .ok_or_else(out_of_range)
+}
+
+/// Whether `s` is how `{:?}` writes a float that isn't finite.
+fn is_non_finite(s: &str) -> bool {
+    matches!(s, "NaN" | "inf" | "-inf")
+}
+
+/// Whether `s` is a whole number without leading zeros, such as `-12`.
+fn is_integer(s: &str) -> bool {
+    let digits = s.strip_prefix(['-', '+']).unwrap_or(s);
+    match digits.as_bytes() {
+        [b'0'] => true,
+        [b'1'..=b'9', rest @ ..] => rest.iter().all(u8::is_ascii_digit),
+        _ => false,
+    }
+}
+
+/// Whether `s` is a number like `1`, `1.`, `.5` or `1.5e-3`, which without a
+/// suffix has to have a decimal point.
+fn is_decimal(s: &str, needs_point: bool) -> bool {
+    let s = s.strip_prefix(['-', '+']).unwrap_or(s);
+
+    let (mantissa, exponent) = match s.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+        None => (s, None),
+    };
+
+    let digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    let mantissa = match mantissa.split_once('.') {
+        Some((whole, fraction)) => {
+            digits(whole) && digits(fraction) && (!whole.is_empty() || !fraction.is_empty())
+        }
+        None => !needs_point && !mantissa.is_empty() && digits(mantissa),
+    };
+
+    let exponent = exponent.is_none_or(|exponent| {
+        let exponent = exponent.strip_prefix(['-', '+']).unwrap_or(exponent);
+        !exponent.is_empty() && digits(exponent)
+    });
+
+    mantissa && exponent
+}
+
+fn write_tag<W: Write>(w: &mut W, tag: &NbtTag, indent: Option<&str>, level: usize) -> fmt::Result {
+    // `{:?}` writes floats so they parse back to the same value, which for
+    // those that aren't finite needs `parse_token` to know its spelling
+    match tag {
+        NbtTag::Byte(value) => write!(w, "{value}b"),
+        NbtTag::Short(value) => write!(w, "{value}s"),
+        NbtTag::Int(value) => write!(w, "{value}"),
+        NbtTag::Long(value) => write!(w, "{value}L"),
+        NbtTag::Float(value) => write!(w, "{value:?}f"),
+        NbtTag::Double(value) => write!(w, "{value:?}d"),
+        NbtTag::ByteArray(values) => write_array(w, 'B', values, "b", indent),
+        NbtTag::String(s) => write_string(w, s),
+        NbtTag::List(list) => write_list(w, list, indent, level),
+        NbtTag::Compound(compound) => write_compound(w, compound, indent, level),
+        NbtTag::IntArray(values) => write_array(w, 'I', values, "", indent),
+        NbtTag::LongArray(values) => write_array(w, 'L', values, "L", indent),
+    }
+}
+
+fn write_array<W: Write, T: fmt::Display>(
+    w: &mut W,
+    kind: char,
+    values: &[T],
+    suffix: &str,
+    indent: Option<&str>,
+) -> fmt::Result {
+    // arrays stay on one line even when pretty printing
+    let separator = if indent.is_some() { ", " } else { "," };
+
+    write!(w, "[{kind};")?;
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            w.write_str(separator)?;
+        } else if indent.is_some() {
+            w.write_char(' ')?;
+        }
+
+        write!(w, "{value}{suffix}")?;
+    }
+    w.write_char(']')
+}
+
+fn write_list<W: Write>(
+    w: &mut W,
+    list: &NbtList,
+    indent: Option<&str>,
+    level: usize,
+) -> fmt::Result {
+    // lists of numbers and strings stay on one line when pretty printing
+    let nested = list
+        .iter()
+        .any(|tag| matches!(tag, NbtTag::List(_) | NbtTag::Compound(_)));
+    let entries = list.iter().map(|tag| (None, tag));
+
+    match indent {
+        Some(_) if !nested => {
+            w.write_char('[')?;
+            for (i, tag) in list.iter().enumerate() {
+                if i > 0 {
+                    w.write_str(", ")?;
+                }
+                write_tag(w, tag, indent, level)?;
+            }
+            w.write_char(']')
+        }
+        _ => write_entries(w, ('[', ']'), entries, indent, level),
+    }
+}
+
+fn write_compound<W: Write>(
+    w: &mut W,
+    compound: &NbtCompound,
+    indent: Option<&str>,
+    level: usize,
+) -> fmt::Result {
+    let entries = compound.iter().map(|(key, tag)| (Some(key.as_str()), tag));
+    write_entries(w, ('{', '}'), entries, indent, level)
+}
+
+/// Writes the entries of a list or compound between `brackets`, one to a
+/// line when there's an indent.
+fn write_entries<'a, W: Write>(
+    w: &mut W,
+    (open, close): (char, char),
+    entries: impl ExactSizeIterator<Item = (Option<&'a str>, &'a NbtTag)>,
+    indent: Option<&str>,
+    level: usize,
+) -> fmt::Result {
+    if entries.len() == 0 {
+        return write!(w, "{open}{close}");
+    }
+
+    w.write_char(open)?;
+    for (i, (key, tag)) in entries.enumerate() {
+        if i > 0 {
+            w.write_char(',')?;
+        }
+
+        if let Some(indent) = indent {
+            w.write_char('\n')?;
+            write_indent(w, indent, level + 1)?;
+        }
+
+        if let Some(key) = key {
+            write_key(w, key)?;
+            w.write_str(if indent.is_some() { ": " } else { ":" })?;
+        }
+
+        write_tag(w, tag, indent, level + 1)?;
+    }
+
+    if let Some(indent) = indent {
+        w.write_char('\n')?;
+        write_indent(w, indent, level)?;
+    }
+    w.write_char(close)
+}
+
+fn write_indent<W: Write>(w: &mut W, indent: &str, level: usize) -> fmt::Result {
+    (0..level).try_for_each(|_| w.write_str(indent))
+}
+
+fn write_key<W: Write>(w: &mut W, key: &str) -> fmt::Result {
+    if !key.is_empty() && key.chars().all(is_unquoted) {
+        w.write_str(key)
+    } else {
+        write_string(w, key)
+    }
+}
+
+/// Writes `s` in double quotes, or single quotes if that saves escaping
+/// double quotes in it.
+fn write_string<W: Write>(w: &mut W, s: &str) -> fmt::Result {
+    let quote = if s.contains('"') && !s.contains('\'') {
+        '\''
+    } else {
+        '"'
+    };
+
+    w.write_char(quote)?;
+    for c in s.chars() {
+        if c == quote || c == '\\' {
+            w.write_char('\\')?;
+        }
+        w.write_char(c)?;
+    }
+    w.write_char(quote)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::read_file;
+
+    fn parse(s: &str) -> NbtTag {
+        NbtTag::from_snbt(s).unwrap()
+    }
+
+    fn error(s: &str) -> SnbtError {
+        NbtTag::from_snbt(s).unwrap_err()
+    }
+
+    #[test]
+    fn test_numbers() {
+        assert_eq!(parse("1b"), NbtTag::Byte(1));
+        assert_eq!(parse("-2S"), NbtTag::Short(-2));
+        assert_eq!(parse("3"), NbtTag::Int(3));
+        assert_eq!(parse("+4L"), NbtTag::Long(4));
+        assert_eq!(parse("1.5f"), NbtTag::Float(1.5));
+        assert_eq!(parse("2F"), NbtTag::Float(2.0));
+        assert_eq!(parse("1.5"), NbtTag::Double(1.5));
+        assert_eq!(parse(".5d"), NbtTag::Double(0.5));
+        assert_eq!(parse("1e3d"), NbtTag::Double(1000.0));
+        assert_eq!(parse("true"), NbtTag::Byte(1));
+        assert_eq!(parse("false"), NbtTag::Byte(0));
+
+        // anything that doesn't look like a number is a string
+        for s in ["1e3", "01", "1.2.3", "b", "minecraft.stone", "-"] {
+            assert_eq!(parse(s), NbtTag::from(s), "{s}");
+        }
+
+        assert_eq!(error("128b").kind, SnbtErrorKind::OutOfRange("128b".into()));
+        assert_eq!(
+            error("1e39f").kind,
+            SnbtErrorKind::OutOfRange("1e39f".into())
+        );
+    }
+
+    #[test]
+    fn test_strings() {
+        assert_eq!(parse(r#""a \"b\" c""#), NbtTag::from(r#"a "b" c"#));
+        assert_eq!(parse(r#"'it\'s "x"'"#), NbtTag::from(r#"it's "x""#));
+        assert_eq!(parse(r#""\\\n""#), NbtTag::from("\\\n"));
+        assert_eq!(parse("\"😀\""), NbtTag::from("😀"));
+
+        assert_eq!(error(r#""\q""#).kind, SnbtErrorKind::InvalidEscape('q'));
+        assert_eq!(error("\"abc").column, 5);
+    }
+
+    #[test]
+    fn test_compound() {
+        let tag = parse(r#" { id : "minecraft:stone", count:3b, "a key":{}, 'x':[], } "#);
+        let NbtTag::Compound(compound) = tag else {
+            panic!("expected a compound");
+        };
+
+        assert_eq!(compound["id"], NbtTag::from("minecraft:stone"));
+        assert_eq!(compound["count"], NbtTag::Byte(3));
+        assert_eq!(compound["a key"], NbtTag::Compound(NbtCompound::new()));
+        assert_eq!(compound["x"], NbtTag::List(NbtList::new()));
+
+        assert_eq!(
+            NbtCompound::from_snbt("{a:1}").unwrap()["a"],
+            NbtTag::Int(1)
+        );
+        assert!(NbtCompound::from_snbt("[1]").is_err());
+    }
+
+    #[test]
+    fn test_arrays() {
+        assert_eq!(parse("[B;1b,-2b]"), NbtTag::ByteArray(vec![1, -2]));
+        assert_eq!(parse("[I; 1, 2, 3]"), NbtTag::IntArray(vec![1, 2, 3]));
+        assert_eq!(parse("[L;1L,]"), NbtTag::LongArray(vec![1]));
+        assert_eq!(parse("[I;]"), NbtTag::IntArray(vec![]));
+
+        let err = error("[L;1L,2]");
+        assert_eq!(
+            err.kind,
+            SnbtErrorKind::ArrayElement {
+                array: "TAG_Long_Array",
+                element: "TAG_Int"
+            }
+        );
+        assert_eq!(err.column, 7);
+        assert_eq!(error("[X;1]").kind, SnbtErrorKind::InvalidArrayType('X'));
+    }
+
+    #[test]
+    fn test_lists() {
+        assert_eq!(
+            parse("[1, 2]"),
+            NbtTag::List(vec![1i32.into(), 2i32.into()].into())
+        );
+        assert_eq!(
+            parse("[[], [1b]]"),
+            NbtTag::List(
+                vec![
+                    NbtTag::List(NbtList::new()),
+                    NbtTag::List(vec![1i8.into()].into()),
+                ]
+                .into()
+            )
+        );
+
+        let err = error("[1, 2b]");
+        assert_eq!(
+            err.kind,
+            SnbtErrorKind::MixedList {
+                first: "TAG_Int",
+                other: "TAG_Byte"
+            }
+        );
+        assert_eq!(err.offset, 4);
+    }
+
+    #[test]
+    fn test_error_positions() {
+        let err = error("{\n  a: 1,\n  b 2\n}");
+        assert_eq!(
+            err.kind,
+            SnbtErrorKind::Expected {
+                expected: "':'",
+                found: Some('2')
+            }
+        );
+        assert_eq!((err.line, err.column, err.offset), (3, 5, 14));
+        assert_eq!(
+            err.to_string(),
+            "expected ':' but found '2' at line 3, column 5"
+        );
+
+        let err = error("{a:1");
+        assert_eq!(
+            err.kind,
+            SnbtErrorKind::Expected {
+                expected: "',' or '}'",
+                found: None
+            }
+        );
+        assert_eq!(err.column, 5);
+
+        assert_eq!(error("{a:1} x").column, 7);
+        // columns count characters rather than bytes
+        assert_eq!(error(r#"{"é":@}"#).column, 6);
+        assert_eq!(error("").column, 1);
+    }
+
+    #[test]
+    fn test_too_deep() {
+        let s = "[".repeat(MAX_DEPTH + 1) + &"]".repeat(MAX_DEPTH + 1);
+        assert_eq!(error(&s).kind, SnbtErrorKind::TooDeep);
+
+        let s = "[".repeat(MAX_DEPTH) + &"]".repeat(MAX_DEPTH);
+        assert!(NbtTag::from_snbt(&s).is_ok());
+    }
+
+    // without preserve_order the keys are written out in another order
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn test_display() {
+        let tag = parse(r#"{a:1b,"b c":[1.5f,2.0f],d:[I;1,2],e:{f:"it's"},g:'"'}"#);
+        assert_eq!(
+            tag.to_string(),
+            r#"{a:1b,"b c":[1.5f,2.0f],d:[I;1,2],e:{f:"it's"},g:'"'}"#
+        );
+
+        assert_eq!(
+            format!("{tag:#}"),
+            r#"{
+    a: 1b,
+    "b c": [1.5f, 2.0f],
+    d: [I; 1, 2],
+    e: {
+        f: "it's"
+    },
+    g: '"'
+}"#
+        );
+        assert_eq!(
+            parse("{a:[{}],b:{}}").to_snbt_pretty("\t"),
+            "{\n\ta: [\n\t\t{}\n\t],\n\tb: {}\n}"
+        );
+        assert_eq!(parse("[L;-1L]").to_string(), "[L;-1L]");
+        assert_eq!(parse("1e300d").to_string(), "1e300d");
+        assert_eq!(NbtTag::Float(f32::NAN).to_string(), "NaNf");
+        assert_eq!(NbtTag::Double(f64::NEG_INFINITY).to_string(), "-infd");
+        assert_eq!(NbtTag::from("a\\b").to_string(), r#""a\\b""#);
+    }
+
+    #[test]
+    fn test_non_finite() {
+        for value in [f32::INFINITY, f32::NEG_INFINITY] {
+            let tag = NbtTag::Float(value);
+            assert_eq!(parse(&tag.to_string()), tag);
+        }
+        for value in [f64::INFINITY, f64::NEG_INFINITY] {
+            let tag = NbtTag::Double(value);
+            assert_eq!(parse(&tag.to_string()), tag);
+        }
+        assert!(matches!(parse("NaNf"), NbtTag::Float(value) if value.is_nan()));
+        assert!(matches!(parse("NaNd"), NbtTag::Double(value) if value.is_nan()));
+
+        let tag = parse(&NbtTag::List(vec![NbtTag::Double(f64::NAN)].into()).to_string());
+        assert!(
+            matches!(&tag, NbtTag::List(list) if matches!(list[..], [NbtTag::Double(value)] if value.is_nan()))
+        );
+
+        // only the spellings the printer uses, and never without a suffix
+        assert_eq!(parse("NaN"), NbtTag::from("NaN"));
+        assert_eq!(parse("infinityf"), NbtTag::from("infinityf"));
+        assert_eq!(parse("+inff"), NbtTag::from("+inff"));
+        assert_eq!(
+            error("1e400d").kind,
+            SnbtErrorKind::OutOfRange("1e400d".into())
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let level = include_bytes!("../tests/fixtures/synthetic_level.dat");
+        let (_, tag) = read_file(&level[..]).unwrap();
+
+        assert_eq!(parse(&tag.to_string()), tag);
+        assert_eq!(parse(&format!("{tag:#}")), tag);
+
+        let NbtTag::Compound(compound) = tag else {
+            panic!("expected a compound");
+        };
+        let pretty = compound.to_snbt_pretty("  ");
+        assert_eq!(NbtCompound::from_snbt(&pretty).unwrap(), compound);
+    }
+}
@@ -39,7 +39,7 @@ impl NbtTag {
         }
     }
 
-    pub fn name(&self) -> &str {
+    pub fn name(&self) -> &'static str {
         match self {
             Self::Byte(_) => "BYTE",
             Self::Short(_) => "SHORT",
@@ -56,7 +56,7 @@ impl NbtTag {
         }
     }
 
-    pub fn pretty_name(&self) -> &str {
+    pub fn pretty_name(&self) -> &'static str {
         match self {
             Self::Byte(_) => "TAG_Byte",
             Self::Short(_) => "TAG_Short",
@@ -73,6 +73,8 @@ impl NbtTag {
         }
     }
 
+    /// How many bytes the payload of this tag is written as, everything
+    /// after its id and name.
     pub fn byte_len(&self) -> usize {
         match self {
             Self::Byte(_) => 1,
@@ -81,19 +83,69 @@ impl NbtTag {
             Self::Long(_) => 8,
             Self::Float(_) => 4,
             Self::Double(_) => 8,
-            Self::ByteArray(v) => v.len(),
-            Self::String(s) => 2 + s.len(),
-            Self::List(l) => todo!(),
-            Self::Compound(c) => todo!(),
+            Self::ByteArray(v) => 4 + v.len(),
+            Self::String(s) => string_len(s),
+            Self::List(l) => l.byte_len(),
+            Self::Compound(c) => c.byte_len(),
             Self::IntArray(a) => 4 + 4 * a.len(),
             Self::LongArray(a) => 4 + 8 * a.len(),
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// The length of a string written in modified UTF-8 with its `u16` length
+/// in front. Nul takes two bytes and characters outside the BMP six, being
+/// written as a surrogate pair.
+pub fn string_len(s: &str) -> usize {
+    let encoded = s
+        .chars()
+        .map(|c| match c as u32 {
+            0 => 2,
+            0x01..=0x7F => 1,
+            0x80..=0x7FF => 2,
+            0x800..=0xFFFF => 3,
+            _ => 6,
+        })
+        .sum::<usize>();
+    2 + encoded
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct NbtList(Vec<NbtTag>);
 
+impl NbtList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How many bytes the list is written as: the id of its elements, their
+    /// count and then their payloads.
+    pub fn byte_len(&self) -> usize {
+        5 + self.iter().map(NbtTag::byte_len).sum::<usize>()
+    }
+}
+
+impl From<Vec<NbtTag>> for NbtList {
+    fn from(value: Vec<NbtTag>) -> Self {
+        Self(value)
+    }
+}
+
+impl FromIterator<NbtTag> for NbtList {
+    fn from_iter<I: IntoIterator<Item = NbtTag>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl IntoIterator for NbtList {
+    type Item = NbtTag;
+    type IntoIter = std::vec::IntoIter<NbtTag>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
 impl Deref for NbtList {
     type Target = Vec<NbtTag>;
 
@@ -108,9 +160,45 @@ impl DerefMut for NbtList {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct NbtCompound(Map<NbtTag>);
 
+impl NbtCompound {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How many bytes the compound is written as: the id, name and payload
+    /// of every entry and then the `TAG_End` closing it.
+    pub fn byte_len(&self) -> usize {
+        let entries = self
+            .iter()
+            .map(|(name, tag)| 1 + string_len(name) + tag.byte_len());
+        entries.sum::<usize>() + 1
+    }
+}
+
+impl From<Map<NbtTag>> for NbtCompound {
+    fn from(value: Map<NbtTag>) -> Self {
+        Self(value)
+    }
+}
+
+impl FromIterator<(String, NbtTag)> for NbtCompound {
+    fn from_iter<I: IntoIterator<Item = (String, NbtTag)>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl IntoIterator for NbtCompound {
+    type Item = (String, NbtTag);
+    type IntoIter = <Map<NbtTag> as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
 impl Deref for NbtCompound {
     type Target = Map<NbtTag>;
 
@@ -143,7 +231,68 @@ impl_from!(f32, Float);
 impl_from!(f64, Double);
 impl_from!(Vec<i8>, ByteArray);
 impl_from!(String, String);
+
+impl From<&str> for NbtTag {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<bool> for NbtTag {
+    fn from(value: bool) -> Self {
+        Self::Byte(value as i8)
+    }
+}
+
 impl_from!(NbtList, List);
 impl_from!(NbtCompound, Compound);
 impl_from!(Vec<i32>, IntArray);
 impl_from!(Vec<i64>, LongArray);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{binary::write_payload, read_file};
+
+    fn written_len(tag: &NbtTag) -> usize {
+        let mut bytes = Vec::new();
+        write_payload(&mut bytes, tag).unwrap();
+        bytes.len()
+    }
+
+    #[test]
+    fn test_byte_len() {
+        let compound: NbtCompound = [
+            ("byte".to_string(), 1i8.into()),
+            ("double".to_string(), 1.5f64.into()),
+            ("bytes".to_string(), NbtTag::ByteArray(vec![1, 2, 3])),
+            ("nul\0".to_string(), "é€😀\0".into()),
+            ("empty".to_string(), NbtTag::List(NbtList::new())),
+            (
+                "lists".to_string(),
+                NbtTag::List(vec![NbtTag::List(vec![1i16.into()].into())].into()),
+            ),
+            ("compound".to_string(), NbtTag::Compound(NbtCompound::new())),
+            ("ints".to_string(), vec![1i32, 2].into()),
+            ("longs".to_string(), vec![1i64].into()),
+        ]
+        .into_iter()
+        .collect();
+
+        for tag in compound.values() {
+            assert_eq!(tag.byte_len(), written_len(tag), "{}", tag.pretty_name());
+        }
+
+        let tag = NbtTag::Compound(compound);
+        assert_eq!(tag.byte_len(), written_len(&tag));
+        // the 2 byte length, 2 bytes for é, 3 for €, 6 for 😀 and 2 for nul
+        assert_eq!(NbtTag::from("é€😀\0").byte_len(), 15);
+    }
+
+    #[test]
+    fn test_byte_len_fixtures() {
+        let level = include_bytes!("../tests/fixtures/synthetic_level.dat");
+        let (_, tag) = read_file(&level[..]).unwrap();
+        assert_eq!(tag.byte_len(), written_len(&tag));
+    }
+}
@@ -0,0 +1,207 @@
+#!/usr/bin/env python3
+"""Writes the synthetic NBT fixtures used by the tests of blocky-nbt.
+
+These are not saves made by the game, which is why their names start with
+synthetic_. They are laid out like the level.dat and player data of a 1.21
+world, but written by this script, separately from the Rust code under
+test, so that reading and writing them checks the crate against an
+independent encoder. Run it from this directory.
+"""
+
+import gzip
+import struct
+
+END, BYTE, SHORT, INT, LONG, FLOAT, DOUBLE, BYTE_ARRAY, STRING, LIST, COMPOUND, INT_ARRAY, LONG_ARRAY = range(13)
+
+
+def mutf8(s):
+    """Java's modified UTF-8: NUL takes two bytes and characters outside
+    the BMP are written as the two surrogates of their UTF-16 form."""
+    units = struct.unpack(f">{len(s.encode('utf-16-be')) // 2}H", s.encode("utf-16-be"))
+    out = bytearray()
+    for unit in units:
+        if 0 < unit < 0x80:
+            out.append(unit)
+        elif unit < 0x800:
+            out += bytes([0xC0 | unit >> 6, 0x80 | unit & 0x3F])
+        else:
+            out += bytes([0xE0 | unit >> 12, 0x80 | unit >> 6 & 0x3F, 0x80 | unit & 0x3F])
+    return bytes(out)
+
+
+def string(s):
+    data = mutf8(s)
+    return struct.pack(">H", len(data)) + data
+
+
+class Tag:
+    def __init__(self, id, payload):
+        self.id = id
+        self.payload = payload
+
+
+def byte(v): return Tag(BYTE, struct.pack(">b", v))
+def short(v): return Tag(SHORT, struct.pack(">h", v))
+def int_(v): return Tag(INT, struct.pack(">i", v))
+def long(v): return Tag(LONG, struct.pack(">q", v))
+def float_(v): return Tag(FLOAT, struct.pack(">f", v))
+def double(v): return Tag(DOUBLE, struct.pack(">d", v))
+def str_(v): return Tag(STRING, string(v))
+def int_array(vs): return Tag(INT_ARRAY, struct.pack(f">i{len(vs)}i", len(vs), *vs))
+def long_array(vs): return Tag(LONG_ARRAY, struct.pack(f">i{len(vs)}q", len(vs), *vs))
+
+
+def list_(tags):
+    id = tags[0].id if tags else END
+    return Tag(LIST, bytes([id]) + struct.pack(">i", len(tags)) + b"".join(t.payload for t in tags))
+
+
+def compound(entries):
+    body = b"".join(bytes([tag.id]) + string(name) + tag.payload for name, tag in entries)
+    return Tag(COMPOUND, body + bytes([END]))
+
+
+def write(path, tag):
+    data = bytes([tag.id]) + string("") + tag.payload
+    with open(path, "wb") as f:
+        # a fixed mtime keeps the output the same between runs
+        f.write(gzip.compress(data, mtime=0))
+
+
+def player():
+    return compound([
+        ("AbsorptionAmount", float_(0.0)),
+        ("Air", short(300)),
+        ("Brain", compound([("memories", compound([]))])),
+        ("DataVersion", int_(3953)),
+        ("DeathTime", short(0)),
+        ("Dimension", str_("minecraft:overworld")),
+        ("EnderItems", list_([])),
+        ("FallDistance", float_(0.0)),
+        ("Fire", short(-20)),
+        ("Health", float_(20.0)),
+        ("HurtTime", short(0)),
+        ("Inventory", list_([
+            compound([
+                ("Slot", byte(0)),
+                ("components", compound([
+                    ("minecraft:custom_name", str_('{"text":"Épée 😀"}')),
+                    ("minecraft:damage", int_(12)),
+                ])),
+                ("count", int_(1)),
+                ("id", str_("minecraft:diamond_sword")),
+            ]),
+            compound([
+                ("Slot", byte(1)),
+                ("count", int_(64)),
+                ("id", str_("minecraft:cobblestone")),
+            ]),
+        ])),
+        ("Motion", list_([double(0.0), double(-0.0784000015258789), double(0.0)])),
+        ("OnGround", byte(1)),
+        ("Pos", list_([double(8.5), double(64.0), double(-3.25)])),
+        ("Rotation", list_([float_(-90.0), float_(12.5)])),
+        ("Score", int_(0)),
+        ("SelectedItemSlot", int_(0)),
+        ("UUID", int_array([-1436914047, 1262961372, -1659706045, 1164578213])),
+        ("XpLevel", int_(3)),
+        ("XpP", float_(0.25)),
+        ("abilities", compound([
+            ("flySpeed", float_(0.05)),
+            ("flying", byte(0)),
+            ("instabuild", byte(0)),
+            ("invulnerable", byte(0)),
+            ("mayBuild", byte(1)),
+            ("mayfly", byte(0)),
+            ("walkSpeed", float_(0.1)),
+        ])),
+        ("foodLevel", int_(20)),
+        ("playerGameType", int_(0)),
+        ("recipeBook", compound([
+            ("recipes", list_([str_("minecraft:crafting_table"), str_("minecraft:stick")])),
+            ("toBeDisplayed", list_([str_("minecraft:stick")])),
+        ])),
+        ("seenCredits", byte(0)),
+    ])
+
+
+def level():
+    game_rules = [
+        ("doDaylightCycle", "true"),
+        ("doMobSpawning", "true"),
+        ("keepInventory", "false"),
+        ("randomTickSpeed", "3"),
+        ("spawnRadius", "10"),
+    ]
+
+    return compound([
+        ("Data", compound([
+            ("BorderCenterX", double(0.0)),
+            ("BorderCenterZ", double(0.0)),
+            ("BorderSize", double(59999968.0)),
+            ("CustomBossEvents", compound([])),
+            ("DataPacks", compound([
+                ("Disabled", list_([str_("bundle"), str_("trade_rebalance")])),
+                ("Enabled", list_([str_("vanilla")])),
+            ])),
+            ("DataVersion", int_(3953)),
+            ("DayTime", long(6000)),
+            ("Difficulty", byte(2)),
+            ("DifficultyLocked", byte(0)),
+            ("DragonFight", compound([
+                ("DragonKilled", byte(0)),
+                ("NeedsStateScanning", byte(1)),
+                ("PreviouslyKilled", byte(0)),
+            ])),
+            ("GameRules", compound([(name, str_(value)) for name, value in game_rules])),
+            ("GameType", int_(0)),
+            ("LastPlayed", long(1719400000000)),
+            ("LevelName", str_("New World")),
+            ("Player", player()),
+            ("ScheduledEvents", list_([])),
+            ("ServerBrands", list_([str_("vanilla")])),
+            ("SpawnAngle", float_(0.0)),
+            ("SpawnX", int_(0)),
+            ("SpawnY", int_(64)),
+            ("SpawnZ", int_(0)),
+            ("Time", long(24000)),
+            ("Version", compound([
+                ("Id", int_(3953)),
+                ("Name", str_("1.21")),
+                ("Series", str_("main")),
+                ("Snapshot", byte(0)),
+            ])),
+            ("WorldGenSettings", compound([
+                ("bonus_chest", byte(0)),
+                ("dimensions", compound([
+                    ("minecraft:overworld", compound([
+                        ("generator", compound([
+                            ("biome_source", compound([
+                                ("preset", str_("minecraft:overworld")),
+                                ("type", str_("minecraft:multi_noise")),
+                            ])),
+                            ("settings", str_("minecraft:overworld")),
+                            ("type", str_("minecraft:noise")),
+                        ])),
+                        ("type", str_("minecraft:overworld")),
+                    ])),
+                ])),
+                ("generate_features", byte(1)),
+                ("seed", long(-4172144997902289642)),
+            ])),
+            ("allowCommands", byte(0)),
+            ("clearWeatherTime", int_(0)),
+            ("hardcore", byte(0)),
+            ("initialized", byte(1)),
+            ("rainTime", int_(52311)),
+            ("raining", byte(0)),
+            ("thunderTime", int_(104201)),
+            ("thundering", byte(0)),
+            ("version", int_(19133)),
+        ])),
+    ])
+
+
+if __name__ == "__main__":
+    write("synthetic_level.dat", level())
+    write("synthetic_player.dat", player())
@@ -0,0 +1,78 @@
+use std::io::Cursor;
+
+use blocky_net::{
+    borrowed::{BorrowDecoder, BytesDecoder},
+    decoder::Decoder,
+    encoder::Encoder,
+    types::{LengthPrefixedVecU8, VarInt},
+};
+use bytes::{Bytes, BytesMut};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+/// Roughly the shape of a chunk data packet, a name followed by a large
+/// blob of section data.
+fn payload() -> Vec<u8> {
+    let mut buf = Vec::new();
+    "minecraft:overworld".encode(&mut buf).unwrap();
+    LengthPrefixedVecU8::<VarInt>::from(vec![0xAB; 16 * 1024])
+        .encode(&mut buf)
+        .unwrap();
+    buf
+}
+
+fn decode(c: &mut Criterion) {
+    let payload = payload();
+    let bytes = Bytes::from(payload.clone());
+
+    let mut group = c.benchmark_group("decode");
+
+    group.bench_function("cursor", |b| {
+        b.iter(|| {
+            let mut cursor = Cursor::new(black_box(&payload[..]));
+            let name = String::decode(&mut cursor).unwrap();
+            let data = LengthPrefixedVecU8::<VarInt>::decode(&mut cursor).unwrap();
+            (name, data)
+        })
+    });
+
+    group.bench_function("borrowed", |b| {
+        b.iter(|| {
+            let mut buf = black_box(&payload[..]);
+            let name = <&str>::decode_borrowed(&mut buf).unwrap();
+            let data = <&[u8]>::decode_borrowed(&mut buf).unwrap();
+            (name, data)
+        })
+    });
+
+    group.bench_function("bytes", |b| {
+        b.iter(|| {
+            let mut buf = black_box(&bytes).clone();
+            let name = String::decode_bytes(&mut buf).unwrap();
+            let data = Bytes::decode_bytes(&mut buf).unwrap();
+            (name, data)
+        })
+    });
+
+    group.finish();
+}
+
+fn encode(c: &mut Criterion) {
+    let data = Bytes::from(vec![0xAB; 16 * 1024]);
+
+    let mut group = c.benchmark_group("encode");
+
+    group.bench_function("vec", |b| b.iter(|| black_box(&data).to_bytes().unwrap()));
+
+    group.bench_function("bytes_mut", |b| {
+        let mut buf = BytesMut::new();
+        b.iter(|| {
+            buf.clear();
+            black_box(&data).encode_into(&mut buf).unwrap();
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, decode, encode);
+criterion_main!(benches);
@@ -0,0 +1,334 @@
+use std::future::Future;
+
+use blocky_world::{
+    position::{BlockPosition, ChunkPosition},
+    resources::ResourceLocation,
+};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use uuid::Uuid;
+
+use crate::{
+    decoder::Decoder,
+    encoder::Encoder,
+    error::{ProtocolError, Result},
+    frame::{Frame, MAX_FRAME_LENGTH},
+    types::{LengthPrefixedVecU8, VarInt, VarLong, VarNumReader, MAX_LENGTH},
+};
+
+/// The async counterpart of [`Decoder`], reading straight from an
+/// [`AsyncRead`] instead of a buffered frame.
+pub trait AsyncDecoder: Sized {
+    fn decode_async<R: AsyncRead + Unpin + Send>(
+        buf: &mut R,
+    ) -> impl Future<Output = Result<Self>> + Send;
+}
+
+/// The async counterpart of [`Encoder`], implemented for every encoder by
+/// encoding into a buffer and writing it in one go.
+pub trait AsyncEncoder {
+    fn encode_async<W: AsyncWrite + Unpin + Send>(
+        &self,
+        buf: &mut W,
+    ) -> impl Future<Output = Result<()>> + Send;
+}
+
+impl<E: Encoder + Sync> AsyncEncoder for E {
+    async fn encode_async<W: AsyncWrite + Unpin + Send>(&self, buf: &mut W) -> Result<()> {
+        let bytes = self.to_bytes()?;
+        buf.write_all(&bytes).await?;
+        Ok(())
+    }
+}
+
+impl AsyncDecoder for VarInt {
+    async fn decode_async<R: AsyncRead + Unpin + Send>(buf: &mut R) -> Result<Self> {
+        let mut reader = VarNumReader::<32>::default();
+
+        loop {
+            if let Some(value) = reader.push(buf.read_u8().await?)? {
+                return Ok(Self(value as i32));
+            }
+        }
+    }
+}
+
+impl AsyncDecoder for VarLong {
+    async fn decode_async<R: AsyncRead + Unpin + Send>(buf: &mut R) -> Result<Self> {
+        let mut reader = VarNumReader::<64>::default();
+
+        loop {
+            if let Some(value) = reader.push(buf.read_u8().await?)? {
+                return Ok(Self(value as i64));
+            }
+        }
+    }
+}
+
+impl AsyncDecoder for bool {
+    async fn decode_async<R: AsyncRead + Unpin + Send>(buf: &mut R) -> Result<Self> {
+        match buf.read_u8().await? {
+            0 => Ok(false),
+            1 => Ok(true),
+            byte => Err(ProtocolError::InvalidBool(byte)),
+        }
+    }
+}
+
+impl AsyncDecoder for String {
+    async fn decode_async<R: AsyncRead + Unpin + Send>(buf: &mut R) -> Result<Self> {
+        let length = VarInt::decode_async(buf).await?.0 as usize;
+
+        let max_length = i16::MAX as usize;
+        if length > max_length {
+            return Err(ProtocolError::StringTooLong {
+                len: length,
+                max: max_length,
+            });
+        }
+
+        let mut bytes = vec![0; length];
+        buf.read_exact(&mut bytes).await?;
+
+        Ok(String::from_utf8(bytes)?)
+    }
+}
+
+impl AsyncDecoder for Uuid {
+    async fn decode_async<R: AsyncRead + Unpin + Send>(buf: &mut R) -> Result<Self> {
+        Ok(Uuid::from_u128(buf.read_u128().await?))
+    }
+}
+
+impl AsyncDecoder for ResourceLocation {
+    async fn decode_async<R: AsyncRead + Unpin + Send>(buf: &mut R) -> Result<Self> {
+        Ok(String::decode_async(buf).await?.parse()?)
+    }
+}
+
+impl AsyncDecoder for ChunkPosition {
+    async fn decode_async<R: AsyncRead + Unpin + Send>(buf: &mut R) -> Result<Self> {
+        let x = buf.read_i32().await?;
+        let z = buf.read_i32().await?;
+
+        Ok(Self { x, z })
+    }
+}
+
+impl AsyncDecoder for BlockPosition {
+    async fn decode_async<R: AsyncRead + Unpin + Send>(buf: &mut R) -> Result<Self> {
+        Self::from_bytes(&buf.read_i64().await?.to_be_bytes())
+    }
+}
+
+impl AsyncDecoder for LengthPrefixedVecU8<VarInt> {
+    async fn decode_async<R: AsyncRead + Unpin + Send>(buf: &mut R) -> Result<Self> {
+        let length = read_length(buf, MAX_LENGTH).await?;
+        let mut data = vec![0; length];
+        buf.read_exact(&mut data).await?;
+        Ok(data.into())
+    }
+}
+
+impl<V: AsyncDecoder + Send> AsyncDecoder for Option<V> {
+    async fn decode_async<R: AsyncRead + Unpin + Send>(buf: &mut R) -> Result<Self> {
+        if bool::decode_async(buf).await? {
+            Ok(Some(V::decode_async(buf).await?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+macro_rules! impl_number_async_decoder {
+    ($($typ:ty => $read:ident),* $(,)?) => {
+        $(
+            impl AsyncDecoder for $typ {
+                async fn decode_async<R: AsyncRead + Unpin + Send>(buf: &mut R) -> Result<Self> {
+                    Ok(buf.$read().await?)
+                }
+            }
+        )*
+    };
+}
+
+impl_number_async_decoder!(
+    u8 => read_u8,
+    u16 => read_u16,
+    u32 => read_u32,
+    u64 => read_u64,
+    u128 => read_u128,
+    i8 => read_i8,
+    i16 => read_i16,
+    i32 => read_i32,
+    i64 => read_i64,
+    i128 => read_i128,
+    f32 => read_f32,
+    f64 => read_f64,
+);
+
+/// Reads a VarInt length, checking it before anything is allocated for it.
+async fn read_length<R: AsyncRead + Unpin + Send>(buf: &mut R, max_length: usize) -> Result<usize> {
+    let length = VarInt::decode_async(buf).await?;
+    let length =
+        usize::try_from(length.0).map_err(|_| ProtocolError::NegativeLength(length.0.into()))?;
+
+    if length > max_length {
+        return Err(ProtocolError::LengthExceeded {
+            len: length,
+            max: max_length,
+        });
+    }
+
+    Ok(length)
+}
+
+/// Reads a VarInt length and exactly that many bytes, then decodes them
+/// with the synchronous [`Decoder`]. This bridges any existing decoder to
+/// an async reader without blocking on a partially received value.
+pub async fn read_length_prefixed<D, R>(buf: &mut R, max_length: usize) -> Result<D>
+where
+    D: Decoder,
+    R: AsyncRead + Unpin + Send,
+{
+    let length = read_length(buf, max_length).await?;
+
+    let mut bytes = vec![0; length];
+    buf.read_exact(&mut bytes).await?;
+    D::from_bytes(&bytes)
+}
+
+/// Reads a single uncompressed and unencrypted frame straight from `buf`.
+pub async fn read_frame<R: AsyncRead + Unpin + Send>(buf: &mut R) -> Result<Frame> {
+    let length = read_length(buf, MAX_FRAME_LENGTH).await?;
+
+    let mut data = vec![0; length];
+    buf.read_exact(&mut data).await?;
+
+    let mut body = &data[..];
+    let id = VarInt::decode(&mut body)?;
+    let offset = length - body.len();
+
+    Ok(Frame::new(id.0, data.split_off(offset)))
+}
+
+/// Writes a single uncompressed and unencrypted frame to `buf`, failing
+/// like the frame encoder does if it is longer than [`MAX_FRAME_LENGTH`].
+pub async fn write_frame<W: AsyncWrite + Unpin + Send>(buf: &mut W, frame: &Frame) -> Result<()> {
+    if frame.byte_len() > MAX_FRAME_LENGTH {
+        return Err(ProtocolError::LengthExceeded {
+            len: frame.byte_len(),
+            max: MAX_FRAME_LENGTH,
+        });
+    }
+
+    let mut bytes = Vec::with_capacity(frame.byte_len() + 3);
+    VarInt(frame.byte_len() as i32).encode(&mut bytes)?;
+    VarInt(frame.id).encode(&mut bytes)?;
+    bytes.extend_from_slice(&frame.body);
+
+    buf.write_all(&bytes).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_varint_decode_async() {
+        let mut buf = &[0xFF, 0xFF, 0xFF, 0xFF, 0x07][..];
+        let varint = VarInt::decode_async(&mut buf).await.unwrap();
+        assert_eq!(varint.0, 2147483647);
+
+        let mut buf = &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01][..];
+        assert!(VarInt::decode_async(&mut buf).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_varlong_decode_async() {
+        let mut buf = &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01][..];
+        let varlong = VarLong::decode_async(&mut buf).await.unwrap();
+        assert_eq!(varlong.0, -1);
+    }
+
+    #[tokio::test]
+    async fn test_decode_async_matches_sync() {
+        let bytes = "hello".to_string().to_bytes().unwrap();
+        let mut buf = &bytes[..];
+        assert_eq!(String::decode_async(&mut buf).await.unwrap(), "hello");
+
+        let mut buf = &[0x01, 0x00, 0x00, 0x00, 0x2A][..];
+        assert_eq!(
+            Option::<i32>::decode_async(&mut buf).await.unwrap(),
+            Some(42)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_frame_async_round_trip() {
+        let (mut client, mut server) = tokio::io::duplex(64);
+
+        let frame = Frame::new(0x01, vec![7; 300]);
+        let writer = tokio::spawn(async move {
+            write_frame(&mut client, &frame).await.unwrap();
+            42u64.encode_async(&mut client).await.unwrap();
+        });
+
+        let frame = read_frame(&mut server).await.unwrap();
+        assert_eq!(frame, Frame::new(0x01, vec![7; 300]));
+        assert_eq!(u64::decode_async(&mut server).await.unwrap(), 42);
+
+        writer.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_write_frame_too_large() {
+        let mut bytes = Vec::new();
+
+        // the id takes up the last byte a frame may have
+        let frame = Frame::new(0x00, vec![0; MAX_FRAME_LENGTH - 1]);
+        write_frame(&mut bytes, &frame).await.unwrap();
+        assert_eq!(bytes.len(), 3 + MAX_FRAME_LENGTH);
+
+        let frame = Frame::new(0x00, vec![0; MAX_FRAME_LENGTH]);
+        assert!(matches!(
+            write_frame(&mut Vec::new(), &frame).await,
+            Err(ProtocolError::LengthExceeded { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_read_length_prefixed() {
+        let mut buf = &[0x02, 0x01, 0x00][..];
+        let value: u16 = read_length_prefixed(&mut buf, 16).await.unwrap();
+        assert_eq!(value, 0x0100);
+
+        let mut buf = &[0x7F][..];
+        assert!(read_length_prefixed::<u16, _>(&mut buf, 16).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_byte_vec_length_checked() {
+        let mut buf = &[0x02, 0x01, 0x02][..];
+        let bytes = LengthPrefixedVecU8::<VarInt>::decode_async(&mut buf)
+            .await
+            .unwrap();
+        assert_eq!(bytes.0, [0x01, 0x02]);
+
+        let mut buf = &[0xFF, 0xFF, 0xFF, 0xFF, 0x0F][..];
+        assert!(matches!(
+            LengthPrefixedVecU8::<VarInt>::decode_async(&mut buf).await,
+            Err(ProtocolError::NegativeLength(-1))
+        ));
+
+        // a length far past the limit fails before it is allocated
+        let mut buf = &[0xFF, 0xFF, 0xFF, 0xFF, 0x07][..];
+        assert!(matches!(
+            LengthPrefixedVecU8::<VarInt>::decode_async(&mut buf).await,
+            Err(ProtocolError::LengthExceeded {
+                len: 2147483647,
+                max: MAX_LENGTH
+            })
+        ));
+    }
+}
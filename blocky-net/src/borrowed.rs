@@ -0,0 +1,342 @@
+use std::io::Write;
+
+use bytes::{Buf, Bytes};
+
+use crate::{
+    decoder::Decoder,
+    encoder::Encoder,
+    error::{ProtocolError, Result},
+    types::VarInt,
+    version::ProtocolVersion,
+};
+
+/// Decodes a value that can borrow from the buffer it is decoded from, so
+/// byte arrays and strings are handed out without copying them.
+///
+/// Every [`Decoder`] is a `BorrowDecoder` too, decoding from the slice and
+/// advancing it past the bytes it read.
+pub trait BorrowDecoder<'a>: Sized {
+    fn decode_borrowed(buf: &mut &'a [u8]) -> Result<Self>;
+
+    /// Decodes a value with the fields it has in `version`, see
+    /// [`Decoder::decode_for`].
+    fn decode_borrowed_for(version: ProtocolVersion, buf: &mut &'a [u8]) -> Result<Self> {
+        let _ = version;
+        Self::decode_borrowed(buf)
+    }
+
+    fn from_slice(bytes: &'a [u8]) -> Result<Self> {
+        let mut buf = bytes;
+        Self::decode_borrowed(&mut buf)
+    }
+}
+
+impl<'a, D: Decoder> BorrowDecoder<'a> for D {
+    fn decode_borrowed(buf: &mut &'a [u8]) -> Result<Self> {
+        D::decode(buf)
+    }
+
+    fn decode_borrowed_for(version: ProtocolVersion, buf: &mut &'a [u8]) -> Result<Self> {
+        D::decode_for(version, buf)
+    }
+}
+
+impl<'a> BorrowDecoder<'a> for &'a [u8] {
+    fn decode_borrowed(buf: &mut &'a [u8]) -> Result<Self> {
+        let length = VarInt::decode(buf)?.0 as usize;
+        take(buf, length)
+    }
+}
+
+impl<'a> BorrowDecoder<'a> for &'a str {
+    fn decode_borrowed(buf: &mut &'a [u8]) -> Result<Self> {
+        let length = VarInt::decode(buf)?.0 as usize;
+
+        let max_length = i16::MAX as usize;
+        if length > max_length {
+            return Err(ProtocolError::StringTooLong {
+                len: length,
+                max: max_length,
+            });
+        }
+
+        Ok(std::str::from_utf8(take(buf, length)?)?)
+    }
+}
+
+/// Decodes a boolean prefixed optional value that may borrow from `buf`. The
+/// `Option<V>` decoder only covers owned values, so the derives use this for
+/// `Option` fields of borrowed structs.
+pub fn decode_option<'a, V: BorrowDecoder<'a>>(buf: &mut &'a [u8]) -> Result<Option<V>> {
+    if bool::decode(buf)? {
+        Ok(Some(V::decode_borrowed(buf)?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// [`decode_option`] with the fields the value has in `version`.
+pub fn decode_option_for<'a, V: BorrowDecoder<'a>>(
+    version: ProtocolVersion,
+    buf: &mut &'a [u8],
+) -> Result<Option<V>> {
+    if bool::decode(buf)? {
+        Ok(Some(V::decode_borrowed_for(version, buf)?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Takes every byte left in `buf`, the borrowed counterpart of
+/// [`Rest`](crate::codec::Rest). The derives use this for `#[rest]` fields of
+/// borrowed structs that are slices.
+pub fn decode_rest<'a>(buf: &mut &'a [u8]) -> Result<&'a [u8]> {
+    Ok(std::mem::take(buf))
+}
+
+/// The bytes making up the rest of a frame without a length prefix, borrowed
+/// from it rather than copied into a
+/// [`LengthInferredVecU8`](crate::types::LengthInferredVecU8).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LengthInferredSlice<'a>(pub &'a [u8]);
+
+impl<'a> BorrowDecoder<'a> for LengthInferredSlice<'a> {
+    fn decode_borrowed(buf: &mut &'a [u8]) -> Result<Self> {
+        decode_rest(buf).map(Self)
+    }
+}
+
+impl Encoder for LengthInferredSlice<'_> {
+    fn byte_len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn encode<T: Write>(&self, buf: &mut T) -> Result<()> {
+        buf.write_all(self.0)?;
+        Ok(())
+    }
+}
+
+/// Splits `length` bytes off the front of `buf`.
+fn take<'a>(buf: &mut &'a [u8], length: usize) -> Result<&'a [u8]> {
+    if buf.len() < length {
+        return Err(ProtocolError::UnexpectedEof);
+    }
+
+    let (bytes, rest) = buf.split_at(length);
+    *buf = rest;
+    Ok(bytes)
+}
+
+/// Decodes a value from a reference counted [`Bytes`] buffer, letting byte
+/// arrays share the allocation of the frame they were read from.
+///
+/// Every [`Decoder`] is a `BytesDecoder` too, decoding from the buffer and
+/// advancing it past the bytes it read.
+pub trait BytesDecoder: Sized {
+    fn decode_bytes(buf: &mut Bytes) -> Result<Self>;
+
+    /// Decodes a value with the fields it has in `version`, see
+    /// [`Decoder::decode_for`].
+    fn decode_bytes_for(version: ProtocolVersion, buf: &mut Bytes) -> Result<Self> {
+        let _ = version;
+        Self::decode_bytes(buf)
+    }
+}
+
+impl<D: Decoder> BytesDecoder for D {
+    fn decode_bytes(buf: &mut Bytes) -> Result<Self> {
+        let mut slice = &buf[..];
+        let value = D::decode(&mut slice)?;
+
+        let read = buf.len() - slice.len();
+        buf.advance(read);
+
+        Ok(value)
+    }
+
+    fn decode_bytes_for(version: ProtocolVersion, buf: &mut Bytes) -> Result<Self> {
+        let mut slice = &buf[..];
+        let value = D::decode_for(version, &mut slice)?;
+
+        let read = buf.len() - slice.len();
+        buf.advance(read);
+
+        Ok(value)
+    }
+}
+
+/// The bytes making up the rest of a frame without a length prefix, sharing
+/// the allocation of the frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LengthInferredBytes(pub Bytes);
+
+impl BytesDecoder for LengthInferredBytes {
+    fn decode_bytes(buf: &mut Bytes) -> Result<Self> {
+        Ok(Self(std::mem::take(buf)))
+    }
+}
+
+impl Encoder for LengthInferredBytes {
+    fn byte_len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn encode<T: Write>(&self, buf: &mut T) -> Result<()> {
+        buf.write_all(&self.0)?;
+        Ok(())
+    }
+}
+
+impl BytesDecoder for Bytes {
+    fn decode_bytes(buf: &mut Bytes) -> Result<Self> {
+        let length = VarInt::decode_bytes(buf)?.0 as usize;
+
+        if buf.len() < length {
+            return Err(ProtocolError::UnexpectedEof);
+        }
+
+        Ok(buf.split_to(length))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use blocky_derive::{Decoder, Encoder};
+    use bytes::BytesMut;
+
+    use super::*;
+    use crate::encoder::Encoder;
+
+    #[derive(Encoder, Decoder, Debug, PartialEq)]
+    struct ChatView<'a> {
+        sender: &'a str,
+        #[varint]
+        kind: i32,
+        signature: Option<&'a [u8]>,
+        #[rest]
+        data: Vec<u8>,
+    }
+
+    #[test]
+    fn test_derive_borrowed() {
+        let view = ChatView {
+            sender: "blocky",
+            kind: 2,
+            signature: Some(&[1, 2, 3]),
+            data: vec![9],
+        };
+
+        let bytes = view.to_bytes().unwrap();
+        assert_eq!(view.byte_len(), bytes.len());
+
+        let decoded = ChatView::from_slice(&bytes).unwrap();
+        assert_eq!(decoded, view);
+        assert_eq!(decoded.sender.as_ptr(), bytes[1..].as_ptr());
+    }
+
+    #[test]
+    fn test_derive_borrowed_error_offset() {
+        // the signature claims 4 bytes but holds 1
+        let bytes = [0x01, b'a', 0x02, 0x01, 0x04, 0x09];
+        let err = ChatView::from_slice(&bytes).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ChatView.signature at byte 3: unexpected end of input"
+        );
+    }
+
+    #[derive(Encoder, Decoder, Debug, PartialEq)]
+    struct PayloadView<'a> {
+        channel: &'a str,
+        #[rest]
+        data: &'a [u8],
+    }
+
+    #[test]
+    fn test_derive_borrowed_rest() {
+        let view = PayloadView {
+            channel: "a:b",
+            data: &[1, 2, 3],
+        };
+
+        let bytes = view.to_bytes().unwrap();
+        assert_eq!(bytes, [0x03, b'a', b':', b'b', 0x01, 0x02, 0x03]);
+        assert_eq!(view.byte_len(), bytes.len());
+
+        let decoded = PayloadView::from_slice(&bytes).unwrap();
+        assert_eq!(decoded, view);
+        assert_eq!(decoded.data.as_ptr(), bytes[4..].as_ptr());
+
+        let decoded = PayloadView::from_slice(&bytes[..4]).unwrap();
+        assert!(decoded.data.is_empty());
+    }
+
+    #[test]
+    fn test_decode_length_inferred() {
+        let bytes = [0x07, 0x01, 0x02];
+
+        let mut buf = &bytes[..];
+        assert_eq!(u8::decode_borrowed(&mut buf).unwrap(), 7);
+        let rest = LengthInferredSlice::decode_borrowed(&mut buf).unwrap();
+        assert_eq!(rest.0, [0x01, 0x02]);
+        assert_eq!(rest.0.as_ptr(), bytes[1..].as_ptr());
+        assert!(buf.is_empty());
+        assert_eq!(rest.to_bytes().unwrap(), [0x01, 0x02]);
+
+        let mut buf = Bytes::copy_from_slice(&bytes);
+        let start = buf.as_ptr();
+        assert_eq!(u8::decode_bytes(&mut buf).unwrap(), 7);
+        let rest = LengthInferredBytes::decode_bytes(&mut buf).unwrap();
+        assert_eq!(&rest.0[..], [0x01, 0x02]);
+        assert_eq!(rest.0.as_ptr(), start.wrapping_add(1));
+        assert!(buf.is_empty());
+        assert_eq!(rest.byte_len(), 2);
+    }
+
+    #[test]
+    fn test_decode_borrowed() {
+        let mut bytes = BytesMut::new();
+        "hello".encode_into(&mut bytes).unwrap();
+        (&[1u8, 2, 3][..]).encode_into(&mut bytes).unwrap();
+        7u16.encode_into(&mut bytes).unwrap();
+
+        let mut buf = &bytes[..];
+        let s = <&str>::decode_borrowed(&mut buf).unwrap();
+        let data = <&[u8]>::decode_borrowed(&mut buf).unwrap();
+        let n = u16::decode_borrowed(&mut buf).unwrap();
+
+        assert_eq!(s, "hello");
+        assert_eq!(data, &[1, 2, 3]);
+        assert_eq!(n, 7);
+        assert!(buf.is_empty());
+
+        // the string points into the original buffer rather than a copy
+        assert_eq!(s.as_ptr(), bytes[1..].as_ptr());
+    }
+
+    #[test]
+    fn test_decode_borrowed_truncated() {
+        let mut buf = &[0x05, b'h', b'i'][..];
+        assert!(<&str>::decode_borrowed(&mut buf).is_err());
+    }
+
+    #[test]
+    fn test_decode_bytes() {
+        let mut bytes = BytesMut::new();
+        Bytes::from_static(&[9; 32])
+            .encode_into(&mut bytes)
+            .unwrap();
+        true.encode_into(&mut bytes).unwrap();
+
+        let mut buf = bytes.freeze();
+        let start = buf.as_ptr();
+
+        let data = Bytes::decode_bytes(&mut buf).unwrap();
+        assert_eq!(&data[..], &[9; 32]);
+        assert_eq!(data.as_ptr(), start.wrapping_add(1));
+
+        assert!(bool::decode_bytes(&mut buf).unwrap());
+        assert!(buf.is_empty());
+    }
+}
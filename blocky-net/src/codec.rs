@@ -0,0 +1,800 @@
+use std::{
+    io::{Read, Write},
+    marker::PhantomData,
+};
+
+use blocky_world::resources::ResourceLocation;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    decoder::{CountingReader, Decoder},
+    encoder::Encoder,
+    error::{ProtocolError, Result},
+    types::{LengthPrefix, VarInt, VarLong, MAX_LENGTH},
+    version::ProtocolVersion,
+};
+
+/// The longest JSON string the server sends, such as a chat component.
+pub const MAX_JSON_LENGTH: usize = 262144;
+
+/// Encodes a `T` in a different way than its own [`Encoder`] does. These are
+/// what the field attributes of the derives map to, so a field can be a plain
+/// Rust type while being encoded the way the protocol wants it.
+pub trait EncodeAs<T> {
+    fn byte_len(value: &T) -> usize;
+
+    fn encode<W: Write>(value: &T, buf: &mut W) -> Result<()>;
+
+    /// Encodes with the fields `value` has in `version`, see
+    /// [`Encoder::encode_for`].
+    fn encode_for<W: Write>(value: &T, version: ProtocolVersion, buf: &mut W) -> Result<()> {
+        let _ = version;
+        Self::encode(value, buf)
+    }
+}
+
+/// Decodes a `T` in a different way than its own [`Decoder`] does.
+pub trait DecodeAs<T> {
+    fn decode<R: Read>(buf: &mut R) -> Result<T>;
+
+    /// Decodes from a reader that is already counting, passing it on to
+    /// the values inside, see [`Decoder::decode_counted`].
+    fn decode_counted<R: Read>(buf: &mut CountingReader<R>) -> Result<T> {
+        Self::decode(buf)
+    }
+
+    /// Decodes with the fields the value has in `version`, see
+    /// [`Decoder::decode_for`].
+    fn decode_for<R: Read>(version: ProtocolVersion, buf: &mut R) -> Result<T> {
+        Self::decode_counted(&mut CountingReader::for_version(buf, version))
+    }
+}
+
+/// Uses the type's own [`Encoder`] and [`Decoder`], the element codec of
+/// [`Prefixed`] unless another one is given.
+pub struct Inherent;
+
+impl<T: Encoder> EncodeAs<T> for Inherent {
+    fn byte_len(value: &T) -> usize {
+        value.byte_len()
+    }
+
+    fn encode<W: Write>(value: &T, buf: &mut W) -> Result<()> {
+        value.encode(buf)
+    }
+
+    fn encode_for<W: Write>(value: &T, version: ProtocolVersion, buf: &mut W) -> Result<()> {
+        value.encode_for(version, buf)
+    }
+}
+
+impl<T: Decoder> DecodeAs<T> for Inherent {
+    fn decode<R: Read>(buf: &mut R) -> Result<T> {
+        T::decode(buf)
+    }
+
+    fn decode_counted<R: Read>(buf: &mut CountingReader<R>) -> Result<T> {
+        T::decode_counted(buf)
+    }
+}
+
+/// An `i32` encoded as a [`VarInt`], for `#[varint]`.
+pub struct AsVarInt;
+
+impl EncodeAs<i32> for AsVarInt {
+    fn byte_len(value: &i32) -> usize {
+        VarInt(*value).byte_len()
+    }
+
+    fn encode<W: Write>(value: &i32, buf: &mut W) -> Result<()> {
+        VarInt(*value).encode(buf)
+    }
+}
+
+impl DecodeAs<i32> for AsVarInt {
+    fn decode<R: Read>(buf: &mut R) -> Result<i32> {
+        Ok(VarInt::decode(buf)?.0)
+    }
+}
+
+/// An `i64` encoded as a [`VarLong`], for `#[varlong]`.
+pub struct AsVarLong;
+
+impl EncodeAs<i64> for AsVarLong {
+    fn byte_len(value: &i64) -> usize {
+        VarLong(*value).byte_len()
+    }
+
+    fn encode<W: Write>(value: &i64, buf: &mut W) -> Result<()> {
+        VarLong(*value).encode(buf)
+    }
+}
+
+impl DecodeAs<i64> for AsVarLong {
+    fn decode<R: Read>(buf: &mut R) -> Result<i64> {
+        Ok(VarLong::decode(buf)?.0)
+    }
+}
+
+/// A `Vec<T>` prefixed with its length as an `L`, each element encoded with
+/// `C`, for `#[prefixed(L)]`.
+pub struct Prefixed<L, C = Inherent>(PhantomData<(L, C)>);
+
+impl<L, C, T> EncodeAs<Vec<T>> for Prefixed<L, C>
+where
+    L: Encoder + LengthPrefix,
+    C: EncodeAs<T>,
+{
+    fn byte_len(value: &Vec<T>) -> usize {
+        L::from_len(value.len()).byte_len() + value.iter().map(C::byte_len).sum::<usize>()
+    }
+
+    fn encode<W: Write>(value: &Vec<T>, buf: &mut W) -> Result<()> {
+        L::from_len(value.len()).encode(buf)?;
+
+        for (i, item) in value.iter().enumerate() {
+            C::encode(item, buf).map_err(|e| e.at_index(i))?;
+        }
+
+        Ok(())
+    }
+
+    fn encode_for<W: Write>(value: &Vec<T>, version: ProtocolVersion, buf: &mut W) -> Result<()> {
+        L::from_len(value.len()).encode(buf)?;
+
+        for (i, item) in value.iter().enumerate() {
+            C::encode_for(item, version, buf).map_err(|e| e.at_index(i))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<L, C, T> DecodeAs<Vec<T>> for Prefixed<L, C>
+where
+    L: Decoder + LengthPrefix,
+    C: DecodeAs<T>,
+{
+    fn decode<R: Read>(buf: &mut R) -> Result<Vec<T>> {
+        Self::decode_counted(&mut CountingReader::new(buf))
+    }
+
+    fn decode_counted<R: Read>(buf: &mut CountingReader<R>) -> Result<Vec<T>> {
+        let start = buf.position();
+        let len = decode_length::<L, _>(buf)?;
+
+        let mut v = Vec::with_capacity(len);
+        for i in 0..len {
+            let offset = buf.position() - start;
+            v.push(C::decode_counted(buf).map_err(|e| e.at_index(i).at_offset(offset))?);
+        }
+
+        Ok(v)
+    }
+}
+
+/// A `Vec<u8>` prefixed with its length as an `L`, copied in one go rather
+/// than byte by byte. The derives use this for `#[prefixed(L)]` on `Vec<u8>`.
+pub struct PrefixedBytes<L>(PhantomData<L>);
+
+impl<L: Encoder + LengthPrefix> EncodeAs<Vec<u8>> for PrefixedBytes<L> {
+    fn byte_len(value: &Vec<u8>) -> usize {
+        L::from_len(value.len()).byte_len() + value.len()
+    }
+
+    fn encode<W: Write>(value: &Vec<u8>, buf: &mut W) -> Result<()> {
+        L::from_len(value.len()).encode(buf)?;
+        buf.write_all(value)?;
+        Ok(())
+    }
+}
+
+impl<L: Decoder + LengthPrefix> DecodeAs<Vec<u8>> for PrefixedBytes<L> {
+    fn decode<R: Read>(buf: &mut R) -> Result<Vec<u8>> {
+        let len = decode_length::<L, R>(buf)?;
+
+        let mut data = vec![0; len];
+        buf.read_exact(&mut data)?;
+        Ok(data)
+    }
+}
+
+/// Reads a length prefix, refusing negative lengths and any longer than
+/// [`MAX_LENGTH`] before anything is allocated for them.
+pub fn decode_length<L: Decoder + LengthPrefix, R: Read>(buf: &mut R) -> Result<usize> {
+    let len = L::decode(buf)?.checked_len()?;
+    if len > MAX_LENGTH {
+        return Err(ProtocolError::LengthExceeded {
+            len,
+            max: MAX_LENGTH,
+        });
+    }
+
+    Ok(len)
+}
+
+/// A string of at most `N` characters, for `#[max_len = N]`.
+pub struct MaxLen<const N: usize>;
+
+impl<const N: usize> EncodeAs<String> for MaxLen<N> {
+    fn byte_len(value: &String) -> usize {
+        value.byte_len()
+    }
+
+    fn encode<W: Write>(value: &String, buf: &mut W) -> Result<()> {
+        check_string_length(value, N)?;
+        value.encode(buf)
+    }
+}
+
+impl<const N: usize> DecodeAs<String> for MaxLen<N> {
+    fn decode<R: Read>(buf: &mut R) -> Result<String> {
+        let value = read_string(buf, N)?;
+        check_string_length(&value, N)?;
+        Ok(value)
+    }
+}
+
+/// Reads a VarInt prefixed string, refusing to allocate for more bytes than
+/// `max` characters could take up.
+fn read_string<R: Read>(buf: &mut R, max: usize) -> Result<String> {
+    let length = VarInt::decode(buf)?;
+    let length =
+        usize::try_from(length.0).map_err(|_| ProtocolError::NegativeLength(length.0.into()))?;
+
+    // a character takes up to three bytes as UTF-8 but counts as one UTF-16 unit
+    let max_bytes = max * 3;
+    if length > max_bytes {
+        return Err(ProtocolError::StringTooLong {
+            len: length,
+            max: max_bytes,
+        });
+    }
+
+    let mut bytes = vec![0; length];
+    buf.read_exact(&mut bytes)?;
+    Ok(String::from_utf8(bytes)?)
+}
+
+/// Checks the length of a string the way the server does, in UTF-16 units.
+fn check_string_length(value: &str, max: usize) -> Result<()> {
+    let len = value.encode_utf16().count();
+    if len > max {
+        return Err(ProtocolError::StringTooLong { len, max });
+    }
+
+    Ok(())
+}
+
+/// Every remaining byte of the frame without a length prefix, for `#[rest]`.
+/// It can only be used on the last field, which borrowed structs can make a
+/// slice, see [`decode_rest`](crate::borrowed::decode_rest).
+pub struct Rest;
+
+impl<T: AsRef<[u8]>> EncodeAs<T> for Rest {
+    fn byte_len(value: &T) -> usize {
+        value.as_ref().len()
+    }
+
+    fn encode<W: Write>(value: &T, buf: &mut W) -> Result<()> {
+        buf.write_all(value.as_ref())?;
+        Ok(())
+    }
+}
+
+impl DecodeAs<Vec<u8>> for Rest {
+    fn decode<R: Read>(buf: &mut R) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        buf.read_to_end(&mut data)?;
+        Ok(data)
+    }
+}
+
+/// Reads a resource location and returns it in full, with the default
+/// namespace added if it was left out. The derives use this to match the
+/// variants of an enum with `#[discriminant(identifier)]`.
+pub fn decode_identifier<R: Read>(buf: &mut R) -> Result<String> {
+    Ok(ResourceLocation::decode(buf)?.to_string())
+}
+
+/// A value serialized as JSON inside a string, for `#[json]`.
+pub struct Json;
+
+impl<T: Serialize> EncodeAs<T> for Json {
+    fn byte_len(value: &T) -> usize {
+        // counted as it is written rather than kept, a value that fails to
+        // serialize fails in encode anyway
+        let mut counter = ByteCounter(0);
+        match serde_json::to_writer(&mut counter, value) {
+            Ok(()) => VarInt(counter.0 as i32).byte_len() + counter.0,
+            Err(_) => 0,
+        }
+    }
+
+    fn encode<W: Write>(value: &T, buf: &mut W) -> Result<()> {
+        let json = serde_json::to_string(value)?;
+        check_string_length(&json, MAX_JSON_LENGTH)?;
+
+        // longer than a plain string may be, so it is written by hand
+        VarInt(json.len() as i32).encode(buf)?;
+        buf.write_all(json.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Counts the bytes written to it and drops them.
+struct ByteCounter(usize);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<T: DeserializeOwned> DecodeAs<T> for Json {
+    fn decode<R: Read>(buf: &mut R) -> Result<T> {
+        let json = read_string(buf, MAX_JSON_LENGTH)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+}
+
+/// An `Option<T>` with a boolean saying whether it is present, the value
+/// encoded with `C`. The derives wrap the codec of an `Option` field in this,
+/// so the attributes apply to the value inside it.
+pub struct Optional<C>(PhantomData<C>);
+
+impl<C: EncodeAs<T>, T> EncodeAs<Option<T>> for Optional<C> {
+    fn byte_len(value: &Option<T>) -> usize {
+        1 + value.as_ref().map_or(0, C::byte_len)
+    }
+
+    fn encode<W: Write>(value: &Option<T>, buf: &mut W) -> Result<()> {
+        value.is_some().encode(buf)?;
+
+        match value {
+            Some(value) => C::encode(value, buf),
+            None => Ok(()),
+        }
+    }
+
+    fn encode_for<W: Write>(
+        value: &Option<T>,
+        version: ProtocolVersion,
+        buf: &mut W,
+    ) -> Result<()> {
+        value.is_some().encode(buf)?;
+
+        match value {
+            Some(value) => C::encode_for(value, version, buf),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<C: DecodeAs<T>, T> DecodeAs<Option<T>> for Optional<C> {
+    fn decode<R: Read>(buf: &mut R) -> Result<Option<T>> {
+        if bool::decode(buf)? {
+            Ok(Some(C::decode(buf)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn decode_counted<R: Read>(buf: &mut CountingReader<R>) -> Result<Option<T>> {
+        if bool::decode(buf)? {
+            Ok(Some(C::decode_counted(buf)?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use blocky_derive::{Decoder, Encoder};
+
+    use super::*;
+
+    fn round_trip<C, T>(value: &T) -> (Vec<u8>, T)
+    where
+        C: EncodeAs<T> + DecodeAs<T>,
+    {
+        let mut bytes = Vec::new();
+        C::encode(value, &mut bytes).unwrap();
+        assert_eq!(C::byte_len(value), bytes.len());
+
+        let mut buf = &bytes[..];
+        let decoded = C::decode(&mut buf).unwrap();
+        assert!(buf.is_empty());
+
+        (bytes, decoded)
+    }
+
+    #[test]
+    fn test_varint_codec() {
+        let (bytes, value) = round_trip::<AsVarInt, _>(&300);
+        assert_eq!(bytes, [0xAC, 0x02]);
+        assert_eq!(value, 300);
+    }
+
+    #[test]
+    fn test_prefixed_codec() {
+        let (bytes, value) = round_trip::<Prefixed<VarInt, AsVarInt>, _>(&vec![1, 300]);
+        assert_eq!(bytes, [0x02, 0x01, 0xAC, 0x02]);
+        assert_eq!(value, [1, 300]);
+
+        let (bytes, value) = round_trip::<PrefixedBytes<u16>, _>(&vec![7, 8]);
+        assert_eq!(bytes, [0x00, 0x02, 0x07, 0x08]);
+        assert_eq!(value, [7, 8]);
+    }
+
+    #[test]
+    fn test_max_len_codec() {
+        let (_, value) = round_trip::<MaxLen<3>, _>(&"ééé".to_string());
+        assert_eq!(value, "ééé");
+
+        let mut bytes = Vec::new();
+        assert!(matches!(
+            MaxLen::<3>::encode(&"four".to_string(), &mut bytes),
+            Err(ProtocolError::StringTooLong { len: 4, max: 3 })
+        ));
+
+        let mut buf = &[0x04, b'f', b'o', b'u', b'r'][..];
+        assert!(matches!(
+            <MaxLen<3> as DecodeAs<String>>::decode(&mut buf),
+            Err(ProtocolError::StringTooLong { len: 4, max: 3 })
+        ));
+    }
+
+    #[test]
+    fn test_json_codec() {
+        let value = serde_json::json!({ "text": "hello" });
+        let (bytes, decoded) = round_trip::<Json, _>(&value);
+        assert_eq!(&bytes[1..], br#"{"text":"hello"}"#);
+        assert_eq!(decoded, value);
+
+        // long enough for a two byte prefix
+        let value = serde_json::json!({ "text": "é".repeat(100) });
+        let (bytes, decoded) = round_trip::<Json, _>(&value);
+        assert_eq!(bytes[..2], [0xD3, 0x01]);
+        assert_eq!(decoded, value);
+    }
+
+    #[test]
+    fn test_optional_codec() {
+        let (bytes, value) = round_trip::<Optional<AsVarInt>, _>(&Some(300));
+        assert_eq!(bytes, [0x01, 0xAC, 0x02]);
+        assert_eq!(value, Some(300));
+
+        let (bytes, value) = round_trip::<Optional<AsVarInt>, _>(&None);
+        assert_eq!(bytes, [0x00]);
+        assert_eq!(value, None);
+    }
+
+    #[derive(Encoder, Decoder, Debug, PartialEq)]
+    struct Attributes {
+        #[varint]
+        id: i32,
+        #[max_len = 16]
+        name: String,
+        #[prefixed(VarInt)]
+        #[varint]
+        ids: Vec<i32>,
+        #[prefixed(VarInt)]
+        payload: Option<Vec<u8>>,
+        #[json]
+        extra: serde_json::Value,
+        #[rest]
+        data: Vec<u8>,
+    }
+
+    #[test]
+    fn test_derive_field_attributes() {
+        let value = Attributes {
+            id: 300,
+            name: "ab".to_string(),
+            ids: vec![1, 2],
+            payload: Some(vec![9]),
+            extra: serde_json::json!(1),
+            data: vec![4, 5],
+        };
+
+        let bytes = value.to_bytes().unwrap();
+        assert_eq!(value.byte_len(), bytes.len());
+        assert_eq!(
+            bytes,
+            [
+                0xAC, 0x02, 0x02, b'a', b'b', 0x02, 0x01, 0x02, 0x01, 0x01, 0x09, 0x01, b'1', 0x04,
+                0x05
+            ]
+        );
+        assert_eq!(Attributes::from_bytes(&bytes).unwrap(), value);
+    }
+
+    #[test]
+    fn test_derive_max_len_error() {
+        let mut bytes = vec![0x01];
+        "a".repeat(17).encode(&mut bytes).unwrap();
+
+        let err = Attributes::from_bytes(&bytes).unwrap_err();
+        assert!(matches!(
+            err.root(),
+            ProtocolError::StringTooLong { len: 17, max: 16 }
+        ));
+        assert_eq!(err.path().unwrap().to_string(), "Attributes.name");
+    }
+
+    #[derive(Encoder, Decoder, Debug, PartialEq)]
+    enum Action {
+        Start,
+        Move {
+            #[varint]
+            distance: i32,
+        },
+        #[discriminant = 5]
+        Stop,
+        Say(String, bool),
+    }
+
+    #[derive(Encoder, Decoder, Debug, PartialEq)]
+    #[discriminant(u8)]
+    enum Hand {
+        #[discriminant = 0x10]
+        Main,
+        Off,
+    }
+
+    #[derive(Encoder, Decoder, Debug, PartialEq)]
+    #[discriminant(identifier)]
+    enum Particle {
+        Flame,
+        DustColorTransition(f32),
+        #[discriminant = "blocky:spark"]
+        Spark,
+    }
+
+    fn assert_round_trip<T: Encoder + Decoder + PartialEq + std::fmt::Debug>(
+        value: T,
+        bytes: &[u8],
+    ) {
+        assert_eq!(value.to_bytes().unwrap(), bytes);
+        assert_eq!(value.byte_len(), bytes.len());
+        assert_eq!(T::from_bytes(bytes).unwrap(), value);
+    }
+
+    #[test]
+    fn test_derive_enum() {
+        assert_round_trip(Action::Start, &[0x00]);
+        assert_round_trip(Action::Move { distance: 300 }, &[0x01, 0xAC, 0x02]);
+        assert_round_trip(Action::Stop, &[0x05]);
+        assert_round_trip(
+            Action::Say("a".to_string(), true),
+            &[0x06, 0x01, b'a', 0x01],
+        );
+
+        assert_round_trip(Hand::Main, &[0x10]);
+        assert_round_trip(Hand::Off, &[0x11]);
+    }
+
+    #[test]
+    fn test_derive_enum_identifier() {
+        let mut bytes = Vec::new();
+        "minecraft:dust_color_transition"
+            .encode(&mut bytes)
+            .unwrap();
+        bytes.extend_from_slice(&1.0f32.to_be_bytes());
+        assert_round_trip(Particle::DustColorTransition(1.0), &bytes);
+
+        let mut bytes = Vec::new();
+        "blocky:spark".encode(&mut bytes).unwrap();
+        assert_round_trip(Particle::Spark, &bytes);
+
+        // the namespace may be left out when decoding
+        assert_eq!(
+            Particle::from_bytes(&[0x05, b'f', b'l', b'a', b'm', b'e']).unwrap(),
+            Particle::Flame
+        );
+    }
+
+    #[test]
+    fn test_derive_enum_invalid_discriminant() {
+        let err = Hand::from_bytes(&[0x02]).unwrap_err();
+        assert!(matches!(
+            err.root(),
+            ProtocolError::InvalidDiscriminant { ty: "Hand", value } if value == "2"
+        ));
+
+        let err = Action::from_bytes(&[0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Action::Move.distance at byte 1: VarInt is too long"
+        );
+    }
+
+    #[derive(Encoder, Decoder, Debug, PartialEq)]
+    struct Icon {
+        has_icon: bool,
+        #[present_if = "self.has_icon"]
+        #[prefixed(VarInt)]
+        icon: Option<Vec<u8>>,
+        #[varint]
+        columns: i32,
+        #[present_if = "self.columns > 0 && self.has_icon"]
+        rows: Option<u8>,
+    }
+
+    #[test]
+    fn test_derive_present_if() {
+        assert_round_trip(
+            Icon {
+                has_icon: true,
+                icon: Some(vec![1, 2]),
+                columns: 1,
+                rows: Some(3),
+            },
+            &[0x01, 0x02, 0x01, 0x02, 0x01, 0x03],
+        );
+        assert_round_trip(
+            Icon {
+                has_icon: false,
+                icon: None,
+                columns: 1,
+                rows: None,
+            },
+            &[0x00, 0x01],
+        );
+
+        let err = Icon {
+            has_icon: false,
+            icon: Some(vec![1]),
+            columns: 0,
+            rows: None,
+        }
+        .to_bytes()
+        .unwrap_err();
+        assert!(matches!(err.root(), ProtocolError::PresenceMismatch));
+        assert_eq!(err.path().unwrap().to_string(), "Icon.icon");
+    }
+
+    #[derive(Encoder, Decoder, Debug, PartialEq)]
+    #[bitmask(u8)]
+    struct Actions {
+        #[bit = 0]
+        name: Option<String>,
+        #[varint]
+        ping: i32,
+        #[bit = 3]
+        #[varint]
+        game_mode: Option<i32>,
+    }
+
+    #[derive(Encoder, Decoder, Debug, PartialEq)]
+    enum Update {
+        #[bitmask(VarInt)]
+        Entity(#[bit = 1] Option<bool>, #[bit = 0] Option<u8>),
+    }
+
+    #[test]
+    fn test_derive_bitmask() {
+        assert_round_trip(
+            Actions {
+                name: Some("a".to_string()),
+                ping: 5,
+                game_mode: Some(2),
+            },
+            &[0b1001, 0x01, b'a', 0x05, 0x02],
+        );
+        assert_round_trip(
+            Actions {
+                name: None,
+                ping: 5,
+                game_mode: Some(2),
+            },
+            &[0b1000, 0x05, 0x02],
+        );
+        assert_round_trip(Update::Entity(Some(true), None), &[0x00, 0b10, 0x01]);
+    }
+
+    #[derive(Encoder, Decoder, Debug, PartialEq)]
+    struct Tagged<T, U = u8>
+    where
+        T: Clone,
+    {
+        #[varint]
+        tag: i32,
+        #[prefixed(VarInt)]
+        values: Vec<T>,
+        extra: Option<U>,
+    }
+
+    #[test]
+    fn test_derive_generics() {
+        assert_round_trip(
+            Tagged {
+                tag: 1,
+                values: vec![2u16, 3],
+                extra: Some(4u8),
+            },
+            &[0x01, 0x02, 0x00, 0x02, 0x00, 0x03, 0x01, 0x04],
+        );
+    }
+
+    #[derive(Encoder, Decoder, Debug, PartialEq)]
+    struct Versioned {
+        id: u8,
+        #[versions(V1_21..)]
+        extra: Option<u8>,
+    }
+
+    #[derive(Encoder, Decoder, Debug, PartialEq)]
+    struct VersionedList {
+        #[prefixed(VarInt)]
+        entries: Vec<Versioned>,
+    }
+
+    #[derive(Encoder, Decoder, Debug, PartialEq)]
+    struct VersionedView<'a> {
+        name: &'a str,
+        #[versions(..V1_21)]
+        legacy: Option<u8>,
+    }
+
+    #[test]
+    fn test_derive_versions() {
+        use crate::{borrowed::BorrowDecoder, version::ProtocolVersion};
+
+        let list = VersionedList {
+            entries: vec![Versioned {
+                id: 1,
+                extra: Some(2),
+            }],
+        };
+
+        let mut bytes = Vec::new();
+        list.encode_for(ProtocolVersion::V1_21, &mut bytes).unwrap();
+        assert_eq!(bytes, [0x01, 0x01, 0x02]);
+        let decoded = VersionedList::decode_for(ProtocolVersion::V1_21, &mut &bytes[..]).unwrap();
+        assert_eq!(decoded, list);
+
+        // the version reaches the elements, which have no extra byte before 1.21
+        let decoded =
+            VersionedList::decode_for(ProtocolVersion::V1_20_6, &mut &bytes[..2]).unwrap();
+        assert_eq!(decoded.entries[0].extra, None);
+
+        // without a version, the fields that depend on it can't be handled
+        let err = list.to_bytes().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "VersionedList.entries[0].extra: field differs between protocol versions but no \
+             version was given"
+        );
+        assert!(matches!(
+            VersionedList::from_bytes(&bytes),
+            Err(ProtocolError::Context { .. })
+        ));
+
+        let view = VersionedView::decode_borrowed_for(
+            ProtocolVersion::V1_20_6,
+            &mut &[0x01, b'a', 0x07][..],
+        )
+        .unwrap();
+        assert_eq!(view.legacy, Some(7));
+        assert!(VersionedView::from_slice(&[0x01, b'a']).is_err());
+    }
+
+    #[derive(Encoder, Decoder, Debug, PartialEq)]
+    struct Pair(#[varint] i32, String);
+
+    #[test]
+    fn test_derive_tuple_struct() {
+        assert_round_trip(Pair(300, "a".to_string()), &[0xAC, 0x02, 0x01, b'a']);
+
+        let err = Pair::from_bytes(&[0x01, 0x02, b'a']).unwrap_err();
+        assert_eq!(err.to_string(), "Pair.1 at byte 1: unexpected end of input");
+    }
+}
@@ -0,0 +1,249 @@
+use bytes::BytesMut;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpStream, ToSocketAddrs},
+};
+
+use crate::{
+    encryption::SharedSecret,
+    error::{ProtocolError, Result},
+    frame::{Frame, FrameDecoder, FrameEncoder},
+    packet::{ConnectionState, Packet, PacketFlow},
+    registry::PacketGroup,
+    version::ProtocolVersion,
+};
+
+/// How many bytes are read from the socket at once.
+const READ_BUF_SIZE: usize = 4096;
+
+/// The client side of a connection to a server, keeping track of the
+/// state the protocol is in and switching it as packets are sent.
+pub struct Connection {
+    stream: TcpStream,
+    state: ConnectionState,
+    version: ProtocolVersion,
+    encoder: FrameEncoder,
+    decoder: FrameDecoder,
+}
+
+impl Connection {
+    pub async fn connect(addr: impl ToSocketAddrs) -> Result<Self> {
+        let stream = TcpStream::connect(addr).await?;
+        Ok(Self::new(stream))
+    }
+
+    pub fn new(stream: TcpStream) -> Self {
+        Self {
+            stream,
+            state: ConnectionState::Handshaking,
+            version: ProtocolVersion::LATEST,
+            encoder: FrameEncoder::new(),
+            decoder: FrameDecoder::new(),
+        }
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        self.state
+    }
+
+    /// Overrides the current state, for the rare cases where it changes
+    /// without a packet being sent.
+    pub fn set_state(&mut self, state: ConnectionState) {
+        self.state = state;
+    }
+
+    pub fn version(&self) -> ProtocolVersion {
+        self.version
+    }
+
+    /// Sets the protocol version packets are sent and received in, which
+    /// should be done before the handshake. It defaults to the latest one.
+    pub fn set_version(&mut self, version: ProtocolVersion) {
+        self.version = version;
+        self.encoder.set_version(version);
+    }
+
+    /// Applies the threshold from a `SetCompression` packet to both directions.
+    pub fn set_compression(&mut self, threshold: Option<usize>) {
+        self.encoder.set_compression(threshold);
+        self.decoder.set_compression(threshold);
+    }
+
+    /// Encrypts both directions with the shared secret, which should be done
+    /// right after the encryption response has been sent.
+    pub fn enable_encryption(&mut self, shared_secret: &SharedSecret) {
+        self.encoder.enable_encryption(shared_secret);
+        self.decoder.enable_encryption(shared_secret);
+    }
+
+    pub fn stream(&self) -> &TcpStream {
+        &self.stream
+    }
+
+    /// Sends a packet, switching to the state it leads to afterwards.
+    pub async fn send<P: Packet>(&mut self, packet: &P) -> Result<()> {
+        if P::FLOW != PacketFlow::Serverbound {
+            return Err(ProtocolError::InvalidFlow(P::FLOW));
+        }
+
+        if P::STATE != self.state {
+            return Err(ProtocolError::InvalidState {
+                packet: P::STATE,
+                current: self.state,
+            });
+        }
+
+        self.encoder.append_packet(packet)?;
+        self.flush().await?;
+
+        if let Some(state) = packet.next_state() {
+            self.state = state;
+        }
+
+        Ok(())
+    }
+
+    /// Sends an already encoded frame without any state checks.
+    pub async fn send_frame(&mut self, frame: &Frame) -> Result<()> {
+        self.encoder.append_frame(frame)?;
+        self.flush().await
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        let bytes = self.encoder.take();
+        self.stream.write_all(&bytes).await?;
+        Ok(())
+    }
+
+    /// Receives the next packet, decoded as one of the packets of the current
+    /// state.
+    pub async fn recv<G: PacketGroup>(&mut self) -> Result<G> {
+        if G::FLOW != PacketFlow::Clientbound {
+            return Err(ProtocolError::InvalidFlow(G::FLOW));
+        }
+
+        if G::STATE != self.state {
+            return Err(ProtocolError::InvalidState {
+                packet: G::STATE,
+                current: self.state,
+            });
+        }
+
+        let frame = self.recv_frame().await?;
+        G::decode_frame_for(self.version, &frame)
+    }
+
+    /// Receives the next frame without decoding its body.
+    pub async fn recv_frame(&mut self) -> Result<Frame> {
+        loop {
+            if let Some(frame) = self.decoder.try_next_frame()? {
+                return Ok(frame);
+            }
+
+            let mut buf = BytesMut::with_capacity(READ_BUF_SIZE);
+            if self.stream.read_buf(&mut buf).await? == 0 {
+                return Err(ProtocolError::ConnectionClosed);
+            }
+
+            self.decoder.queue_bytes(buf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use blocky_derive::{Decoder, Encoder, Packet};
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::{packet_group, types::VarInt};
+
+    #[derive(Encoder, Decoder)]
+    struct Handshake {
+        next_state: VarInt,
+    }
+
+    impl Packet for Handshake {
+        const ID: i32 = 0x00;
+        const STATE: ConnectionState = ConnectionState::Handshaking;
+        const FLOW: PacketFlow = PacketFlow::Serverbound;
+
+        fn next_state(&self) -> Option<ConnectionState> {
+            match self.next_state.0 {
+                1 => Some(ConnectionState::Status),
+                _ => Some(ConnectionState::Login),
+            }
+        }
+    }
+
+    #[derive(Packet)]
+    #[packet(id = 0x03, state = Login, flow = Serverbound, next_state = Configuration)]
+    struct LoginAcknowledged;
+
+    #[derive(Packet, Debug, PartialEq)]
+    #[packet(id = 0x02, state = Login, flow = Clientbound)]
+    struct LoginSuccess {
+        name: String,
+    }
+
+    packet_group! {
+        #[derive(Debug, PartialEq)]
+        enum ClientboundLoginPacket(Login, Clientbound) {
+            LoginSuccess,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connection_state_transitions() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+
+            let mut encoder = FrameEncoder::new();
+            encoder
+                .append_packet(&LoginSuccess {
+                    name: "blocky".to_string(),
+                })
+                .unwrap();
+            stream.write_all(&encoder.take()).await.unwrap();
+
+            let mut received = Vec::new();
+            stream.read_to_end(&mut received).await.unwrap();
+            received
+        });
+
+        let mut connection = Connection::connect(addr).await.unwrap();
+        assert_eq!(connection.state(), ConnectionState::Handshaking);
+
+        // packets from other states are rejected
+        assert!(matches!(
+            connection.send(&LoginAcknowledged).await,
+            Err(ProtocolError::InvalidState { .. })
+        ));
+
+        connection
+            .send(&Handshake {
+                next_state: VarInt(2),
+            })
+            .await
+            .unwrap();
+        assert_eq!(connection.state(), ConnectionState::Login);
+
+        let packet = connection.recv::<ClientboundLoginPacket>().await.unwrap();
+        assert_eq!(
+            packet,
+            ClientboundLoginPacket::LoginSuccess(LoginSuccess {
+                name: "blocky".to_string()
+            })
+        );
+
+        connection.send(&LoginAcknowledged).await.unwrap();
+        assert_eq!(connection.state(), ConnectionState::Configuration);
+        drop(connection);
+
+        let received = server.await.unwrap();
+        assert_eq!(received, vec![0x02, 0x00, 0x02, 0x01, 0x03]);
+    }
+}
@@ -1,7 +1,4 @@
-use std::{
-    io::{Cursor, Read},
-    str::FromStr,
-};
+use std::{io::Read, str::FromStr};
 
 use blocky_world::{
     position::{BlockPosition, ChunkPosition},
@@ -9,34 +6,116 @@ use blocky_world::{
 };
 use uuid::Uuid;
 
-use crate::types::VarInt;
+use crate::{
+    error::{ProtocolError, Result},
+    types::VarInt,
+    version::ProtocolVersion,
+};
 
 pub trait Decoder {
-    fn decode<T: Read>(buf: &mut T) -> anyhow::Result<Self>
+    fn decode<T: Read>(buf: &mut T) -> Result<Self>
     where
         Self: Sized;
 
-    fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self>
+    /// Decodes a value from a reader that is already counting, which the
+    /// derives pass to the fields of a struct so that nested structs share
+    /// the counter of the outermost one instead of each wrapping the reader
+    /// again. Anything that doesn't record offsets reads through it as usual.
+    fn decode_counted<T: Read>(buf: &mut CountingReader<T>) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        Self::decode(buf)
+    }
+
+    /// Decodes a value with the fields it has in `version`, see
+    /// [`Encoder::encode_for`](crate::encoder::Encoder::encode_for).
+    fn decode_for<T: Read>(version: ProtocolVersion, buf: &mut T) -> Result<Self>
     where
         Self: Sized,
     {
-        let mut cursor = Cursor::new(bytes);
-        Self::decode(&mut cursor)
+        let _ = version;
+        Self::decode(buf)
+    }
+
+    /// Decodes a value from the start of `bytes`. Fields that differ between
+    /// protocol versions fail with [`ProtocolError::VersionRequired`] here,
+    /// decode them with [`Self::decode_for`] or
+    /// [`Frame::decode`](crate::frame::Frame::decode).
+    fn from_bytes(bytes: &[u8]) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        let mut buf = bytes;
+        Self::decode(&mut buf)
+    }
+}
+
+/// A reader counting the bytes read through it, which the derives use to
+/// record where each field starts for the offsets of errors. It is created
+/// once by the outermost derived decoder, see [`Decoder::decode_counted`],
+/// and carries the version being decoded to the structs inside it.
+pub struct CountingReader<R> {
+    inner: R,
+    position: usize,
+    version: Option<ProtocolVersion>,
+}
+
+impl<R: Read> CountingReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            position: 0,
+            version: None,
+        }
+    }
+
+    /// A reader decoding the fields that differ between versions as they are
+    /// in `version`.
+    pub fn for_version(inner: R, version: ProtocolVersion) -> Self {
+        Self {
+            version: Some(version),
+            ..Self::new(inner)
+        }
+    }
+
+    /// How many bytes have been read so far.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// The version being decoded, if one was given.
+    pub fn version(&self) -> Option<ProtocolVersion> {
+        self.version
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.position += read;
+        Ok(read)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> std::io::Result<()> {
+        self.inner.read_exact(buf)?;
+        self.position += buf.len();
+        Ok(())
     }
 }
 
 impl Decoder for ResourceLocation {
-    fn decode<T: Read>(buf: &mut T) -> anyhow::Result<Self>
+    fn decode<T: Read>(buf: &mut T) -> Result<Self>
     where
         Self: Sized,
     {
         let s = String::decode(buf)?;
-        ResourceLocation::from_str(&s)
+        Ok(ResourceLocation::from_str(&s)?)
     }
 }
 
 impl<V: Decoder> Decoder for Option<V> {
-    fn decode<T: Read>(buf: &mut T) -> anyhow::Result<Self> {
+    fn decode<T: Read>(buf: &mut T) -> Result<Self> {
         let present = bool::decode(buf)?;
 
         if present {
@@ -45,10 +124,38 @@ impl<V: Decoder> Decoder for Option<V> {
             Ok(None)
         }
     }
+
+    fn decode_for<T: Read>(version: ProtocolVersion, buf: &mut T) -> Result<Self> {
+        Self::decode_counted(&mut CountingReader::for_version(buf, version))
+    }
+
+    fn decode_counted<T: Read>(buf: &mut CountingReader<T>) -> Result<Self> {
+        let present = bool::decode(buf)?;
+
+        if present {
+            Ok(Some(V::decode_counted(buf)?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<V: Decoder> Decoder for Box<V> {
+    fn decode<T: Read>(buf: &mut T) -> Result<Self> {
+        V::decode(buf).map(Box::new)
+    }
+
+    fn decode_for<T: Read>(version: ProtocolVersion, buf: &mut T) -> Result<Self> {
+        V::decode_for(version, buf).map(Box::new)
+    }
+
+    fn decode_counted<T: Read>(buf: &mut CountingReader<T>) -> Result<Self> {
+        V::decode_counted(buf).map(Box::new)
+    }
 }
 
 impl Decoder for ChunkPosition {
-    fn decode<T: Read>(buf: &mut T) -> anyhow::Result<Self> {
+    fn decode<T: Read>(buf: &mut T) -> Result<Self> {
         let x = i32::decode(buf)?;
         let z = i32::decode(buf)?;
 
@@ -57,7 +164,7 @@ impl Decoder for ChunkPosition {
 }
 
 impl Decoder for BlockPosition {
-    fn decode<T: Read>(buf: &mut T) -> anyhow::Result<Self> {
+    fn decode<T: Read>(buf: &mut T) -> Result<Self> {
         let val = i64::decode(buf)?;
 
         let x = (val >> 38) as i32;
@@ -69,7 +176,7 @@ impl Decoder for BlockPosition {
 }
 
 impl Decoder for bool {
-    fn decode<T: Read>(buf: &mut T) -> anyhow::Result<Self> {
+    fn decode<T: Read>(buf: &mut T) -> Result<Self> {
         let mut byte = [0];
         buf.read_exact(&mut byte)?;
         let byte = byte[0];
@@ -77,22 +184,21 @@ impl Decoder for bool {
         match byte {
             0 => Ok(false),
             1 => Ok(true),
-            _ => anyhow::bail!("Invalid boolean value"),
+            byte => Err(ProtocolError::InvalidBool(byte)),
         }
     }
 }
 
 impl Decoder for String {
-    fn decode<T: Read>(buf: &mut T) -> anyhow::Result<Self> {
+    fn decode<T: Read>(buf: &mut T) -> Result<Self> {
         let length = VarInt::decode(buf)?.0 as usize;
 
         let max_length = i16::MAX as usize;
         if length > max_length {
-            anyhow::bail!(
-                "String length {} exceeds maximum allowed length of {}",
-                length,
-                max_length
-            );
+            return Err(ProtocolError::StringTooLong {
+                len: length,
+                max: max_length,
+            });
         }
 
         let mut bytes = vec![0; length];
@@ -103,8 +209,16 @@ impl Decoder for String {
     }
 }
 
+impl<const N: usize> Decoder for [u8; N] {
+    fn decode<T: Read>(buf: &mut T) -> Result<Self> {
+        let mut bytes = [0; N];
+        buf.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
 impl Decoder for Uuid {
-    fn decode<T: Read>(buf: &mut T) -> anyhow::Result<Self> {
+    fn decode<T: Read>(buf: &mut T) -> Result<Self> {
         let value = u128::decode(buf)?;
         Ok(Uuid::from_u128(value))
     }
@@ -114,7 +228,7 @@ macro_rules! impl_number_decoder {
     ($($typ:ty),* $(,)?) => {
         $(
             impl Decoder for $typ {
-                fn decode<T: Read>(buf: &mut T) -> anyhow::Result<Self> {
+                fn decode<T: Read>(buf: &mut T) -> Result<Self> {
                     let mut bytes = [0; std::mem::size_of::<Self>()];
                     buf.read_exact(&mut bytes)?;
                     Ok(Self::from_be_bytes(bytes))
@@ -4,20 +4,41 @@ use blocky_world::{
     position::{BlockPosition, ChunkPosition},
     resources::ResourceLocation,
 };
+use bytes::{BufMut, Bytes, BytesMut};
 use uuid::Uuid;
 
-use crate::types::VarInt;
+use crate::{
+    error::{ProtocolError, Result},
+    types::VarInt,
+    version::ProtocolVersion,
+};
 
 pub trait Encoder {
     fn byte_len(&self) -> usize
     where
         Self: Sized;
 
-    fn encode<T: Write>(&self, buf: &mut T) -> anyhow::Result<()>
+    fn encode<T: Write>(&self, buf: &mut T) -> Result<()>
     where
         Self: Sized;
 
-    fn to_bytes(&self) -> anyhow::Result<Vec<u8>>
+    /// Encodes the value with the fields it has in `version`. Values whose
+    /// fields are the same in every version encode as they always do, so
+    /// only the derives and the containers passing it on to their values
+    /// override this.
+    fn encode_for<T: Write>(&self, version: ProtocolVersion, buf: &mut T) -> Result<()>
+    where
+        Self: Sized,
+    {
+        let _ = version;
+        self.encode(buf)
+    }
+
+    /// Encodes the value on its own. Fields that differ between protocol
+    /// versions fail with [`ProtocolError::VersionRequired`] here, encode
+    /// them with [`Self::encode_for`] or
+    /// [`Frame::from_packet`](crate::frame::Frame::from_packet).
+    fn to_bytes(&self) -> Result<Vec<u8>>
     where
         Self: Sized,
     {
@@ -25,6 +46,15 @@ pub trait Encoder {
         self.encode(&mut buf)?;
         Ok(buf)
     }
+
+    /// Appends the encoded value to `buf`, reserving room for it up front.
+    fn encode_into(&self, buf: &mut BytesMut) -> Result<()>
+    where
+        Self: Sized,
+    {
+        buf.reserve(self.byte_len());
+        self.encode(&mut buf.writer())
+    }
 }
 
 impl Encoder for ResourceLocation {
@@ -36,7 +66,7 @@ impl Encoder for ResourceLocation {
         VarInt(len as i32).byte_len() + len
     }
 
-    fn encode<T: Write>(&self, buf: &mut T) -> anyhow::Result<()>
+    fn encode<T: Write>(&self, buf: &mut T) -> Result<()>
     where
         Self: Sized,
     {
@@ -52,7 +82,7 @@ impl<V: Encoder> Encoder for Option<V> {
         }
     }
 
-    fn encode<T: Write>(&self, buf: &mut T) -> anyhow::Result<()> {
+    fn encode<T: Write>(&self, buf: &mut T) -> Result<()> {
         match self {
             Some(value) => {
                 bool::encode(&true, buf)?;
@@ -61,6 +91,30 @@ impl<V: Encoder> Encoder for Option<V> {
             None => bool::encode(&false, buf),
         }
     }
+
+    fn encode_for<T: Write>(&self, version: ProtocolVersion, buf: &mut T) -> Result<()> {
+        match self {
+            Some(value) => {
+                bool::encode(&true, buf)?;
+                value.encode_for(version, buf)
+            }
+            None => bool::encode(&false, buf),
+        }
+    }
+}
+
+impl<V: Encoder> Encoder for Box<V> {
+    fn byte_len(&self) -> usize {
+        V::byte_len(self)
+    }
+
+    fn encode<T: Write>(&self, buf: &mut T) -> Result<()> {
+        V::encode(self, buf)
+    }
+
+    fn encode_for<T: Write>(&self, version: ProtocolVersion, buf: &mut T) -> Result<()> {
+        V::encode_for(self, version, buf)
+    }
 }
 
 impl Encoder for ChunkPosition {
@@ -68,7 +122,7 @@ impl Encoder for ChunkPosition {
         8
     }
 
-    fn encode<T: Write>(&self, buf: &mut T) -> anyhow::Result<()> {
+    fn encode<T: Write>(&self, buf: &mut T) -> Result<()> {
         i32::encode(&self.x, buf)?;
         i32::encode(&self.z, buf)?;
         Ok(())
@@ -80,7 +134,7 @@ impl Encoder for BlockPosition {
         8
     }
 
-    fn encode<T: Write>(&self, buf: &mut T) -> anyhow::Result<()> {
+    fn encode<T: Write>(&self, buf: &mut T) -> Result<()> {
         let value = ((self.x as u64 & 0x3FFFFFF) << 38)
             | ((self.z as u64 & 0x3FFFFFF) << 12)
             | (self.y as u64 & 0xFFF);
@@ -94,41 +148,85 @@ impl Encoder for bool {
         1
     }
 
-    fn encode<T: Write>(&self, buf: &mut T) -> anyhow::Result<()> {
+    fn encode<T: Write>(&self, buf: &mut T) -> Result<()> {
         buf.write_all(&[*self as u8])?;
         Ok(())
     }
 }
 
 impl Encoder for String {
+    fn byte_len(&self) -> usize {
+        self.as_str().byte_len()
+    }
+
+    fn encode<T: Write>(&self, buf: &mut T) -> Result<()> {
+        self.as_str().encode(buf)
+    }
+}
+
+impl Encoder for &str {
     fn byte_len(&self) -> usize {
         VarInt(self.len() as i32).byte_len() + self.len()
     }
 
-    fn encode<T: Write>(&self, buf: &mut T) -> anyhow::Result<()> {
+    fn encode<T: Write>(&self, buf: &mut T) -> Result<()> {
         let length = self.len();
 
         let max_length = i16::MAX as usize;
         if length > max_length {
-            anyhow::bail!(
-                "String length {} exceeds maximum allowed length of {}",
-                length,
-                max_length
-            );
+            return Err(ProtocolError::StringTooLong {
+                len: length,
+                max: max_length,
+            });
         }
 
         VarInt(length as i32).encode(buf)?;
-        buf.write_all(&self.to_bytes()?)?;
+        buf.write_all(self.as_bytes())?;
         Ok(())
     }
 }
 
+impl Encoder for &[u8] {
+    fn byte_len(&self) -> usize {
+        VarInt(self.len() as i32).byte_len() + self.len()
+    }
+
+    fn encode<T: Write>(&self, buf: &mut T) -> Result<()> {
+        VarInt(self.len() as i32).encode(buf)?;
+        buf.write_all(self)?;
+        Ok(())
+    }
+}
+
+/// A fixed number of bytes, written without a length as the protocol knows
+/// how many there are.
+impl<const N: usize> Encoder for [u8; N] {
+    fn byte_len(&self) -> usize {
+        N
+    }
+
+    fn encode<T: Write>(&self, buf: &mut T) -> Result<()> {
+        buf.write_all(self)?;
+        Ok(())
+    }
+}
+
+impl Encoder for Bytes {
+    fn byte_len(&self) -> usize {
+        (&self[..]).byte_len()
+    }
+
+    fn encode<T: Write>(&self, buf: &mut T) -> Result<()> {
+        (&self[..]).encode(buf)
+    }
+}
+
 impl Encoder for Uuid {
     fn byte_len(&self) -> usize {
         16
     }
 
-    fn encode<T: Write>(&self, buf: &mut T) -> anyhow::Result<()> {
+    fn encode<T: Write>(&self, buf: &mut T) -> Result<()> {
         self.as_u128().encode(buf)?;
         Ok(())
     }
@@ -142,23 +240,49 @@ macro_rules! impl_number_encoder {
                     std::mem::size_of::<Self>()
                 }
 
-                fn encode<T: Write>(&self, buf: &mut T) -> anyhow::Result<()> {
+                fn encode<T: Write>(&self, buf: &mut T) -> Result<()> {
                     buf.write_all(&self.to_be_bytes())?;
                     Ok(())
                 }
             }
+        )*
+    };
+}
+
+impl_number_encoder!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+macro_rules! impl_length_prefix {
+    (@impl $typ:ty, $($checked_len:item)?) => {
+        impl super::types::LengthPrefix for $typ {
+            fn len(&self) -> usize {
+                *self as usize
+            }
 
-            impl super::types::LengthPrefix for $typ {
-                fn len(&self) -> usize {
-                    *self as usize
-                }
+            fn from_len(value: usize) -> Self {
+                value as $typ
+            }
 
-                fn from_len(value: usize) -> Self {
-                    value as $typ
+            $($checked_len)?
+        }
+    };
+    (signed: $($typ:ty),* $(,)?) => {
+        $(
+            impl_length_prefix!(@impl $typ, fn checked_len(&self) -> Result<usize> {
+                if *self < 0 {
+                    let len = i64::try_from(*self).unwrap_or(i64::MIN);
+                    return Err(ProtocolError::NegativeLength(len));
                 }
-            }
+
+                Ok(self.len())
+            });
+        )*
+    };
+    ($($typ:ty),* $(,)?) => {
+        $(
+            impl_length_prefix!(@impl $typ,);
         )*
     };
 }
 
-impl_number_encoder!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+impl_length_prefix!(u8, u16, u32, u64, u128, f32, f64);
+impl_length_prefix!(signed: i8, i16, i32, i64, i128);
@@ -0,0 +1,179 @@
+use std::io::{Read, Write};
+
+use aes::{
+    cipher::{inout::InOutBuf, BlockDecryptMut, BlockEncryptMut, KeyIvInit},
+    Aes128,
+};
+use rand::RngCore;
+use rsa::{pkcs8::DecodePublicKey, Pkcs1v15Encrypt, RsaPublicKey};
+
+use crate::error::Result;
+
+/// The length in bytes of the shared secret, which is used as both the key
+/// and the IV of the stream cipher.
+pub const SHARED_SECRET_LENGTH: usize = 16;
+
+pub type SharedSecret = [u8; SHARED_SECRET_LENGTH];
+
+/// Encrypts bytes in place with AES-128 in CFB8 mode, keeping its state
+/// between calls so it can be applied to a stream piece by piece.
+pub struct StreamEncryptor(cfb8::Encryptor<Aes128>);
+
+impl StreamEncryptor {
+    pub fn new(shared_secret: &SharedSecret) -> Self {
+        Self(cfb8::Encryptor::new(
+            shared_secret.into(),
+            shared_secret.into(),
+        ))
+    }
+
+    pub fn encrypt(&mut self, bytes: &mut [u8]) {
+        let (blocks, _) = InOutBuf::from(bytes).into_chunks();
+        self.0.encrypt_blocks_inout_mut(blocks);
+    }
+}
+
+/// Decrypts bytes in place with AES-128 in CFB8 mode, keeping its state
+/// between calls so it can be applied to a stream piece by piece.
+pub struct StreamDecryptor(cfb8::Decryptor<Aes128>);
+
+impl StreamDecryptor {
+    pub fn new(shared_secret: &SharedSecret) -> Self {
+        Self(cfb8::Decryptor::new(
+            shared_secret.into(),
+            shared_secret.into(),
+        ))
+    }
+
+    pub fn decrypt(&mut self, bytes: &mut [u8]) {
+        let (blocks, _) = InOutBuf::from(bytes).into_chunks();
+        self.0.decrypt_blocks_inout_mut(blocks);
+    }
+}
+
+/// A reader that decrypts everything read from the inner reader.
+pub struct EncryptedReader<R> {
+    inner: R,
+    decryptor: StreamDecryptor,
+}
+
+impl<R: Read> EncryptedReader<R> {
+    pub fn new(inner: R, shared_secret: &SharedSecret) -> Self {
+        Self {
+            inner,
+            decryptor: StreamDecryptor::new(shared_secret),
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for EncryptedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.decryptor.decrypt(&mut buf[..read]);
+        Ok(read)
+    }
+}
+
+/// A writer that encrypts everything before passing it to the inner writer.
+pub struct EncryptedWriter<W> {
+    inner: W,
+    encryptor: StreamEncryptor,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> EncryptedWriter<W> {
+    pub fn new(inner: W, shared_secret: &SharedSecret) -> Self {
+        Self {
+            inner,
+            encryptor: StreamEncryptor::new(shared_secret),
+            buf: Vec::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for EncryptedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        // the cipher state advances with every byte, so everything has to be
+        // handed to the inner writer before it can be reported as written
+        self.buf.clear();
+        self.buf.extend_from_slice(buf);
+        self.encryptor.encrypt(&mut self.buf);
+        self.inner.write_all(&self.buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Generates a random shared secret to send to the server.
+pub fn generate_shared_secret() -> SharedSecret {
+    let mut secret = [0; SHARED_SECRET_LENGTH];
+    rand::thread_rng().fill_bytes(&mut secret);
+    secret
+}
+
+/// Encrypts `data` with the DER encoded RSA public key the server sends in
+/// its encryption request, using PKCS#1 v1.5 padding.
+pub fn encrypt_with_public_key(public_key: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+    let key = RsaPublicKey::from_public_key_der(public_key)?;
+    Ok(key.encrypt(&mut rand::thread_rng(), Pkcs1v15Encrypt, data)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use rsa::{pkcs8::EncodePublicKey, RsaPrivateKey};
+
+    use super::*;
+
+    #[test]
+    fn test_stream_cipher_round_trip() {
+        let secret = generate_shared_secret();
+        let plaintext = b"hello, this is a longer message than a single block".to_vec();
+
+        let mut writer = EncryptedWriter::new(Vec::new(), &secret);
+        writer.write_all(&plaintext[..10]).unwrap();
+        writer.write_all(&plaintext[10..]).unwrap();
+        let ciphertext = writer.into_inner();
+        assert_ne!(ciphertext, plaintext);
+
+        let mut reader = EncryptedReader::new(Cursor::new(ciphertext), &secret);
+        let mut decrypted = Vec::new();
+        reader.read_to_end(&mut decrypted).unwrap();
+        assert_eq!(decrypted, plaintext);
+    }
+
+    #[test]
+    fn test_stream_cipher_known_vector() {
+        // AES-128-CFB8 with key and IV both set to 0x00..0x0F
+        let secret: SharedSecret = core::array::from_fn(|i| i as u8);
+        let mut bytes = [0u8; 4];
+        StreamEncryptor::new(&secret).encrypt(&mut bytes);
+        assert_eq!(bytes, [0x0A, 0x23, 0x8E, 0x2E]);
+    }
+
+    #[test]
+    fn test_encrypt_with_public_key() {
+        let mut rng = rand::thread_rng();
+        let private_key = RsaPrivateKey::new(&mut rng, 1024).unwrap();
+        let public_key = private_key.to_public_key().to_public_key_der().unwrap();
+
+        let secret = generate_shared_secret();
+        let encrypted = encrypt_with_public_key(public_key.as_bytes(), &secret).unwrap();
+        assert_eq!(encrypted.len(), 128);
+
+        let decrypted = private_key.decrypt(Pkcs1v15Encrypt, &encrypted).unwrap();
+        assert_eq!(decrypted, secret);
+    }
+}
@@ -0,0 +1,276 @@
+use std::fmt;
+
+use blocky_world::resources::ResourceLocationError;
+
+use crate::{
+    packet::{ConnectionState, PacketFlow},
+    version::ProtocolVersion,
+};
+
+pub type Result<T> = std::result::Result<T, ProtocolError>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProtocolError {
+    /// The input ended before the value was fully read, which usually means
+    /// the frame was truncated or more bytes are needed.
+    #[error("unexpected end of input")]
+    UnexpectedEof,
+    #[error("VarInt is too long")]
+    VarIntTooLong,
+    #[error("VarLong is too long")]
+    VarLongTooLong,
+    #[error("string length {len} exceeds maximum allowed length of {max}")]
+    StringTooLong { len: usize, max: usize },
+    #[error("invalid boolean value {0:#04x}")]
+    InvalidBool(u8),
+    #[error("invalid {ty} discriminant {value}")]
+    InvalidDiscriminant { ty: &'static str, value: String },
+    #[error("field is set but its condition says it is absent, or the other way around")]
+    PresenceMismatch,
+    #[error("field differs between protocol versions but no version was given")]
+    VersionRequired,
+    #[error("invalid UTF-8: {0}")]
+    InvalidUtf8(#[from] std::str::Utf8Error),
+    #[error("length {len} exceeds maximum allowed length of {max}")]
+    LengthExceeded { len: usize, max: usize },
+    #[error("negative length {0}")]
+    NegativeLength(i64),
+    #[error("invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("invalid resource location: {0}")]
+    InvalidResourceLocation(#[from] ResourceLocationError),
+    #[error("unknown {state:?} {flow:?} packet id {id:#04x}")]
+    UnknownPacketId {
+        state: ConnectionState,
+        flow: PacketFlow,
+        id: i32,
+    },
+    #[error("there are no {state:?} {flow:?} packets for protocol version {version}")]
+    UnsupportedVersion {
+        state: ConnectionState,
+        flow: PacketFlow,
+        version: ProtocolVersion,
+    },
+    #[error("{state:?} {flow:?} packet {id:#04x} is not part of protocol version {version}")]
+    NotInVersion {
+        state: ConnectionState,
+        flow: PacketFlow,
+        id: i32,
+        version: ProtocolVersion,
+    },
+    #[error("frame with id {id:#04x} has {remaining} unread bytes after decoding")]
+    TrailingBytes { id: i32, remaining: usize },
+    #[error(
+        "badly compressed frame, uncompressed length {len} is below the threshold of {threshold}"
+    )]
+    BelowCompressionThreshold { len: usize, threshold: usize },
+    #[error("frame inflated to {actual} bytes but claimed an uncompressed length of {expected}")]
+    UncompressedLengthMismatch { expected: usize, actual: usize },
+    #[error("cannot use a {packet:?} packet while in the {current:?} state")]
+    InvalidState {
+        packet: ConnectionState,
+        current: ConnectionState,
+    },
+    #[error("cannot use a {0:?} packet from this side of the connection")]
+    InvalidFlow(PacketFlow),
+    #[error("connection closed")]
+    ConnectionClosed,
+    #[error("invalid public key: {0}")]
+    InvalidPublicKey(#[from] rsa::pkcs8::spki::Error),
+    #[error("encryption error: {0}")]
+    Encryption(#[from] rsa::Error),
+    #[error("I/O error: {0}")]
+    Io(std::io::Error),
+    #[error("{0}")]
+    Custom(String),
+    /// Wraps an error with where in a packet it happened, added by the
+    /// derives, lists and frames as the error is passed up.
+    #[error("{}", context_message(.path, .offset, .source))]
+    Context {
+        path: FieldPath,
+        offset: Option<usize>,
+        source: Box<ProtocolError>,
+    },
+}
+
+impl ProtocolError {
+    pub fn custom(message: impl Into<String>) -> Self {
+        Self::Custom(message.into())
+    }
+
+    fn into_context(self) -> (FieldPath, Option<usize>, Box<ProtocolError>) {
+        match self {
+            Self::Context {
+                path,
+                offset,
+                source,
+            } => (path, offset, source),
+            _ => (FieldPath::default(), None, Box::new(self)),
+        }
+    }
+
+    /// Marks this error as having happened in `field` of the struct `ty`.
+    pub fn in_field(self, ty: &'static str, field: &'static str) -> Self {
+        let (mut path, offset, source) = self.into_context();
+        path.ty = Some(ty);
+        path.segments.insert(0, PathSegment::Field(field));
+
+        Self::Context {
+            path,
+            offset,
+            source,
+        }
+    }
+
+    /// Marks this error as having happened in the element at `index` of a list.
+    pub fn at_index(self, index: usize) -> Self {
+        let (mut path, offset, source) = self.into_context();
+        path.ty = None;
+        path.segments.insert(0, PathSegment::Index(index));
+
+        Self::Context {
+            path,
+            offset,
+            source,
+        }
+    }
+
+    /// Records that the value this error happened in starts `offset` bytes
+    /// into the value around it, so an offset recorded inside the value
+    /// becomes one from the start of the outer value.
+    pub fn at_offset(self, offset: usize) -> Self {
+        let (path, existing, source) = self.into_context();
+
+        Self::Context {
+            path,
+            offset: Some(existing.unwrap_or(0) + offset),
+            source,
+        }
+    }
+
+    /// The error underneath any context that was added to it.
+    pub fn root(&self) -> &ProtocolError {
+        match self {
+            Self::Context { source, .. } => source,
+            _ => self,
+        }
+    }
+
+    /// The path to the field the error happened in, if any was recorded.
+    pub fn path(&self) -> Option<&FieldPath> {
+        match self {
+            Self::Context { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// The byte offset into the frame the error happened at, if known.
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            Self::Context { offset, .. } => *offset,
+            _ => None,
+        }
+    }
+
+    /// Whether the input ran out before a value was fully read, as opposed
+    /// to the input being malformed.
+    pub fn is_unexpected_eof(&self) -> bool {
+        matches!(self.root(), Self::UnexpectedEof)
+    }
+}
+
+fn context_message(path: &FieldPath, offset: &Option<usize>, source: &ProtocolError) -> String {
+    let mut location = path.to_string();
+
+    if let Some(offset) = offset {
+        if !location.is_empty() {
+            location.push(' ');
+        }
+
+        location.push_str(&format!("at byte {offset}"));
+    }
+
+    format!("{location}: {source}")
+}
+
+/// The path from the outermost struct down to the field an error happened
+/// in, such as `LoginSuccess.properties[3].signature`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FieldPath {
+    /// The outermost struct, unless the path starts at a list.
+    pub ty: Option<&'static str>,
+    pub segments: Vec<PathSegment>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathSegment {
+    Field(&'static str),
+    Index(usize),
+}
+
+impl fmt::Display for FieldPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(ty) = self.ty {
+            write!(f, "{ty}")?;
+        }
+
+        for segment in &self.segments {
+            match segment {
+                PathSegment::Field(field) => write!(f, ".{field}")?,
+                PathSegment::Index(index) => write!(f, "[{index}]")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl From<std::io::Error> for ProtocolError {
+    fn from(value: std::io::Error) -> Self {
+        match value.kind() {
+            std::io::ErrorKind::UnexpectedEof => Self::UnexpectedEof,
+            _ => Self::Io(value),
+        }
+    }
+}
+
+impl From<std::string::FromUtf8Error> for ProtocolError {
+    fn from(value: std::string::FromUtf8Error) -> Self {
+        Self::InvalidUtf8(value.utf8_error())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_path() {
+        let err = ProtocolError::UnexpectedEof
+            .in_field("LoginSuccessProperty", "signature")
+            .at_offset(8)
+            .at_index(3)
+            .at_offset(30)
+            .in_field("LoginSuccess", "properties")
+            .at_offset(19);
+
+        assert!(err.is_unexpected_eof());
+        assert_eq!(err.offset(), Some(57));
+        assert_eq!(
+            err.to_string(),
+            "LoginSuccess.properties[3].signature at byte 57: unexpected end of input"
+        );
+    }
+
+    #[test]
+    fn test_error_offset_without_path() {
+        let err = ProtocolError::VarIntTooLong.at_offset(5);
+        assert_eq!(err.to_string(), "at byte 5: VarInt is too long");
+    }
+
+    #[test]
+    fn test_error_path_without_offset() {
+        let err = ProtocolError::InvalidBool(2).at_index(1);
+        assert_eq!(err.to_string(), "[1]: invalid boolean value 0x02");
+    }
+}
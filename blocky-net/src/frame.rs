@@ -0,0 +1,619 @@
+use std::{
+    fmt,
+    io::{Read, Write},
+};
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use flate2::{bufread::ZlibDecoder, write::ZlibEncoder, Compression};
+
+use crate::{
+    borrowed::{BorrowDecoder, BytesDecoder},
+    decoder::Decoder,
+    encoder::Encoder,
+    encryption::{SharedSecret, StreamDecryptor, StreamEncryptor},
+    error::{ProtocolError, Result},
+    packet::Packet,
+    types::VarInt,
+    version::ProtocolVersion,
+};
+
+/// The largest frame the vanilla server will accept, which is the largest
+/// length that can be written as a 3 byte VarInt (2 MiB - 1).
+pub const MAX_FRAME_LENGTH: usize = (1 << 21) - 1;
+
+/// The largest size a compressed frame is allowed to inflate to.
+pub const MAX_UNCOMPRESSED_LENGTH: usize = 1 << 23;
+
+/// The maximum number of bytes the length prefix of a frame can take up.
+const MAX_LENGTH_PREFIX_BYTES: usize = 3;
+
+/// A single packet as it appears on the wire, made up of the packet id
+/// and the still encoded packet body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub id: i32,
+    pub body: Bytes,
+}
+
+impl Frame {
+    pub fn new(id: i32, body: impl Into<Bytes>) -> Self {
+        Self {
+            id,
+            body: body.into(),
+        }
+    }
+
+    /// Encodes a packet with the id and fields it has in `version`, failing
+    /// if it isn't part of that version.
+    pub fn from_packet<P: Packet>(packet: &P, version: ProtocolVersion) -> Result<Self> {
+        let Some(id) = P::id_for(version) else {
+            return Err(ProtocolError::NotInVersion {
+                state: P::STATE,
+                flow: P::FLOW,
+                id: P::ID,
+                version,
+            });
+        };
+
+        let mut body = Vec::with_capacity(packet.byte_len());
+        packet.encode_for(version, &mut body)?;
+        Ok(Self::new(id, body))
+    }
+
+    /// Decodes the body of this frame with the fields it has in `version`,
+    /// failing if the packet does not consume every byte of it.
+    pub fn decode<P: Decoder>(&self, version: ProtocolVersion) -> Result<P> {
+        let mut buf = &self.body[..];
+        let packet = P::decode_for(version, &mut buf)?;
+
+        self.check_consumed(buf.len())?;
+        Ok(packet)
+    }
+
+    /// Decodes the body of this frame into a value that borrows from it.
+    pub fn decode_borrowed<'a, P: BorrowDecoder<'a>>(
+        &'a self,
+        version: ProtocolVersion,
+    ) -> Result<P> {
+        let mut buf = &self.body[..];
+        let packet = P::decode_borrowed_for(version, &mut buf)?;
+
+        self.check_consumed(buf.len())?;
+        Ok(packet)
+    }
+
+    /// Decodes the body of this frame into a value that can share its
+    /// allocation.
+    pub fn decode_bytes<P: BytesDecoder>(&self, version: ProtocolVersion) -> Result<P> {
+        let mut buf = self.body.clone();
+        let packet = P::decode_bytes_for(version, &mut buf)?;
+
+        self.check_consumed(buf.len())?;
+        Ok(packet)
+    }
+
+    fn check_consumed(&self, remaining: usize) -> Result<()> {
+        if remaining > 0 {
+            return Err(ProtocolError::TrailingBytes {
+                id: self.id,
+                remaining,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// The length of the id and the body, which is the value of the
+    /// length prefix when the frame is written.
+    pub fn byte_len(&self) -> usize {
+        VarInt(self.id).byte_len() + self.body.len()
+    }
+}
+
+/// Writes frames into an internal buffer with their length prefix so they can
+/// be flushed to a socket in one go.
+#[derive(Default)]
+pub struct FrameEncoder {
+    buf: BytesMut,
+    compression_threshold: Option<usize>,
+    version: ProtocolVersion,
+    encryptor: Option<StreamEncryptor>,
+    /// How many bytes at the start of `buf` were written before encryption
+    /// was enabled and have to be sent as plaintext.
+    plaintext_len: usize,
+}
+
+// the cipher state is left out so the key can't end up in logs
+
+impl fmt::Debug for FrameEncoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FrameEncoder")
+            .field("buf", &self.buf)
+            .field("compression_threshold", &self.compression_threshold)
+            .field("version", &self.version)
+            .field("encrypted", &self.encryptor.is_some())
+            .field("plaintext_len", &self.plaintext_len)
+            .finish()
+    }
+}
+
+impl FrameEncoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enables compression for every frame appended from now on. Bodies at or
+    /// above the threshold are compressed, passing `None` disables it again.
+    pub fn set_compression(&mut self, threshold: Option<usize>) {
+        self.compression_threshold = threshold;
+    }
+
+    pub fn compression(&self) -> Option<usize> {
+        self.compression_threshold
+    }
+
+    /// Sets the protocol version packets are encoded for, which decides their
+    /// ids and which of their fields are written.
+    pub fn set_version(&mut self, version: ProtocolVersion) {
+        self.version = version;
+    }
+
+    pub fn version(&self) -> ProtocolVersion {
+        self.version
+    }
+
+    /// Encrypts every byte appended from now on with the shared secret.
+    /// Bytes appended before this call are still taken as plaintext.
+    pub fn enable_encryption(&mut self, shared_secret: &SharedSecret) {
+        self.encryptor = Some(StreamEncryptor::new(shared_secret));
+        self.plaintext_len = self.buf.len();
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.encryptor.is_some()
+    }
+
+    pub fn append_frame(&mut self, frame: &Frame) -> Result<()> {
+        self.append_raw(frame.id, &frame.body)
+    }
+
+    /// Appends a packet as it is in the version set with [`Self::set_version`].
+    pub fn append_packet<P: Packet>(&mut self, packet: &P) -> Result<()> {
+        let frame = Frame::from_packet(packet, self.version)?;
+        self.append_frame(&frame)
+    }
+
+    fn append_raw(&mut self, id: i32, body: &[u8]) -> Result<()> {
+        let id = VarInt(id);
+        let data_length = id.byte_len() + body.len();
+
+        match self.compression_threshold {
+            Some(threshold) if data_length >= threshold => {
+                let mut data = Vec::with_capacity(data_length);
+                id.encode(&mut data)?;
+                data.extend_from_slice(body);
+
+                let mut compressor = ZlibEncoder::new(Vec::new(), Compression::default());
+                compressor.write_all(&data)?;
+                let compressed = compressor.finish()?;
+
+                let data_length = VarInt(data_length as i32);
+                let length = data_length.byte_len() + compressed.len();
+                self.write_header(length)?;
+
+                let mut writer = (&mut self.buf).writer();
+                data_length.encode(&mut writer)?;
+                self.buf.put_slice(&compressed);
+            }
+            Some(_) => {
+                // a data length of zero marks the frame as uncompressed
+                self.write_header(1 + data_length)?;
+                self.buf.put_u8(0);
+
+                id.encode(&mut (&mut self.buf).writer())?;
+                self.buf.put_slice(body);
+            }
+            None => {
+                self.write_header(data_length)?;
+
+                id.encode(&mut (&mut self.buf).writer())?;
+                self.buf.put_slice(body);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_header(&mut self, length: usize) -> Result<()> {
+        if length > MAX_FRAME_LENGTH {
+            return Err(ProtocolError::LengthExceeded {
+                len: length,
+                max: MAX_FRAME_LENGTH,
+            });
+        }
+
+        let length = VarInt(length as i32);
+        self.buf.reserve(length.byte_len() + length.0 as usize);
+        length.encode(&mut (&mut self.buf).writer())
+    }
+
+    /// Takes every byte written so far, leaving the encoder empty.
+    pub fn take(&mut self) -> BytesMut {
+        if let Some(encryptor) = &mut self.encryptor {
+            encryptor.encrypt(&mut self.buf[self.plaintext_len..]);
+            self.plaintext_len = 0;
+        }
+
+        self.buf.split()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+}
+
+/// Splits a stream of bytes back into frames, holding on to partial frames
+/// until the rest of their bytes have been queued.
+#[derive(Default)]
+pub struct FrameDecoder {
+    buf: BytesMut,
+    compression_threshold: Option<usize>,
+    decryptor: Option<StreamDecryptor>,
+}
+
+impl fmt::Debug for FrameDecoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FrameDecoder")
+            .field("buf", &self.buf)
+            .field("compression_threshold", &self.compression_threshold)
+            .field("encrypted", &self.decryptor.is_some())
+            .finish()
+    }
+}
+
+impl FrameDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Expects every frame read from now on to be in the compressed format,
+    /// passing `None` disables it again.
+    pub fn set_compression(&mut self, threshold: Option<usize>) {
+        self.compression_threshold = threshold;
+    }
+
+    pub fn compression(&self) -> Option<usize> {
+        self.compression_threshold
+    }
+
+    /// Decrypts every byte queued from now on with the shared secret. Bytes
+    /// that are already queued but not yet part of a frame are decrypted too,
+    /// as the server only sends them after enabling encryption itself.
+    pub fn enable_encryption(&mut self, shared_secret: &SharedSecret) {
+        let mut decryptor = StreamDecryptor::new(shared_secret);
+        decryptor.decrypt(&mut self.buf);
+        self.decryptor = Some(decryptor);
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.decryptor.is_some()
+    }
+
+    pub fn queue_slice(&mut self, bytes: &[u8]) {
+        let start = self.buf.len();
+        self.buf.extend_from_slice(bytes);
+
+        if let Some(decryptor) = &mut self.decryptor {
+            decryptor.decrypt(&mut self.buf[start..]);
+        }
+    }
+
+    pub fn queue_bytes(&mut self, mut bytes: BytesMut) {
+        if let Some(decryptor) = &mut self.decryptor {
+            decryptor.decrypt(&mut bytes);
+        }
+
+        self.buf.unsplit(bytes);
+    }
+
+    /// Returns the next complete frame, or `None` if more bytes are needed.
+    pub fn try_next_frame(&mut self) -> Result<Option<Frame>> {
+        let Some((length, prefix_len)) = peek_frame_length(&self.buf)? else {
+            return Ok(None);
+        };
+
+        if self.buf.len() < prefix_len + length {
+            return Ok(None);
+        }
+
+        self.buf.advance(prefix_len);
+        let mut data = self.buf.split_to(length).freeze();
+
+        if let Some(threshold) = self.compression_threshold {
+            let data_length = VarInt::decode(&mut (&data[..]).reader())?;
+            data.advance(data_length.byte_len());
+
+            if data_length.0 != 0 {
+                data = decompress(data, data_length.0, threshold)?;
+            }
+        }
+
+        let id = VarInt::decode(&mut (&data[..]).reader())?;
+        data.advance(id.byte_len());
+
+        Ok(Some(Frame::new(id.0, data)))
+    }
+
+    /// The number of bytes queued that have not been returned as a frame yet.
+    pub fn buffered_len(&self) -> usize {
+        self.buf.len()
+    }
+}
+
+/// Inflates the data of a compressed frame, validating it against the
+/// compression threshold and the uncompressed length it claims to have.
+fn decompress(data: Bytes, data_length: i32, threshold: usize) -> Result<Bytes> {
+    let data_length = usize::try_from(data_length)
+        .map_err(|_| ProtocolError::NegativeLength(data_length.into()))?;
+
+    if data_length < threshold {
+        return Err(ProtocolError::BelowCompressionThreshold {
+            len: data_length,
+            threshold,
+        });
+    }
+
+    if data_length > MAX_UNCOMPRESSED_LENGTH {
+        return Err(ProtocolError::LengthExceeded {
+            len: data_length,
+            max: MAX_UNCOMPRESSED_LENGTH,
+        });
+    }
+
+    // read one byte more than expected so oversized data can be detected
+    let mut inflated = Vec::with_capacity(data_length);
+    ZlibDecoder::new(&data[..])
+        .take(data_length as u64 + 1)
+        .read_to_end(&mut inflated)?;
+
+    if inflated.len() != data_length {
+        return Err(ProtocolError::UncompressedLengthMismatch {
+            expected: data_length,
+            actual: inflated.len(),
+        });
+    }
+
+    Ok(inflated.into())
+}
+
+/// Reads the VarInt length prefix at the start of `buf` without consuming it,
+/// returning the frame length and the size of the prefix.
+fn peek_frame_length(buf: &[u8]) -> Result<Option<(usize, usize)>> {
+    let mut length = 0;
+
+    for (i, byte) in buf.iter().take(MAX_LENGTH_PREFIX_BYTES).enumerate() {
+        length |= ((byte & 0b01111111) as usize) << (i * 7);
+
+        if byte & 0b10000000 == 0 {
+            if length > MAX_FRAME_LENGTH {
+                return Err(ProtocolError::LengthExceeded {
+                    len: length,
+                    max: MAX_FRAME_LENGTH,
+                });
+            }
+
+            return Ok(Some((length, i + 1)));
+        }
+    }
+
+    if buf.len() >= MAX_LENGTH_PREFIX_BYTES {
+        return Err(ProtocolError::VarIntTooLong);
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_round_trip() {
+        let mut encoder = FrameEncoder::new();
+        encoder
+            .append_frame(&Frame::new(0x00, "hello".to_string().to_bytes().unwrap()))
+            .unwrap();
+        encoder
+            .append_frame(&Frame::new(0x01, 1234u64.to_bytes().unwrap()))
+            .unwrap();
+
+        let mut decoder = FrameDecoder::new();
+        decoder.queue_bytes(encoder.take());
+
+        let frame = decoder.try_next_frame().unwrap().unwrap();
+        assert_eq!(frame.id, 0x00);
+        assert_eq!(
+            frame.decode::<String>(ProtocolVersion::LATEST).unwrap(),
+            "hello"
+        );
+
+        let frame = decoder.try_next_frame().unwrap().unwrap();
+        assert_eq!(frame.id, 0x01);
+        assert_eq!(frame.decode::<u64>(ProtocolVersion::LATEST).unwrap(), 1234);
+
+        assert!(decoder.try_next_frame().unwrap().is_none());
+        assert_eq!(decoder.buffered_len(), 0);
+    }
+
+    #[test]
+    fn test_frame_partial_reads() {
+        let mut encoder = FrameEncoder::new();
+        encoder.append_frame(&Frame::new(0x00, vec![0x01])).unwrap();
+        encoder
+            .append_frame(&Frame::new(0x2A, vec![7u8; 300]))
+            .unwrap();
+        let bytes = encoder.take();
+
+        let mut decoder = FrameDecoder::new();
+        let mut frames = Vec::new();
+        for byte in bytes.iter() {
+            decoder.queue_slice(&[*byte]);
+            while let Some(frame) = decoder.try_next_frame().unwrap() {
+                frames.push(frame);
+            }
+        }
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[1], Frame::new(0x2A, vec![7u8; 300]));
+    }
+
+    #[test]
+    fn test_frame_encode_layout() {
+        let mut encoder = FrameEncoder::new();
+        encoder.append_frame(&Frame::new(0x01, vec![0xAB])).unwrap();
+        assert_eq!(&encoder.take()[..], &[0x02, 0x01, 0xAB]);
+    }
+
+    #[test]
+    fn test_frame_too_large() {
+        let mut encoder = FrameEncoder::new();
+        let frame = Frame::new(0x00, vec![0; MAX_FRAME_LENGTH]);
+        assert!(matches!(
+            encoder.append_frame(&frame),
+            Err(ProtocolError::LengthExceeded { .. })
+        ));
+
+        // a length of 2^21 needs a 4 byte VarInt
+        let mut decoder = FrameDecoder::new();
+        decoder.queue_slice(&[0x80, 0x80, 0x80, 0x01]);
+        assert!(matches!(
+            decoder.try_next_frame(),
+            Err(ProtocolError::VarIntTooLong)
+        ));
+    }
+
+    #[test]
+    fn test_frame_compression_round_trip() {
+        let mut encoder = FrameEncoder::new();
+        encoder.set_compression(Some(256));
+        encoder
+            .append_frame(&Frame::new(0x01, vec![1; 16]))
+            .unwrap();
+        encoder
+            .append_frame(&Frame::new(0x02, vec![2; 4096]))
+            .unwrap();
+        let bytes = encoder.take();
+
+        // the large frame should have shrunk considerably
+        assert!(bytes.len() < 256);
+
+        let mut decoder = FrameDecoder::new();
+        decoder.set_compression(Some(256));
+        decoder.queue_bytes(bytes);
+
+        let small = decoder.try_next_frame().unwrap().unwrap();
+        assert_eq!(small, Frame::new(0x01, vec![1; 16]));
+
+        let large = decoder.try_next_frame().unwrap().unwrap();
+        assert_eq!(large, Frame::new(0x02, vec![2; 4096]));
+    }
+
+    #[test]
+    fn test_frame_compression_uncompressed_layout() {
+        let mut encoder = FrameEncoder::new();
+        encoder.set_compression(Some(256));
+        encoder.append_frame(&Frame::new(0x01, vec![0xAB])).unwrap();
+        assert_eq!(&encoder.take()[..], &[0x03, 0x00, 0x01, 0xAB]);
+    }
+
+    #[test]
+    fn test_frame_compression_below_threshold() {
+        let mut encoder = FrameEncoder::new();
+        encoder.set_compression(Some(16));
+        encoder
+            .append_frame(&Frame::new(0x01, vec![0; 64]))
+            .unwrap();
+
+        let mut decoder = FrameDecoder::new();
+        decoder.set_compression(Some(1024));
+        decoder.queue_bytes(encoder.take());
+        assert!(matches!(
+            decoder.try_next_frame(),
+            Err(ProtocolError::BelowCompressionThreshold { .. })
+        ));
+    }
+
+    #[test]
+    fn test_frame_compression_length_mismatch() {
+        let mut compressor = ZlibEncoder::new(Vec::new(), Compression::default());
+        compressor.write_all(&[0; 512]).unwrap();
+        let compressed = compressor.finish().unwrap();
+
+        // claims 300 bytes but inflates to 512
+        let mut bytes = Vec::new();
+        let data_length = VarInt(300);
+        VarInt((data_length.byte_len() + compressed.len()) as i32)
+            .encode(&mut bytes)
+            .unwrap();
+        data_length.encode(&mut bytes).unwrap();
+        bytes.extend_from_slice(&compressed);
+
+        let mut decoder = FrameDecoder::new();
+        decoder.set_compression(Some(256));
+        decoder.queue_slice(&bytes);
+        assert!(matches!(
+            decoder.try_next_frame(),
+            Err(ProtocolError::UncompressedLengthMismatch {
+                expected: 300,
+                actual: 301
+            })
+        ));
+    }
+
+    #[test]
+    fn test_frame_encryption_round_trip() {
+        let secret = crate::encryption::generate_shared_secret();
+
+        let mut encoder = FrameEncoder::new();
+        encoder.append_frame(&Frame::new(0x01, vec![1; 8])).unwrap();
+        encoder.enable_encryption(&secret);
+        encoder.set_compression(Some(64));
+        encoder.append_frame(&Frame::new(0x02, vec![2; 8])).unwrap();
+        encoder
+            .append_frame(&Frame::new(0x03, vec![3; 512]))
+            .unwrap();
+        let bytes = encoder.take();
+
+        // the frame written before encryption was enabled stays readable
+        assert_eq!(&bytes[..3], &[0x09, 0x01, 0x01]);
+
+        let mut decoder = FrameDecoder::new();
+        decoder.queue_slice(&bytes[..10]);
+        let first = decoder.try_next_frame().unwrap().unwrap();
+        assert_eq!(first, Frame::new(0x01, vec![1; 8]));
+
+        decoder.enable_encryption(&secret);
+        decoder.set_compression(Some(64));
+        for chunk in bytes[10..].chunks(7) {
+            decoder.queue_slice(chunk);
+        }
+
+        let second = decoder.try_next_frame().unwrap().unwrap();
+        assert_eq!(second, Frame::new(0x02, vec![2; 8]));
+
+        let third = decoder.try_next_frame().unwrap().unwrap();
+        assert_eq!(third, Frame::new(0x03, vec![3; 512]));
+
+        assert!(format!("{encoder:?}").contains("encrypted: true"));
+        assert!(format!("{decoder:?}").contains("encrypted: true"));
+    }
+
+    #[test]
+    fn test_frame_trailing_bytes() {
+        let frame = Frame::new(0x00, vec![0x00, 0x00, 0x00, 0x01, 0xFF]);
+        assert!(matches!(
+            frame.decode::<i32>(ProtocolVersion::LATEST),
+            Err(ProtocolError::TrailingBytes { remaining: 1, .. })
+        ));
+    }
+}
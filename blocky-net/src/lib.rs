@@ -1,4 +1,18 @@
+// lets the derives from blocky-derive be used inside this crate
+extern crate self as blocky_net;
+
+#[cfg(feature = "tokio")]
+pub mod async_io;
+pub mod borrowed;
+pub mod codec;
+#[cfg(feature = "tokio")]
+pub mod connection;
 pub mod decoder;
 pub mod encoder;
+pub mod encryption;
+pub mod error;
+pub mod frame;
 pub mod packet;
+pub mod registry;
 pub mod types;
+pub mod version;
@@ -1,4 +1,4 @@
-use crate::{decoder::Decoder, encoder::Encoder};
+use crate::{decoder::Decoder, encoder::Encoder, version::ProtocolVersion};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
 pub enum PacketFlow {
@@ -6,4 +6,33 @@ pub enum PacketFlow {
     Serverbound,
 }
 
-pub trait Packet: Encoder + Decoder {}
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
+pub enum ConnectionState {
+    Handshaking,
+    Status,
+    Login,
+    Configuration,
+    Play,
+}
+
+pub trait Packet: Encoder + Decoder {
+    /// The id written before the packet body in the newest version the packet
+    /// is part of, unique within a state and flow.
+    const ID: i32;
+    /// The connection state the packet can be sent in.
+    const STATE: ConnectionState;
+    /// The direction the packet is sent in.
+    const FLOW: PacketFlow;
+
+    /// The id of the packet in `version`, or `None` if the packet isn't part
+    /// of it.
+    fn id_for(version: ProtocolVersion) -> Option<i32> {
+        let _ = version;
+        Some(Self::ID)
+    }
+
+    /// The state the connection switches to once this packet has been sent.
+    fn next_state(&self) -> Option<ConnectionState> {
+        None
+    }
+}
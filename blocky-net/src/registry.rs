@@ -0,0 +1,468 @@
+use std::collections::HashMap;
+
+use crate::{
+    error::{ProtocolError, Result},
+    frame::Frame,
+    packet::{ConnectionState, Packet, PacketFlow},
+    version::ProtocolVersion,
+};
+
+pub type DecodeFn<T> = fn(&Frame, ProtocolVersion) -> Result<T>;
+
+/// Maps the state, flow and id of a frame to the packet it should be decoded
+/// as in one protocol version, converting the packet into `T` once decoded.
+pub struct PacketRegistry<T> {
+    version: ProtocolVersion,
+    decoders: HashMap<(ConnectionState, PacketFlow, i32), DecodeFn<T>>,
+}
+
+impl<T> Default for PacketRegistry<T> {
+    fn default() -> Self {
+        Self::for_version(ProtocolVersion::LATEST)
+    }
+}
+
+impl<T> PacketRegistry<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn for_version(version: ProtocolVersion) -> Self {
+        Self {
+            version,
+            decoders: HashMap::new(),
+        }
+    }
+
+    pub fn version(&self) -> ProtocolVersion {
+        self.version
+    }
+
+    /// Registers `P` under its own state, flow and its id in the version of
+    /// this registry, skipping it if it isn't part of that version.
+    ///
+    /// # Panics
+    ///
+    /// Panics if another packet is already registered under the same key.
+    pub fn register<P>(&mut self) -> &mut Self
+    where
+        P: Packet + Into<T>,
+    {
+        let Some(id) = P::id_for(self.version) else {
+            return self;
+        };
+
+        let key = (P::STATE, P::FLOW, id);
+        if self.decoders.insert(key, decode_into::<P, T>).is_some() {
+            panic!(
+                "{:?} {:?} packet id {:#04x} is registered twice for {}",
+                P::STATE,
+                P::FLOW,
+                id,
+                self.version
+            );
+        }
+
+        self
+    }
+
+    pub fn get(&self, state: ConnectionState, flow: PacketFlow, id: i32) -> Option<DecodeFn<T>> {
+        self.decoders.get(&(state, flow, id)).copied()
+    }
+
+    /// Decodes the frame as whichever packet is registered for its id, with
+    /// the fields of the version of this registry.
+    pub fn decode(&self, state: ConnectionState, flow: PacketFlow, frame: &Frame) -> Result<T> {
+        match self.get(state, flow, frame.id) {
+            Some(decode) => decode(frame, self.version),
+            None => Err(ProtocolError::UnknownPacketId {
+                state,
+                flow,
+                id: frame.id,
+            }),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.decoders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.decoders.is_empty()
+    }
+}
+
+fn decode_into<P, T>(frame: &Frame, version: ProtocolVersion) -> Result<T>
+where
+    P: Packet + Into<T>,
+{
+    frame.decode::<P>(version).map(Into::into)
+}
+
+/// Every packet that can be sent in one state and flow, usually an enum
+/// generated by [`packet_group!`](crate::packet_group).
+pub trait PacketGroup: Sized + 'static {
+    const STATE: ConnectionState;
+    const FLOW: PacketFlow;
+
+    /// The registry of the packets in `version`, or `None` if there is no
+    /// packet table for it.
+    fn registry_for(version: ProtocolVersion) -> Option<&'static PacketRegistry<Self>>;
+
+    /// What a frame with an id no packet of the group has is decoded as, for
+    /// groups that keep such frames instead of failing on them.
+    fn unknown(frame: &Frame) -> Option<Self> {
+        let _ = frame;
+        None
+    }
+
+    /// The registry of the packets in the latest version.
+    fn registry() -> &'static PacketRegistry<Self> {
+        Self::registry_for(ProtocolVersion::LATEST).expect("packet groups cover the latest version")
+    }
+
+    /// Decodes the frame as it is in the latest version.
+    fn decode_frame(frame: &Frame) -> Result<Self> {
+        Self::decode_frame_for(ProtocolVersion::LATEST, frame)
+    }
+
+    fn decode_frame_for(version: ProtocolVersion, frame: &Frame) -> Result<Self> {
+        match Self::registry_for(version) {
+            Some(registry) => match registry.get(Self::STATE, Self::FLOW, frame.id) {
+                Some(decode) => decode(frame, version),
+                None => Self::unknown(frame).ok_or(ProtocolError::UnknownPacketId {
+                    state: Self::STATE,
+                    flow: Self::FLOW,
+                    id: frame.id,
+                }),
+            },
+            None => Err(ProtocolError::UnsupportedVersion {
+                state: Self::STATE,
+                flow: Self::FLOW,
+                version,
+            }),
+        }
+    }
+}
+
+/// Declares an enum with a variant for every listed packet and implements
+/// [`PacketGroup`] for it, registering each packet with a registry for every
+/// supported version.
+///
+/// A group whose packets only have ids for newer versions can say which
+/// version it starts at with `since`, and has no registry before it. A group
+/// that names an `unknown` variant gets it with the frame in it for ids none
+/// of its packets have, rather than failing with
+/// [`ProtocolError::UnknownPacketId`].
+///
+/// ```ignore
+/// packet_group! {
+///     pub enum ClientboundStatusPacket(Status, Clientbound) {
+///         StatusResponse,
+///         PongResponse,
+///     }
+/// }
+///
+/// packet_group! {
+///     pub enum ClientboundPlayPacket(Play, Clientbound, since = V1_20_6, unknown = Unknown) {
+///         BundleDelimiter,
+///     }
+/// }
+/// ```
+///
+/// Every packet has to belong to the state and flow of the group, which is
+/// checked when the group is compiled:
+///
+/// ```compile_fail
+/// # use blocky_net::packet_group;
+/// # use blocky_derive::Packet;
+/// #[derive(Packet)]
+/// #[packet(id = 0x00, state = Status, flow = Serverbound)]
+/// struct StatusRequest;
+///
+/// packet_group! {
+///     enum ClientboundStatusPacket(Status, Clientbound) {
+///         StatusRequest,
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! packet_group {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident(
+            $state:ident,
+            $flow:ident
+            $(, since = $since:ident)?
+            $(, unknown = $unknown:ident)?
+        ) {
+            $($packet:ident),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $($packet($packet),)*
+            $($unknown($crate::frame::Frame),)?
+        }
+
+        $(
+            impl From<$packet> for $name {
+                fn from(value: $packet) -> Self {
+                    Self::$packet(value)
+                }
+            }
+
+            // the registries look packets up by the state and flow of the
+            // group, so a packet of another one could never be decoded
+            const _: () = assert!(
+                matches!(
+                    <$packet as $crate::packet::Packet>::STATE,
+                    $crate::packet::ConnectionState::$state
+                ) && matches!(
+                    <$packet as $crate::packet::Packet>::FLOW,
+                    $crate::packet::PacketFlow::$flow
+                ),
+                concat!(
+                    stringify!($packet),
+                    " is not a ",
+                    stringify!($state),
+                    " ",
+                    stringify!($flow),
+                    " packet"
+                ),
+            );
+        )*
+
+        impl $crate::registry::PacketGroup for $name {
+            const STATE: $crate::packet::ConnectionState = $crate::packet::ConnectionState::$state;
+            const FLOW: $crate::packet::PacketFlow = $crate::packet::PacketFlow::$flow;
+
+            fn registry_for(
+                version: $crate::version::ProtocolVersion,
+            ) -> Option<&'static $crate::registry::PacketRegistry<Self>> {
+                static REGISTRIES: std::sync::OnceLock<Vec<$crate::registry::PacketRegistry<$name>>> =
+                    std::sync::OnceLock::new();
+
+                $(
+                    if version < $crate::version::ProtocolVersion::$since {
+                        return None;
+                    }
+                )?
+
+                REGISTRIES
+                    .get_or_init(|| {
+                        $crate::version::ProtocolVersion::SUPPORTED
+                            .into_iter()
+                            .map(|version| {
+                                let mut registry = $crate::registry::PacketRegistry::for_version(version);
+                                $(registry.register::<$packet>();)*
+                                registry
+                            })
+                            .collect()
+                    })
+                    .iter()
+                    .find(|registry| registry.version() == version)
+            }
+
+            $(
+                fn unknown(frame: &$crate::frame::Frame) -> Option<Self> {
+                    Some(Self::$unknown(frame.clone()))
+                }
+            )?
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    use blocky_derive::Packet;
+
+    use super::*;
+    use crate::{
+        decoder::Decoder,
+        encoder::Encoder,
+        frame::FrameEncoder,
+        types::{LengthPrefixedVecU8, VarInt},
+    };
+
+    #[derive(Debug, PartialEq)]
+    pub struct Ping(u64);
+
+    impl Encoder for Ping {
+        fn byte_len(&self) -> usize {
+            8
+        }
+
+        fn encode<T: Write>(&self, buf: &mut T) -> Result<()> {
+            self.0.encode(buf)
+        }
+    }
+
+    impl Decoder for Ping {
+        fn decode<T: Read>(buf: &mut T) -> Result<Self> {
+            Ok(Self(u64::decode(buf)?))
+        }
+    }
+
+    impl Packet for Ping {
+        const ID: i32 = 0x01;
+        const STATE: ConnectionState = ConnectionState::Status;
+        const FLOW: PacketFlow = PacketFlow::Serverbound;
+    }
+
+    packet_group! {
+        #[derive(Debug, PartialEq)]
+        enum StatusPacket(Status, Serverbound) {
+            Ping,
+        }
+    }
+
+    #[test]
+    fn test_registry_dispatch() {
+        let frame = Frame::from_packet(&Ping(42), ProtocolVersion::LATEST).unwrap();
+        assert_eq!(
+            StatusPacket::decode_frame(&frame).unwrap(),
+            StatusPacket::Ping(Ping(42))
+        );
+
+        let unknown = Frame::new(0x05, Vec::new());
+        assert!(matches!(
+            StatusPacket::decode_frame(&unknown),
+            Err(ProtocolError::UnknownPacketId { id: 0x05, .. })
+        ));
+    }
+
+    packet_group! {
+        #[derive(Debug, PartialEq)]
+        enum LenientStatusPacket(Status, Serverbound, unknown = Unknown) {
+            Ping,
+        }
+    }
+
+    #[test]
+    fn test_registry_unknown() {
+        let frame = Frame::from_packet(&Ping(42), ProtocolVersion::LATEST).unwrap();
+        assert_eq!(
+            LenientStatusPacket::decode_frame(&frame).unwrap(),
+            LenientStatusPacket::Ping(Ping(42))
+        );
+
+        // frames the group has no packet for are handed back as they are
+        let unknown = Frame::new(0x05, vec![1, 2]);
+        assert_eq!(
+            LenientStatusPacket::decode_frame(&unknown).unwrap(),
+            LenientStatusPacket::Unknown(unknown.clone())
+        );
+
+        // but a packet the group has still has to decode
+        let truncated = Frame::new(0x01, vec![0]);
+        assert!(LenientStatusPacket::decode_frame(&truncated).is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_registry_duplicate() {
+        PacketRegistry::<StatusPacket>::new()
+            .register::<Ping>()
+            .register::<Ping>();
+    }
+
+    #[derive(Packet)]
+    #[packet(id = 0x03, state = Status, flow = Serverbound)]
+    struct Wrapper<T> {
+        inner: T,
+    }
+
+    #[test]
+    fn test_generic_packet() {
+        let packet = Wrapper { inner: VarInt(300) };
+        let frame = Frame::from_packet(&packet, ProtocolVersion::LATEST).unwrap();
+        assert_eq!((frame.id, &frame.body[..]), (0x03, &[0xAC, 0x02][..]));
+        let decoded = frame
+            .decode::<Wrapper<VarInt>>(ProtocolVersion::LATEST)
+            .unwrap();
+        assert_eq!(decoded.inner, VarInt(300));
+
+        let packet = Wrapper {
+            inner: LengthPrefixedVecU8::<VarInt>::from(vec![1, 2]),
+        };
+        let frame = Frame::from_packet(&packet, ProtocolVersion::LATEST).unwrap();
+        assert_eq!(frame.body[..], [0x02, 0x01, 0x02]);
+        let decoded = frame
+            .decode::<Wrapper<LengthPrefixedVecU8<VarInt>>>(ProtocolVersion::LATEST)
+            .unwrap();
+        assert_eq!(decoded.inner.0, [1, 2]);
+    }
+
+    #[derive(Packet, Debug, PartialEq)]
+    #[packet(id = 0x02, state = Status, flow = Clientbound, ids(V1_20_4 = 0x01))]
+    struct Pong {
+        time: u64,
+        #[versions(V1_20_6..)]
+        extra: Option<u8>,
+    }
+
+    #[derive(Packet, Debug, PartialEq)]
+    #[packet(id = 0x01, state = Status, flow = Clientbound)]
+    #[versions(V1_20_6..)]
+    struct Status;
+
+    packet_group! {
+        #[derive(Debug, PartialEq)]
+        enum ClientboundStatusPacket(Status, Clientbound) {
+            Pong,
+            Status,
+        }
+    }
+
+    packet_group! {
+        enum NewStatusPacket(Status, Clientbound, since = V1_21) {
+            Status,
+        }
+    }
+
+    #[test]
+    fn test_registry_versions() {
+        assert_eq!(Pong::id_for(ProtocolVersion::V1_20_4), Some(0x01));
+        assert_eq!(Pong::id_for(ProtocolVersion::V1_21), Some(0x02));
+        assert_eq!(Status::id_for(ProtocolVersion::V1_20_4), None);
+
+        let old = ClientboundStatusPacket::registry_for(ProtocolVersion::V1_20_4).unwrap();
+        assert_eq!(old.len(), 1);
+        assert_eq!(ClientboundStatusPacket::registry().len(), 2);
+        assert!(ClientboundStatusPacket::registry_for(ProtocolVersion(1)).is_none());
+        assert!(NewStatusPacket::registry_for(ProtocolVersion::V1_20_6).is_none());
+
+        let mut encoder = FrameEncoder::new();
+        encoder.set_version(ProtocolVersion::V1_20_4);
+        assert!(matches!(
+            encoder.append_packet(&Status),
+            Err(ProtocolError::NotInVersion { id: 0x01, .. })
+        ));
+
+        // the field only written from 1.20.5 on has to be left out before it
+        let pong = Pong {
+            time: 7,
+            extra: Some(1),
+        };
+        assert!(matches!(
+            encoder.append_packet(&pong),
+            Err(ProtocolError::Context { .. })
+        ));
+        let pong = Pong {
+            time: 7,
+            extra: None,
+        };
+        encoder.append_packet(&pong).unwrap();
+        assert_eq!(encoder.take()[..2], [0x09, 0x01]);
+
+        let frame = Frame::new(0x01, 7u64.to_bytes().unwrap());
+        assert_eq!(
+            ClientboundStatusPacket::decode_frame_for(ProtocolVersion::V1_20_4, &frame).unwrap(),
+            ClientboundStatusPacket::Pong(pong)
+        );
+        assert!(ClientboundStatusPacket::decode_frame(&frame).is_err());
+    }
+}
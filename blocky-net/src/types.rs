@@ -1,16 +1,23 @@
 use std::{
     io::{Read, Write},
-    iter,
     marker::PhantomData,
     ops::{Deref, DerefMut},
 };
 
-use crate::{decoder::Decoder, encoder::Encoder};
+use blocky_derive::{Decoder, Encoder};
+
+use crate::{
+    codec::decode_length,
+    decoder::{CountingReader, Decoder},
+    encoder::Encoder,
+    error::{ProtocolError, Result},
+    version::ProtocolVersion,
+};
 
 pub struct LengthInferredVecU8(pub Vec<u8>);
 
 impl Decoder for LengthInferredVecU8 {
-    fn decode<T: Read>(buf: &mut T) -> anyhow::Result<Self> {
+    fn decode<T: Read>(buf: &mut T) -> Result<Self> {
         let mut v = Vec::new();
         buf.read_to_end(&mut v)?;
         Ok(Self(v))
@@ -22,7 +29,7 @@ impl Encoder for LengthInferredVecU8 {
         self.0.len()
     }
 
-    fn encode<T: Write>(&self, buf: &mut T) -> anyhow::Result<()> {
+    fn encode<T: Write>(&self, buf: &mut T) -> Result<()> {
         buf.write_all(&self.0)?;
         Ok(())
     }
@@ -33,6 +40,14 @@ pub const MAX_LENGTH: usize = 1024 * 1024;
 pub trait LengthPrefix {
     fn len(&self) -> usize;
     fn from_len(value: usize) -> Self;
+
+    /// The length as read off the wire, failing if it is negative rather
+    /// than wrapping around to a huge `usize` the way [`len`](Self::len)
+    /// does.
+    fn checked_len(&self) -> Result<usize> {
+        Ok(self.len())
+    }
+
     fn is_empty(&self) -> bool {
         self.len() == 0
     }
@@ -42,12 +57,21 @@ pub struct LengthPrefixedVecU8<L>(pub Vec<u8>, PhantomData<L>)
 where
     L: Encoder + Decoder + LengthPrefix;
 
+impl<L> From<Vec<u8>> for LengthPrefixedVecU8<L>
+where
+    L: Encoder + Decoder + LengthPrefix,
+{
+    fn from(value: Vec<u8>) -> Self {
+        Self(value, PhantomData)
+    }
+}
+
 impl<L> Decoder for LengthPrefixedVecU8<L>
 where
     L: Encoder + Decoder + LengthPrefix,
 {
-    fn decode<T: Read>(buf: &mut T) -> anyhow::Result<Self> {
-        let len = L::decode(buf)?.len();
+    fn decode<T: Read>(buf: &mut T) -> Result<Self> {
+        let len = decode_length::<L, _>(buf)?;
         let mut data = vec![0; len];
         buf.read_exact(&mut data)?;
         Ok(Self(data, PhantomData))
@@ -62,7 +86,7 @@ where
         L::from_len(self.0.len()).byte_len() + self.0.len()
     }
 
-    fn encode<T: Write>(&self, buf: &mut T) -> anyhow::Result<()> {
+    fn encode<T: Write>(&self, buf: &mut T) -> Result<()> {
         L::from_len(self.0.len()).encode(buf)?;
         buf.write_all(&self.0)?;
         Ok(())
@@ -74,20 +98,38 @@ where
     L: Encoder + Decoder + LengthPrefix,
     V: Encoder + Decoder;
 
+impl<L, V> From<Vec<V>> for LengthPrefixedVec<L, V>
+where
+    L: Encoder + Decoder + LengthPrefix,
+    V: Encoder + Decoder,
+{
+    fn from(value: Vec<V>) -> Self {
+        Self(value, PhantomData)
+    }
+}
+
 impl<L, V> Decoder for LengthPrefixedVec<L, V>
 where
     L: Encoder + Decoder + LengthPrefix,
     V: Encoder + Decoder,
 {
-    fn decode<T: Read>(buf: &mut T) -> anyhow::Result<Self> {
-        let len = L::decode(buf)?.len();
-        if len > MAX_LENGTH {
-            anyhow::bail!("Length exceeds maximum allowed length");
-        }
+    fn decode<T: Read>(buf: &mut T) -> Result<Self> {
+        Self::decode_counted(&mut CountingReader::new(buf))
+    }
 
-        let v = iter::repeat_with(|| V::decode(buf))
-            .take(len)
-            .collect::<anyhow::Result<Vec<V>>>()?;
+    fn decode_for<T: Read>(version: ProtocolVersion, buf: &mut T) -> Result<Self> {
+        Self::decode_counted(&mut CountingReader::for_version(buf, version))
+    }
+
+    fn decode_counted<T: Read>(buf: &mut CountingReader<T>) -> Result<Self> {
+        let start = buf.position();
+        let len = decode_length::<L, _>(buf)?;
+
+        let mut v = Vec::with_capacity(len);
+        for i in 0..len {
+            let offset = buf.position() - start;
+            v.push(V::decode_counted(buf).map_err(|e| e.at_index(i).at_offset(offset))?);
+        }
 
         Ok(Self(v, PhantomData))
     }
@@ -102,11 +144,21 @@ where
         L::from_len(self.0.len()).byte_len() + self.0.iter().map(|v| v.byte_len()).sum::<usize>()
     }
 
-    fn encode<T: Write>(&self, buf: &mut T) -> anyhow::Result<()> {
+    fn encode<T: Write>(&self, buf: &mut T) -> Result<()> {
+        L::from_len(self.0.len()).encode(buf)?;
+
+        for (i, item) in self.0.iter().enumerate() {
+            item.encode(buf).map_err(|e| e.at_index(i))?;
+        }
+
+        Ok(())
+    }
+
+    fn encode_for<T: Write>(&self, version: ProtocolVersion, buf: &mut T) -> Result<()> {
         L::from_len(self.0.len()).encode(buf)?;
 
-        for item in &self.0 {
-            item.encode(buf)?;
+        for (i, item) in self.0.iter().enumerate() {
+            item.encode_for(version, buf).map_err(|e| e.at_index(i))?;
         }
 
         Ok(())
@@ -116,6 +168,36 @@ where
 static SEGMENT_BITS: u8 = 0b01111111;
 static CONTINUE_BIT: u8 = 0b10000000;
 
+/// The value of a VarInt or VarLong of at most `BITS` bits as its bytes come
+/// in, which the sync and async decoders feed one byte at a time.
+#[derive(Default)]
+pub(crate) struct VarNumReader<const BITS: u32> {
+    value: u64,
+    position: u32,
+}
+
+impl<const BITS: u32> VarNumReader<BITS> {
+    /// Adds the next byte, returning the value once its last byte is in.
+    pub(crate) fn push(&mut self, byte: u8) -> Result<Option<u64>> {
+        self.value |= ((byte & SEGMENT_BITS) as u64) << self.position;
+
+        if byte & CONTINUE_BIT == 0 {
+            return Ok(Some(self.value));
+        }
+
+        self.position += 7;
+
+        if self.position >= BITS {
+            return Err(match BITS {
+                32 => ProtocolError::VarIntTooLong,
+                _ => ProtocolError::VarLongTooLong,
+            });
+        }
+
+        Ok(None)
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Default, PartialOrd, Ord)]
 pub struct VarInt(pub i32);
 
@@ -127,6 +209,10 @@ impl LengthPrefix for VarInt {
     fn from_len(value: usize) -> Self {
         Self(value as i32)
     }
+
+    fn checked_len(&self) -> Result<usize> {
+        usize::try_from(self.0).map_err(|_| ProtocolError::NegativeLength(self.0.into()))
+    }
 }
 
 impl From<i32> for VarInt {
@@ -156,31 +242,18 @@ impl DerefMut for VarInt {
 }
 
 impl Decoder for VarInt {
-    fn decode<T: Read>(buf: &mut T) -> anyhow::Result<Self> {
-        let mut value = 0;
-        let mut position = 0;
+    fn decode<T: Read>(buf: &mut T) -> Result<Self> {
+        let mut reader = VarNumReader::<32>::default();
         let mut byte_buf = [0];
 
         loop {
             // read a byte from the buffer
             buf.read_exact(&mut byte_buf)?;
 
-            let byte = byte_buf[0];
-
-            value |= ((byte & SEGMENT_BITS) as i32) << position;
-
-            if byte & CONTINUE_BIT == 0 {
-                break;
-            }
-
-            position += 7;
-
-            if position >= 32 {
-                anyhow::bail!("VarInt is too big");
+            if let Some(value) = reader.push(byte_buf[0])? {
+                return Ok(Self(value as i32));
             }
         }
-
-        Ok(Self(value))
     }
 }
 
@@ -197,7 +270,7 @@ impl Encoder for VarInt {
         5
     }
 
-    fn encode<T: Write>(&self, buf: &mut T) -> anyhow::Result<()> {
+    fn encode<T: Write>(&self, buf: &mut T) -> Result<()> {
         let mut value = self.0;
 
         loop {
@@ -225,6 +298,10 @@ impl LengthPrefix for VarLong {
     fn from_len(value: usize) -> Self {
         Self(value as i64)
     }
+
+    fn checked_len(&self) -> Result<usize> {
+        usize::try_from(self.0).map_err(|_| ProtocolError::NegativeLength(self.0))
+    }
 }
 
 impl From<i64> for VarLong {
@@ -254,31 +331,18 @@ impl DerefMut for VarLong {
 }
 
 impl Decoder for VarLong {
-    fn decode<T: Read>(buf: &mut T) -> anyhow::Result<Self> {
-        let mut value = 0;
-        let mut position = 0;
+    fn decode<T: Read>(buf: &mut T) -> Result<Self> {
+        let mut reader = VarNumReader::<64>::default();
         let mut byte_buf = [0];
 
         loop {
             // read a byte from the buffer
             buf.read_exact(&mut byte_buf)?;
 
-            let byte = byte_buf[0];
-
-            value |= ((byte & SEGMENT_BITS) as i64) << position;
-
-            if byte & CONTINUE_BIT == 0 {
-                break;
-            }
-
-            position += 7;
-
-            if position >= 64 {
-                anyhow::bail!("VarLong is too big");
+            if let Some(value) = reader.push(byte_buf[0])? {
+                return Ok(Self(value as i64));
             }
         }
-
-        Ok(Self(value))
     }
 }
 
@@ -295,7 +359,7 @@ impl Encoder for VarLong {
         10
     }
 
-    fn encode<T: Write>(&self, buf: &mut T) -> anyhow::Result<()> {
+    fn encode<T: Write>(&self, buf: &mut T) -> Result<()> {
         let mut value = self.0;
 
         loop {
@@ -312,6 +376,72 @@ impl Encoder for VarLong {
     }
 }
 
+/// A rotation in steps of 1/256 of a full turn.
+#[derive(Encoder, Decoder, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[transparent]
+pub struct Angle(pub u8);
+
+impl Angle {
+    pub fn from_degrees(degrees: f32) -> Self {
+        // wraps around like the server does rather than saturating
+        Self((degrees * 256.0 / 360.0).floor() as i32 as u8)
+    }
+
+    pub fn degrees(self) -> f32 {
+        self.0 as f32 * 360.0 / 256.0
+    }
+}
+
+/// A set of bits packed into longs, prefixed with how many longs follow.
+#[derive(Encoder, Decoder, Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[transparent]
+pub struct BitSet(#[prefixed(VarInt)] pub Vec<i64>);
+
+impl BitSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, index: usize) -> bool {
+        self.0
+            .get(index / 64)
+            .is_some_and(|word| word & (1 << (index % 64)) != 0)
+    }
+
+    pub fn set(&mut self, index: usize, value: bool) {
+        let word = index / 64;
+        if value {
+            if word >= self.0.len() {
+                self.0.resize(word + 1, 0);
+            }
+
+            self.0[word] |= 1 << (index % 64);
+        } else if word < self.0.len() {
+            self.0[word] &= !(1 << (index % 64));
+
+            // the server leaves out trailing empty longs
+            while self.0.last() == Some(&0) {
+                self.0.pop();
+            }
+        }
+    }
+}
+
+/// A number stored as an integer with `BITS` fractional bits.
+#[derive(Encoder, Decoder, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[transparent]
+pub struct FixedPoint<const BITS: u32 = 5>(pub i32);
+
+impl<const BITS: u32> FixedPoint<BITS> {
+    pub fn from_f64(value: f64) -> Self {
+        Self((value * (1 << BITS) as f64) as i32)
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / (1 << BITS) as f64
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -493,4 +623,80 @@ mod tests {
             vec![0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01]
         );
     }
+
+    #[test]
+    fn test_angle() {
+        assert_eq!(Angle::from_degrees(90.0), Angle(64));
+        assert_eq!(Angle::from_degrees(-90.0), Angle(192));
+        assert_eq!(Angle::from_degrees(360.0), Angle(0));
+        assert_eq!(Angle(128).degrees(), 180.0);
+        assert_eq!(Angle(64).to_bytes().unwrap(), vec![64]);
+    }
+
+    #[test]
+    fn test_bit_set() {
+        let mut bits = BitSet::new();
+        bits.set(1, true);
+        bits.set(65, true);
+        assert!(bits.get(1) && bits.get(65) && !bits.get(2) && !bits.get(1000));
+
+        let bytes = bits.to_bytes().unwrap();
+        assert_eq!(bytes.len(), 1 + 16);
+        assert_eq!(&bytes[..9], &[0x02, 0, 0, 0, 0, 0, 0, 0, 0x02]);
+        assert_eq!(BitSet::from_bytes(&bytes).unwrap(), bits);
+
+        bits.set(65, false);
+        assert_eq!(bits, BitSet(vec![0b10]));
+    }
+
+    #[test]
+    fn test_fixed_point() {
+        let value = FixedPoint::<5>::from_f64(1.5);
+        assert_eq!(value, FixedPoint(48));
+        assert_eq!(value.to_f64(), 1.5);
+        assert_eq!(value.to_bytes().unwrap(), vec![0, 0, 0, 48]);
+    }
+
+    #[test]
+    fn test_transparent_error_path() {
+        // the error is reported as if it happened in the long array itself
+        let err = BitSet::from_bytes(&[0x01, 0x00]).unwrap_err();
+        assert_eq!(err.to_string(), "[0] at byte 1: unexpected end of input");
+    }
+
+    #[test]
+    fn test_byte_vec_length_checked() {
+        let bytes = LengthPrefixedVecU8::<VarInt>::from_bytes(&[0x02, 0x01, 0x02]).unwrap();
+        assert_eq!(bytes.0, [0x01, 0x02]);
+
+        let err = LengthPrefixedVecU8::<VarInt>::from_bytes(&[0xFF, 0xFF, 0xFF, 0xFF, 0x0F])
+            .err()
+            .unwrap();
+        assert!(matches!(err, ProtocolError::NegativeLength(-1)));
+
+        // a length far past the limit fails before it is allocated
+        let err = LengthPrefixedVecU8::<VarInt>::from_bytes(&[0xFF, 0xFF, 0xFF, 0xFF, 0x07])
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err,
+            ProtocolError::LengthExceeded {
+                len: 2147483647,
+                max: MAX_LENGTH
+            }
+        ));
+
+        let err = LengthPrefixedVecU8::<i16>::from_bytes(&[0xFF, 0xFE])
+            .err()
+            .unwrap();
+        assert!(matches!(err, ProtocolError::NegativeLength(-2)));
+    }
+
+    #[test]
+    fn test_vec_length_checked() {
+        let err = LengthPrefixedVec::<VarInt, u8>::from_bytes(&[0xFF, 0xFF, 0xFF, 0xFF, 0x0F])
+            .err()
+            .unwrap();
+        assert!(matches!(err, ProtocolError::NegativeLength(-1)));
+    }
 }
@@ -0,0 +1,75 @@
+use std::fmt;
+
+/// A version of the protocol, as sent in the handshake and the status
+/// response.
+///
+/// Packets whose id or fields changed between versions say so with
+/// `#[packet(ids(...))]` and `#[versions(...)]`. The version is handed down
+/// through [`Encoder::encode_for`](crate::encoder::Encoder::encode_for) and
+/// [`Decoder::decode_for`](crate::decoder::Decoder::decode_for), which
+/// [`Frame::from_packet`](crate::frame::Frame::from_packet),
+/// [`Frame::decode`](crate::frame::Frame::decode), the frame encoder and the
+/// packet registries call with the version of the connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ProtocolVersion(pub i32);
+
+impl ProtocolVersion {
+    pub const V1_20_4: Self = Self(765);
+    /// 1.20.5 and 1.20.6, which share a protocol version.
+    pub const V1_20_6: Self = Self(766);
+    /// 1.21 and 1.21.1, which share a protocol version.
+    pub const V1_21: Self = Self(767);
+
+    pub const LATEST: Self = Self::V1_21;
+
+    /// Every version there are packet tables for, from oldest to newest.
+    pub const SUPPORTED: [Self; 3] = [Self::V1_20_4, Self::V1_20_6, Self::V1_21];
+
+    /// The supported version with the given number, if there is one.
+    pub fn from_protocol(protocol: i32) -> Option<Self> {
+        Self::SUPPORTED
+            .into_iter()
+            .find(|version| version.0 == protocol)
+    }
+
+    /// The newest release with this version, if it is a supported one.
+    pub fn name(self) -> Option<&'static str> {
+        match self {
+            Self::V1_20_4 => Some("1.20.4"),
+            Self::V1_20_6 => Some("1.20.6"),
+            Self::V1_21 => Some("1.21.1"),
+            _ => None,
+        }
+    }
+}
+
+impl Default for ProtocolVersion {
+    fn default() -> Self {
+        Self::LATEST
+    }
+}
+
+impl fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{name} ({})", self.0),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_lookup() {
+        assert_eq!(
+            ProtocolVersion::from_protocol(766),
+            Some(ProtocolVersion::V1_20_6)
+        );
+        assert_eq!(ProtocolVersion::from_protocol(764), None);
+        assert_eq!(ProtocolVersion::V1_21.to_string(), "1.21.1 (767)");
+        assert_eq!(ProtocolVersion(5).to_string(), "5");
+    }
+}
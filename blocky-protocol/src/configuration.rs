@@ -0,0 +1,366 @@
+use blocky_derive::{Decoder, Encoder, Packet};
+use blocky_nbt::NbtCompound;
+use blocky_net::{packet_group, types::VarInt};
+use blocky_world::resources::ResourceLocation;
+use uuid::Uuid;
+
+use crate::{
+    text::TextComponent,
+    types::{
+        ClientInformation as Information, CustomReportDetail, KnownPack, RegistryTags,
+        ResourcePackResult, ServerLink,
+    },
+};
+
+packet_group! {
+    pub enum ClientboundConfigurationPacket(Configuration, Clientbound) {
+        CookieRequest,
+        ClientboundPluginMessage,
+        Disconnect,
+        FinishConfiguration,
+        ClientboundKeepAlive,
+        Ping,
+        ResetChat,
+        RegistryData,
+        RegistryCodec,
+        RemoveResourcePack,
+        AddResourcePack,
+        StoreCookie,
+        Transfer,
+        FeatureFlags,
+        UpdateTags,
+        ClientboundKnownPacks,
+        CustomReportDetails,
+        ServerLinks,
+    }
+}
+
+packet_group! {
+    pub enum ServerboundConfigurationPacket(Configuration, Serverbound) {
+        ClientInformation,
+        CookieResponse,
+        ServerboundPluginMessage,
+        AcknowledgeFinishConfiguration,
+        ServerboundKeepAlive,
+        Pong,
+        ResourcePackResponse,
+        ServerboundKnownPacks,
+    }
+}
+
+// clientbound
+
+#[derive(Packet)]
+#[packet(id = 0x00, state = Configuration, flow = Clientbound)]
+#[versions(V1_20_6..)]
+pub struct CookieRequest {
+    pub key: ResourceLocation,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x01, state = Configuration, flow = Clientbound, ids(V1_20_4 = 0x00))]
+pub struct ClientboundPluginMessage {
+    pub channel: ResourceLocation,
+    #[rest]
+    pub data: Vec<u8>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x02, state = Configuration, flow = Clientbound, ids(V1_20_4 = 0x01))]
+pub struct Disconnect {
+    pub reason: TextComponent,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x03, state = Configuration, flow = Clientbound, ids(V1_20_4 = 0x02))]
+pub struct FinishConfiguration;
+
+#[derive(Packet)]
+#[packet(id = 0x04, state = Configuration, flow = Clientbound, ids(V1_20_4 = 0x03))]
+pub struct ClientboundKeepAlive {
+    pub id: i64,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x05, state = Configuration, flow = Clientbound, ids(V1_20_4 = 0x04))]
+pub struct Ping {
+    pub id: i32,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x06, state = Configuration, flow = Clientbound)]
+#[versions(V1_20_6..)]
+pub struct ResetChat;
+
+#[derive(Packet)]
+#[packet(id = 0x07, state = Configuration, flow = Clientbound)]
+#[versions(V1_20_6..)]
+pub struct RegistryData {
+    pub registry: ResourceLocation,
+    #[prefixed(VarInt)]
+    pub entries: Vec<RegistryEntry>,
+}
+
+/// Every registry the server sends in one compound, which is how they were
+/// sent before 1.20.5 split them into [`RegistryData`] packets.
+#[derive(Packet)]
+#[packet(id = 0x05, state = Configuration, flow = Clientbound)]
+#[versions(..V1_20_6)]
+pub struct RegistryCodec {
+    pub codec: NbtCompound,
+}
+
+#[derive(Encoder, Decoder, Debug, Clone, PartialEq)]
+pub struct RegistryEntry {
+    pub id: ResourceLocation,
+    /// Left out when the entry comes from a pack both sides know about.
+    pub data: Option<NbtCompound>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x08, state = Configuration, flow = Clientbound, ids(V1_20_4 = 0x06))]
+pub struct RemoveResourcePack {
+    /// The pack to remove, or every pack if `None`.
+    pub uuid: Option<Uuid>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x09, state = Configuration, flow = Clientbound, ids(V1_20_4 = 0x07))]
+pub struct AddResourcePack {
+    pub uuid: Uuid,
+    pub url: String,
+    #[max_len = 40]
+    pub hash: String,
+    pub forced: bool,
+    pub prompt_message: Option<TextComponent>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x0A, state = Configuration, flow = Clientbound)]
+#[versions(V1_20_6..)]
+pub struct StoreCookie {
+    pub key: ResourceLocation,
+    #[prefixed(VarInt)]
+    pub payload: Vec<u8>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x0B, state = Configuration, flow = Clientbound)]
+#[versions(V1_20_6..)]
+pub struct Transfer {
+    pub host: String,
+    #[varint]
+    pub port: i32,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x0C, state = Configuration, flow = Clientbound, ids(V1_20_4 = 0x08))]
+pub struct FeatureFlags {
+    #[prefixed(VarInt)]
+    pub flags: Vec<ResourceLocation>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x0D, state = Configuration, flow = Clientbound, ids(V1_20_4 = 0x09))]
+pub struct UpdateTags {
+    #[prefixed(VarInt)]
+    pub registries: Vec<RegistryTags>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x0E, state = Configuration, flow = Clientbound)]
+#[versions(V1_20_6..)]
+pub struct ClientboundKnownPacks {
+    #[prefixed(VarInt)]
+    pub packs: Vec<KnownPack>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x0F, state = Configuration, flow = Clientbound)]
+#[versions(V1_21..)]
+pub struct CustomReportDetails {
+    #[prefixed(VarInt)]
+    pub details: Vec<CustomReportDetail>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x10, state = Configuration, flow = Clientbound)]
+#[versions(V1_21..)]
+pub struct ServerLinks {
+    #[prefixed(VarInt)]
+    pub links: Vec<ServerLink>,
+}
+
+// serverbound
+
+#[derive(Packet)]
+#[packet(id = 0x00, state = Configuration, flow = Serverbound)]
+pub struct ClientInformation {
+    pub information: Information,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x01, state = Configuration, flow = Serverbound)]
+#[versions(V1_20_6..)]
+pub struct CookieResponse {
+    pub key: ResourceLocation,
+    #[prefixed(VarInt)]
+    pub payload: Option<Vec<u8>>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x02, state = Configuration, flow = Serverbound, ids(V1_20_4 = 0x01))]
+pub struct ServerboundPluginMessage {
+    pub channel: ResourceLocation,
+    #[rest]
+    pub data: Vec<u8>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x03, state = Configuration, flow = Serverbound, ids(V1_20_4 = 0x02), next_state = Play)]
+pub struct AcknowledgeFinishConfiguration;
+
+#[derive(Packet)]
+#[packet(id = 0x04, state = Configuration, flow = Serverbound, ids(V1_20_4 = 0x03))]
+pub struct ServerboundKeepAlive {
+    pub id: i64,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x05, state = Configuration, flow = Serverbound, ids(V1_20_4 = 0x04))]
+pub struct Pong {
+    pub id: i32,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x06, state = Configuration, flow = Serverbound, ids(V1_20_4 = 0x05))]
+pub struct ResourcePackResponse {
+    pub uuid: Uuid,
+    pub result: ResourcePackResult,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x07, state = Configuration, flow = Serverbound)]
+#[versions(V1_20_6..)]
+pub struct ServerboundKnownPacks {
+    #[prefixed(VarInt)]
+    pub packs: Vec<KnownPack>,
+}
+
+#[cfg(test)]
+mod tests {
+    use blocky_nbt::NbtTag;
+    use blocky_net::{
+        decoder::Decoder, encoder::Encoder, frame::Frame, registry::PacketGroup,
+        version::ProtocolVersion,
+    };
+
+    use super::*;
+    use crate::types::Tag;
+
+    #[test]
+    fn test_configuration_registries() {
+        assert_eq!(ClientboundConfigurationPacket::registry().len(), 17);
+        assert_eq!(ServerboundConfigurationPacket::registry().len(), 8);
+
+        let clientbound =
+            ClientboundConfigurationPacket::registry_for(ProtocolVersion::V1_20_6).unwrap();
+        assert_eq!(clientbound.len(), 15);
+
+        let clientbound =
+            ClientboundConfigurationPacket::registry_for(ProtocolVersion::V1_20_4).unwrap();
+        let serverbound =
+            ServerboundConfigurationPacket::registry_for(ProtocolVersion::V1_20_4).unwrap();
+        assert_eq!((clientbound.len(), serverbound.len()), (10, 6));
+    }
+
+    #[test]
+    fn test_registry_codec() {
+        // {"a": 1b} as the whole registry codec, sent with the id registry
+        // data has since 1.20.5
+        let frame = Frame::new(0x05, vec![0x0A, 0x01, 0x00, 0x01, b'a', 0x01, 0x00]);
+
+        match ClientboundConfigurationPacket::decode_frame_for(ProtocolVersion::V1_20_4, &frame)
+            .unwrap()
+        {
+            ClientboundConfigurationPacket::RegistryCodec(packet) => {
+                assert_eq!(packet.codec["a"], NbtTag::Byte(1))
+            }
+            _ => panic!("decoded the wrong packet"),
+        }
+
+        // and a ping with bytes left over in later versions
+        assert!(ClientboundConfigurationPacket::decode_frame(&frame).is_err());
+    }
+
+    #[test]
+    fn test_registry_data() {
+        let packet = RegistryData {
+            registry: "minecraft:dimension_type"
+                .parse::<ResourceLocation>()
+                .unwrap(),
+            entries: vec![
+                RegistryEntry {
+                    id: "minecraft:overworld".parse::<ResourceLocation>().unwrap(),
+                    data: None,
+                },
+                RegistryEntry {
+                    id: "minecraft:custom".parse::<ResourceLocation>().unwrap(),
+                    data: Some([("a".to_string(), 1i8.into())].into_iter().collect()),
+                },
+            ],
+        };
+
+        let frame = Frame::from_packet(&packet, ProtocolVersion::LATEST).unwrap();
+        assert_eq!(frame.id, 0x07);
+
+        match ClientboundConfigurationPacket::decode_frame(&frame).unwrap() {
+            ClientboundConfigurationPacket::RegistryData(decoded) => {
+                assert_eq!(decoded.registry, packet.registry);
+                assert_eq!(decoded.entries, packet.entries);
+            }
+            _ => panic!("decoded the wrong packet"),
+        }
+    }
+
+    #[test]
+    fn test_update_tags_layout() {
+        let tags = RegistryTags {
+            registry: "minecraft:item".parse::<ResourceLocation>().unwrap(),
+            tags: vec![Tag {
+                name: "minecraft:logs".parse::<ResourceLocation>().unwrap(),
+                entries: vec![1, 300],
+            }],
+        };
+
+        let bytes = tags.to_bytes().unwrap();
+        let mut expected = Vec::new();
+        "minecraft:item".encode(&mut expected).unwrap();
+        expected.push(0x01);
+        "minecraft:logs".encode(&mut expected).unwrap();
+        expected.extend_from_slice(&[0x02, 0x01, 0xAC, 0x02]);
+
+        assert_eq!(bytes, expected);
+        assert_eq!(tags.byte_len(), expected.len());
+        assert_eq!(RegistryTags::from_bytes(&bytes).unwrap(), tags);
+    }
+
+    #[test]
+    fn test_known_packs() {
+        let packet = ServerboundKnownPacks {
+            packs: vec![KnownPack {
+                namespace: "minecraft".to_string(),
+                id: "core".to_string(),
+                version: "1.21".to_string(),
+            }],
+        };
+
+        let frame = Frame::from_packet(&packet, ProtocolVersion::LATEST).unwrap();
+        assert_eq!(frame.body[0], 0x01);
+
+        let decoded = frame
+            .decode::<ServerboundKnownPacks>(ProtocolVersion::LATEST)
+            .unwrap();
+        assert_eq!(decoded.packs, packet.packs);
+    }
+}
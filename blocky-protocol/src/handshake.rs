@@ -0,0 +1,47 @@
+use blocky_derive::{Decoder, Encoder};
+use blocky_net::{
+    packet::{ConnectionState, Packet, PacketFlow},
+    packet_group,
+};
+
+packet_group! {
+    pub enum ServerboundHandshakePacket(Handshaking, Serverbound) {
+        Handshake,
+    }
+}
+
+// clientbound
+
+// serverbound
+
+#[derive(Encoder, Decoder)]
+pub struct Handshake {
+    #[varint]
+    pub protocol_version: i32,
+    #[max_len = 255]
+    pub server_address: String,
+    pub server_port: u16,
+    pub next_state: Intent,
+}
+
+/// What the client wants to do after the handshake.
+#[derive(Encoder, Decoder, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Intent {
+    #[discriminant = 1]
+    Status,
+    Login,
+    Transfer,
+}
+
+impl Packet for Handshake {
+    const ID: i32 = 0x00;
+    const STATE: ConnectionState = ConnectionState::Handshaking;
+    const FLOW: PacketFlow = PacketFlow::Serverbound;
+
+    fn next_state(&self) -> Option<ConnectionState> {
+        match self.next_state {
+            Intent::Status => Some(ConnectionState::Status),
+            Intent::Login | Intent::Transfer => Some(ConnectionState::Login),
+        }
+    }
+}
@@ -1,126 +1,121 @@
-pub mod handshake {
-    use blocky_derive::Packet;
-    use blocky_net::types::VarInt;
-
-    // clientbound
-
-    // serverbound
-
-    #[derive(Packet)]
-    pub struct Handshake {
-        pub protocol_version: VarInt,
-        pub server_address: String,
-        pub server_port: u16,
-        pub next_state: VarInt,
-    }
-}
-
-pub mod status {
-    use blocky_derive::Packet;
-
-    // clientbound
-
-    #[derive(Packet)]
-    pub struct StatusResponse {
-        pub status: String,
-    }
-
-    #[derive(Packet)]
-    pub struct PongResponse {
-        pub time: u64,
-    }
-
-    // serverbound
-
-    #[derive(Packet)]
-    pub struct StatusRequest;
-
-    #[derive(Packet)]
-    pub struct PingRequest {
-        pub time: u64,
-    }
-}
-
-pub mod login {
-    use blocky_derive::{Decoder, Encoder, Packet};
-    use blocky_net::types::{LengthInferredVecU8, LengthPrefixedVec, LengthPrefixedVecU8, VarInt};
-    use blocky_world::resources::ResourceLocation;
-    use uuid::Uuid;
-
-    // clientbound
-
-    #[derive(Packet)]
-    pub struct Disconnect {
-        pub reason: String, // TODO: This should be a JSON Text Component (need to add)
+use blocky_net::version::ProtocolVersion;
+
+pub mod configuration;
+pub mod handshake;
+pub mod login;
+pub mod play;
+pub mod status;
+pub mod text;
+pub mod types;
+
+/// The newest protocol version of the packets in this crate (1.21), see
+/// [`ProtocolVersion::SUPPORTED`] for the others.
+pub const PROTOCOL_VERSION: i32 = ProtocolVersion::LATEST.0;
+
+#[cfg(test)]
+mod tests {
+    use blocky_net::{
+        decoder::Decoder,
+        encoder::Encoder,
+        frame::Frame,
+        packet::{ConnectionState, Packet},
+        registry::PacketGroup,
+        version::ProtocolVersion,
+    };
+
+    use crate::{
+        handshake::{Handshake, Intent},
+        login::{ClientboundLoginPacket, LoginStart, SetCompression},
+        text::NamedColor,
+    };
+
+    #[test]
+    fn test_handshake_intent() {
+        let handshake = Handshake {
+            protocol_version: crate::PROTOCOL_VERSION,
+            server_address: "localhost".to_string(),
+            server_port: 25565,
+            next_state: Intent::Transfer,
+        };
+
+        let frame = Frame::from_packet(&handshake, ProtocolVersion::LATEST).unwrap();
+        assert_eq!(frame.body.last(), Some(&0x03));
+        assert_eq!(handshake.next_state(), Some(ConnectionState::Login));
+
+        let decoded = frame.decode::<Handshake>(ProtocolVersion::LATEST).unwrap();
+        assert_eq!(decoded.next_state, Intent::Transfer);
     }
 
-    #[derive(Packet)]
-    pub struct EncryptionRequest {
-        pub server_id: String,
-        pub public_key: LengthPrefixedVecU8<VarInt>,
-        pub verify_token: LengthPrefixedVecU8<VarInt>,
-        pub should_authenticate: bool,
+    #[test]
+    fn test_login_dispatch() {
+        let frame = Frame::from_packet(&SetCompression { threshold: 256 }, ProtocolVersion::LATEST)
+            .unwrap();
+        assert_eq!(frame.id, 0x03);
+
+        match ClientboundLoginPacket::decode_frame(&frame).unwrap() {
+            ClientboundLoginPacket::SetCompression(packet) => {
+                assert_eq!(packet.compression_threshold(), Some(256))
+            }
+            _ => panic!("decoded the wrong packet"),
+        }
     }
 
-    #[derive(Encoder, Decoder)]
-    pub struct LoginSuccessProperty {
-        pub name: String,
-        pub value: String,
-        pub signature: Option<String>,
-    }
-
-    #[derive(Packet)]
-    pub struct LoginSuccess {
-        pub uuid: Uuid,
-        pub username: String,
-        pub properties: LengthPrefixedVec<VarInt, LoginSuccessProperty>,
-        pub strict_error_handling: bool,
-    }
+    #[test]
+    fn test_login_disconnect_json() {
+        let mut body = Vec::new();
+        r#"{"text":"Banned","color":"red"}"#.encode(&mut body).unwrap();
 
-    #[derive(Packet)]
-    pub struct SetCompression {
-        pub threshold: VarInt,
+        let disconnect = Frame::new(0x00, body)
+            .decode::<crate::login::Disconnect>(ProtocolVersion::LATEST)
+            .unwrap();
+        assert_eq!(disconnect.reason.to_plain(), "Banned");
+        assert_eq!(disconnect.reason.style.color, Some(NamedColor::Red.into()));
     }
 
-    #[derive(Packet)]
-    pub struct LoginPluginRequest {
-        pub message_id: VarInt,
-        pub channel: ResourceLocation,
-        pub data: LengthInferredVecU8,
+    #[test]
+    fn test_decode_error_context() {
+        // a uuid followed by a username claiming 5 bytes but only holding 2
+        let mut body = vec![0; 16];
+        body.extend_from_slice(&[0x05, b'a', b'b']);
+
+        let err = Frame::new(0x02, body)
+            .decode::<crate::login::LoginSuccess>(ProtocolVersion::LATEST)
+            .err()
+            .unwrap();
+
+        assert!(err.is_unexpected_eof());
+        assert_eq!(
+            err.to_string(),
+            "LoginSuccess.username at byte 16: unexpected end of input"
+        );
     }
 
-    #[derive(Packet)]
-    pub struct CookieRequest {
-        pub key: ResourceLocation,
+    #[test]
+    fn test_decode_error_first_field() {
+        let err = LoginStart::from_bytes(&[0x05, b'a', b'b']).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "LoginStart.name at byte 0: unexpected end of input"
+        );
     }
 
-    // serverbound
-
-    #[derive(Packet)]
-    pub struct LoginStart {
-        pub name: String,
-        pub uuid: Uuid,
-    }
-
-    #[derive(Packet)]
-    pub struct EncryptionResponse {
-        pub shared_secret: LengthPrefixedVec<VarInt, u8>,
-        pub verify_token: LengthPrefixedVec<VarInt, u8>,
-    }
-
-    #[derive(Packet)]
-    pub struct LoginPluginResponse {
-        pub message_id: VarInt,
-        pub success: bool,
-        pub data: LengthInferredVecU8,
-    }
-
-    #[derive(Packet)]
-    pub struct LoginAcknowledged;
-
-    #[derive(Packet)]
-    pub struct CookieResponse {
-        pub key: ResourceLocation,
-        pub payload: Option<LengthPrefixedVecU8<VarInt>>,
+    #[test]
+    fn test_decode_error_nested_path() {
+        let mut body = vec![0; 16];
+        body.extend_from_slice(&[0x02, b'a', b'b']);
+        // two properties, the second with a truncated signature
+        body.extend_from_slice(&[0x02]);
+        body.extend_from_slice(&[0x01, b'n', 0x01, b'v', 0x00]);
+        body.extend_from_slice(&[0x01, b'n', 0x01, b'v', 0x01, 0x05, b's']);
+
+        let err = Frame::new(0x02, body)
+            .decode::<crate::login::LoginSuccess>(ProtocolVersion::LATEST)
+            .err()
+            .unwrap();
+
+        assert_eq!(
+            err.to_string(),
+            "LoginSuccess.properties[1].signature at byte 29: unexpected end of input"
+        );
     }
 }
@@ -0,0 +1,165 @@
+use blocky_derive::{Decoder, Encoder, Packet};
+use blocky_net::{
+    encryption::{self, SharedSecret},
+    error::Result,
+    packet_group,
+    types::VarInt,
+};
+use blocky_world::resources::ResourceLocation;
+use uuid::Uuid;
+
+use crate::text::TextComponent;
+
+packet_group! {
+    pub enum ClientboundLoginPacket(Login, Clientbound) {
+        Disconnect,
+        EncryptionRequest,
+        LoginSuccess,
+        SetCompression,
+        LoginPluginRequest,
+        CookieRequest,
+    }
+}
+
+packet_group! {
+    pub enum ServerboundLoginPacket(Login, Serverbound) {
+        LoginStart,
+        EncryptionResponse,
+        LoginPluginResponse,
+        LoginAcknowledged,
+        CookieResponse,
+    }
+}
+
+// clientbound
+
+#[derive(Packet)]
+#[packet(id = 0x00, state = Login, flow = Clientbound)]
+pub struct Disconnect {
+    #[json]
+    pub reason: TextComponent,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x01, state = Login, flow = Clientbound)]
+pub struct EncryptionRequest {
+    #[max_len = 20]
+    pub server_id: String,
+    #[prefixed(VarInt)]
+    pub public_key: Vec<u8>,
+    #[prefixed(VarInt)]
+    pub verify_token: Vec<u8>,
+    /// Whether the client should authenticate with Mojang before answering,
+    /// which servers before 1.20.5 always expect.
+    #[versions(V1_20_6..)]
+    pub should_authenticate: Option<bool>,
+}
+
+impl EncryptionRequest {
+    /// Builds the response to this request by encrypting the shared secret
+    /// and the verify token with the server's public key.
+    pub fn respond(&self, shared_secret: &SharedSecret) -> Result<EncryptionResponse> {
+        let public_key = &self.public_key;
+        let shared_secret = encryption::encrypt_with_public_key(public_key, shared_secret)?;
+        let verify_token = encryption::encrypt_with_public_key(public_key, &self.verify_token)?;
+
+        Ok(EncryptionResponse {
+            shared_secret,
+            verify_token,
+        })
+    }
+}
+
+#[derive(Encoder, Decoder, Debug, Clone, PartialEq, Eq)]
+pub struct LoginSuccessProperty {
+    pub name: String,
+    pub value: String,
+    pub signature: Option<String>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x02, state = Login, flow = Clientbound)]
+pub struct LoginSuccess {
+    pub uuid: Uuid,
+    #[max_len = 16]
+    pub username: String,
+    #[prefixed(VarInt)]
+    pub properties: Vec<LoginSuccessProperty>,
+    #[versions(V1_20_6..)]
+    pub strict_error_handling: Option<bool>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x03, state = Login, flow = Clientbound)]
+pub struct SetCompression {
+    #[varint]
+    pub threshold: i32,
+}
+
+impl SetCompression {
+    /// The threshold to hand to the frame codec, a negative value means
+    /// compression is disabled.
+    pub fn compression_threshold(&self) -> Option<usize> {
+        usize::try_from(self.threshold).ok()
+    }
+}
+
+#[derive(Packet)]
+#[packet(id = 0x04, state = Login, flow = Clientbound)]
+pub struct LoginPluginRequest {
+    #[varint]
+    pub message_id: i32,
+    pub channel: ResourceLocation,
+    #[rest]
+    pub data: Vec<u8>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x05, state = Login, flow = Clientbound)]
+#[versions(V1_20_6..)]
+pub struct CookieRequest {
+    pub key: ResourceLocation,
+}
+
+// serverbound
+
+#[derive(Packet)]
+#[packet(id = 0x00, state = Login, flow = Serverbound)]
+pub struct LoginStart {
+    #[max_len = 16]
+    pub name: String,
+    pub uuid: Uuid,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x01, state = Login, flow = Serverbound)]
+pub struct EncryptionResponse {
+    #[prefixed(VarInt)]
+    pub shared_secret: Vec<u8>,
+    #[prefixed(VarInt)]
+    pub verify_token: Vec<u8>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x02, state = Login, flow = Serverbound)]
+pub struct LoginPluginResponse {
+    #[varint]
+    pub message_id: i32,
+    pub success: bool,
+    #[present_if = "self.success"]
+    #[rest]
+    pub data: Option<Vec<u8>>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x03, state = Login, flow = Serverbound, next_state = Configuration)]
+pub struct LoginAcknowledged;
+
+#[derive(Packet)]
+#[packet(id = 0x04, state = Login, flow = Serverbound)]
+#[versions(V1_20_6..)]
+pub struct CookieResponse {
+    pub key: ResourceLocation,
+    #[prefixed(VarInt)]
+    pub payload: Option<Vec<u8>>,
+}
@@ -0,0 +1,2005 @@
+//! The packets of the play state, which a client stays in from joining the
+//! world until it disconnects.
+//!
+//! Item slots and entity metadata are kept as the bytes they were sent as,
+//! since reading them needs the format of every item component and metadata
+//! type. The packets holding them read their other fields as usual and leave
+//! those bytes for last. The command tree is kept the same way. Packets that
+//! have no type here decode as [`ClientboundPlayPacket::Unknown`], so they
+//! can be skipped.
+//!
+//! The ids are those of 1.20.5 onwards, so there are no play packet tables
+//! for 1.20.4.
+
+use std::io::{Read, Write};
+
+use blocky_derive::{Decoder, Encoder, Packet};
+use blocky_nbt::{NbtCompound, OptionalNbt};
+use blocky_net::{
+    codec::decode_length,
+    decoder::Decoder,
+    encoder::Encoder,
+    error::{ProtocolError, Result},
+    packet::{ConnectionState, Packet, PacketFlow},
+    packet_group,
+    types::{Angle, BitSet, LengthPrefix, VarInt},
+};
+use blocky_world::{position::BlockPosition, resources::ResourceLocation};
+use uuid::Uuid;
+
+use crate::{
+    login::LoginSuccessProperty,
+    text::{Style, TextComponent},
+    types::{ClientInformation as Information, IdOr, RegistryTags, ResourcePackResult},
+};
+
+packet_group! {
+    pub enum ClientboundPlayPacket(Play, Clientbound, since = V1_20_6, unknown = Unknown) {
+        BundleDelimiter,
+        SpawnEntity,
+        SpawnExperienceOrb,
+        EntityAnimation,
+        AcknowledgeBlockChange,
+        SetBlockDestroyStage,
+        BlockEntityData,
+        BlockAction,
+        BlockUpdate,
+        BossBar,
+        ChangeDifficulty,
+        ChunkBatchFinished,
+        ChunkBatchStart,
+        Commands,
+        ClientboundCloseContainer,
+        SetContainerContent,
+        SetContainerProperty,
+        SetContainerSlot,
+        CookieRequest,
+        SetCooldown,
+        ClientboundPluginMessage,
+        Disconnect,
+        DisguisedChatMessage,
+        EntityEvent,
+        UnloadChunk,
+        GameEvent,
+        HurtAnimation,
+        ClientboundKeepAlive,
+        ChunkDataAndUpdateLight,
+        UpdateLight,
+        Login,
+        UpdateEntityPosition,
+        UpdateEntityPositionAndRotation,
+        UpdateEntityRotation,
+        OpenScreen,
+        Ping,
+        PingResponse,
+        ClientboundPlayerAbilities,
+        PlayerChatMessage,
+        CombatDeath,
+        PlayerInfoRemove,
+        PlayerInfoUpdate,
+        SynchronizePlayerPosition,
+        RemoveEntities,
+        RemoveResourcePack,
+        AddResourcePack,
+        Respawn,
+        SetHeadRotation,
+        UpdateSectionBlocks,
+        ServerData,
+        SetActionBarText,
+        ClientboundSetHeldItem,
+        SetCenterChunk,
+        SetRenderDistance,
+        SetDefaultSpawnPosition,
+        SetEntityMetadata,
+        SetEntityVelocity,
+        SetEquipment,
+        SetExperience,
+        SetHealth,
+        SetSimulationDistance,
+        UpdateTime,
+        StartConfiguration,
+        StoreCookie,
+        SystemChatMessage,
+        PickupItem,
+        TeleportEntity,
+        Transfer,
+        UpdateAttributes,
+        EntityEffect,
+        UpdateRecipes,
+        UpdateTags,
+    }
+}
+
+packet_group! {
+    pub enum ServerboundPlayPacket(Play, Serverbound, since = V1_20_6) {
+        ConfirmTeleportation,
+        AcknowledgeMessage,
+        ChatCommand,
+        ChatMessage,
+        ChunkBatchReceived,
+        ClientStatus,
+        ClientInformation,
+        AcknowledgeConfiguration,
+        ClickContainerButton,
+        ClickContainer,
+        ServerboundCloseContainer,
+        CookieResponse,
+        ServerboundPluginMessage,
+        Interact,
+        ServerboundKeepAlive,
+        SetPlayerPosition,
+        SetPlayerPositionAndRotation,
+        SetPlayerRotation,
+        SetPlayerOnGround,
+        PingRequest,
+        PlayerAction,
+        PlayerCommand,
+        Pong,
+        ResourcePackResponse,
+        ServerboundSetHeldItem,
+        SetCreativeModeSlot,
+        SwingArm,
+        UseItemOn,
+        UseItem,
+    }
+}
+
+// clientbound
+
+/// Marks the start and end of packets the client handles all in one tick.
+#[derive(Packet)]
+#[packet(id = 0x00, state = Play, flow = Clientbound)]
+pub struct BundleDelimiter;
+
+#[derive(Packet)]
+#[packet(id = 0x01, state = Play, flow = Clientbound)]
+pub struct SpawnEntity {
+    #[varint]
+    pub entity_id: i32,
+    pub uuid: Uuid,
+    #[varint]
+    pub entity_type: i32,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub pitch: Angle,
+    pub yaw: Angle,
+    pub head_yaw: Angle,
+    /// Depends on the type of entity, such as the block state of a falling
+    /// block.
+    #[varint]
+    pub data: i32,
+    /// The velocity in 1/8000 of a block per tick.
+    pub velocity_x: i16,
+    pub velocity_y: i16,
+    pub velocity_z: i16,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x02, state = Play, flow = Clientbound)]
+pub struct SpawnExperienceOrb {
+    #[varint]
+    pub entity_id: i32,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub count: i16,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x03, state = Play, flow = Clientbound)]
+pub struct EntityAnimation {
+    #[varint]
+    pub entity_id: i32,
+    pub animation: u8,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x05, state = Play, flow = Clientbound)]
+pub struct AcknowledgeBlockChange {
+    #[varint]
+    pub sequence: i32,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x06, state = Play, flow = Clientbound)]
+pub struct SetBlockDestroyStage {
+    #[varint]
+    pub entity_id: i32,
+    pub location: BlockPosition,
+    /// From 0 to 9, anything else removes the cracks.
+    pub stage: i8,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x07, state = Play, flow = Clientbound)]
+pub struct BlockEntityData {
+    pub location: BlockPosition,
+    #[varint]
+    pub kind: i32,
+    pub data: OptionalNbt,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x08, state = Play, flow = Clientbound)]
+pub struct BlockAction {
+    pub location: BlockPosition,
+    pub action_id: u8,
+    pub action_parameter: u8,
+    #[varint]
+    pub block_type: i32,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x09, state = Play, flow = Clientbound)]
+pub struct BlockUpdate {
+    pub location: BlockPosition,
+    #[varint]
+    pub block_state: i32,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x0A, state = Play, flow = Clientbound)]
+pub struct BossBar {
+    pub uuid: Uuid,
+    pub action: BossBarAction,
+}
+
+impl BossBar {
+    pub const DARKEN_SKY: u8 = 0x01;
+    /// Plays the music of the dragon fight.
+    pub const DRAGON_BAR: u8 = 0x02;
+    pub const CREATE_FOG: u8 = 0x04;
+}
+
+#[derive(Encoder, Decoder, Debug, Clone, PartialEq)]
+pub enum BossBarAction {
+    Add {
+        title: TextComponent,
+        /// From 0 to 1.
+        health: f32,
+        color: BossBarColor,
+        division: BossBarDivision,
+        /// The `BossBar` flags that are set.
+        flags: u8,
+    },
+    Remove,
+    UpdateHealth(f32),
+    UpdateTitle(TextComponent),
+    UpdateStyle {
+        color: BossBarColor,
+        division: BossBarDivision,
+    },
+    UpdateFlags(u8),
+}
+
+#[derive(Encoder, Decoder, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BossBarColor {
+    Pink,
+    Blue,
+    Red,
+    Green,
+    Yellow,
+    Purple,
+    White,
+}
+
+/// How many notches the bar is split into.
+#[derive(Encoder, Decoder, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BossBarDivision {
+    None,
+    Notches6,
+    Notches10,
+    Notches12,
+    Notches20,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x0B, state = Play, flow = Clientbound)]
+pub struct ChangeDifficulty {
+    pub difficulty: Difficulty,
+    pub locked: bool,
+}
+
+#[derive(Encoder, Decoder, Debug, Clone, Copy, PartialEq, Eq)]
+#[discriminant(u8)]
+pub enum Difficulty {
+    Peaceful,
+    Easy,
+    Normal,
+    Hard,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x0C, state = Play, flow = Clientbound)]
+pub struct ChunkBatchFinished {
+    /// How many chunks were sent since the matching [`ChunkBatchStart`].
+    #[varint]
+    pub batch_size: i32,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x0D, state = Play, flow = Clientbound)]
+pub struct ChunkBatchStart;
+
+/// The tree of commands the player can use, sent after joining and whenever
+/// it changes.
+#[derive(Packet)]
+#[packet(id = 0x11, state = Play, flow = Clientbound)]
+pub struct Commands {
+    /// The nodes of the tree followed by the index of the root node. Kept as
+    /// bytes, since the properties of an argument depend on its parser.
+    #[rest]
+    pub data: Vec<u8>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x12, state = Play, flow = Clientbound)]
+pub struct ClientboundCloseContainer {
+    pub window_id: u8,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x13, state = Play, flow = Clientbound)]
+pub struct SetContainerContent {
+    pub window_id: u8,
+    #[varint]
+    pub state_id: i32,
+    /// The slots of the container followed by the item held by the cursor.
+    #[rest]
+    pub slot_data: Vec<u8>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x14, state = Play, flow = Clientbound)]
+pub struct SetContainerProperty {
+    pub window_id: u8,
+    pub property: i16,
+    pub value: i16,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x15, state = Play, flow = Clientbound)]
+pub struct SetContainerSlot {
+    pub window_id: i8,
+    #[varint]
+    pub state_id: i32,
+    pub slot: i16,
+    #[rest]
+    pub slot_data: Vec<u8>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x16, state = Play, flow = Clientbound)]
+pub struct CookieRequest {
+    pub key: ResourceLocation,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x17, state = Play, flow = Clientbound)]
+pub struct SetCooldown {
+    #[varint]
+    pub item_id: i32,
+    #[varint]
+    pub ticks: i32,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x19, state = Play, flow = Clientbound)]
+pub struct ClientboundPluginMessage {
+    pub channel: ResourceLocation,
+    #[rest]
+    pub data: Vec<u8>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x1D, state = Play, flow = Clientbound)]
+pub struct Disconnect {
+    pub reason: TextComponent,
+}
+
+/// A chat message sent without a signature, such as from `/say`.
+#[derive(Packet)]
+#[packet(id = 0x1E, state = Play, flow = Clientbound)]
+pub struct DisguisedChatMessage {
+    pub message: TextComponent,
+    pub chat_type: ChatTypeBound,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x1F, state = Play, flow = Clientbound)]
+pub struct EntityEvent {
+    pub entity_id: i32,
+    pub status: i8,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x21, state = Play, flow = Clientbound)]
+pub struct UnloadChunk {
+    // the z coordinate really does come first
+    pub chunk_z: i32,
+    pub chunk_x: i32,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x22, state = Play, flow = Clientbound)]
+pub struct GameEvent {
+    pub event: u8,
+    pub value: f32,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x24, state = Play, flow = Clientbound)]
+pub struct HurtAnimation {
+    #[varint]
+    pub entity_id: i32,
+    pub yaw: f32,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x26, state = Play, flow = Clientbound)]
+pub struct ClientboundKeepAlive {
+    pub id: i64,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x27, state = Play, flow = Clientbound)]
+pub struct ChunkDataAndUpdateLight {
+    pub chunk_x: i32,
+    pub chunk_z: i32,
+    pub data: ChunkData,
+    pub light: LightData,
+}
+
+#[derive(Encoder, Decoder, Debug, Clone, PartialEq)]
+pub struct ChunkData {
+    pub heightmaps: NbtCompound,
+    /// The chunk sections from the bottom of the world up.
+    #[prefixed(VarInt)]
+    pub data: Vec<u8>,
+    #[prefixed(VarInt)]
+    pub block_entities: Vec<ChunkBlockEntity>,
+}
+
+#[derive(Encoder, Decoder, Debug, Clone, PartialEq)]
+pub struct ChunkBlockEntity {
+    /// The x and z of the block within the chunk, in the high and low nibble.
+    pub packed_xz: u8,
+    pub y: i16,
+    #[varint]
+    pub kind: i32,
+    /// Left out for block entities with nothing for the client to show.
+    pub data: OptionalNbt,
+}
+
+/// The light of the sections of a chunk column, with one bit per section in
+/// each mask starting from the one below the world.
+#[derive(Encoder, Decoder, Debug, Clone, PartialEq, Eq, Default)]
+pub struct LightData {
+    pub sky_light_mask: BitSet,
+    pub block_light_mask: BitSet,
+    pub empty_sky_light_mask: BitSet,
+    pub empty_block_light_mask: BitSet,
+    /// One array for each bit set in the sky light mask, in order.
+    #[prefixed(VarInt)]
+    pub sky_light: Vec<LightArray>,
+    #[prefixed(VarInt)]
+    pub block_light: Vec<LightArray>,
+}
+
+/// The light levels of a section, half a byte per block.
+#[derive(Encoder, Decoder, Debug, Clone, PartialEq, Eq)]
+#[transparent]
+pub struct LightArray(#[prefixed(VarInt)] pub Vec<u8>);
+
+#[derive(Packet)]
+#[packet(id = 0x2A, state = Play, flow = Clientbound)]
+pub struct UpdateLight {
+    #[varint]
+    pub chunk_x: i32,
+    #[varint]
+    pub chunk_z: i32,
+    pub light: LightData,
+}
+
+/// Sent once configuration is finished to put the player in the world.
+#[derive(Packet)]
+#[packet(id = 0x2B, state = Play, flow = Clientbound)]
+pub struct Login {
+    pub entity_id: i32,
+    pub is_hardcore: bool,
+    #[prefixed(VarInt)]
+    pub dimension_names: Vec<ResourceLocation>,
+    /// Ignored by the client.
+    #[varint]
+    pub max_players: i32,
+    #[varint]
+    pub view_distance: i32,
+    #[varint]
+    pub simulation_distance: i32,
+    pub reduced_debug_info: bool,
+    pub enable_respawn_screen: bool,
+    pub do_limited_crafting: bool,
+    pub spawn_info: SpawnInfo,
+    pub enforces_secure_chat: bool,
+}
+
+/// The world a player is spawned in, sent when joining and respawning.
+#[derive(Encoder, Decoder, Debug, Clone, PartialEq, Eq)]
+pub struct SpawnInfo {
+    #[varint]
+    pub dimension_type: i32,
+    pub dimension_name: ResourceLocation,
+    /// The first 8 bytes of the SHA-256 hash of the world's seed.
+    pub hashed_seed: i64,
+    pub game_mode: GameMode,
+    /// `-1` if there is no previous game mode.
+    pub previous_game_mode: i8,
+    pub is_debug: bool,
+    pub is_flat: bool,
+    pub death_location: Option<GlobalPosition>,
+    #[varint]
+    pub portal_cooldown: i32,
+}
+
+#[derive(Encoder, Decoder, Debug, Clone, Copy, PartialEq, Eq)]
+#[discriminant(u8)]
+pub enum GameMode {
+    Survival,
+    Creative,
+    Adventure,
+    Spectator,
+}
+
+/// A block position together with the dimension it is in.
+#[derive(Encoder, Decoder, Debug, Clone, PartialEq, Eq)]
+pub struct GlobalPosition {
+    pub dimension: ResourceLocation,
+    pub location: BlockPosition,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x2E, state = Play, flow = Clientbound)]
+pub struct UpdateEntityPosition {
+    #[varint]
+    pub entity_id: i32,
+    /// The change in position in 1/4096 of a block.
+    pub delta_x: i16,
+    pub delta_y: i16,
+    pub delta_z: i16,
+    pub on_ground: bool,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x2F, state = Play, flow = Clientbound)]
+pub struct UpdateEntityPositionAndRotation {
+    #[varint]
+    pub entity_id: i32,
+    pub delta_x: i16,
+    pub delta_y: i16,
+    pub delta_z: i16,
+    pub yaw: Angle,
+    pub pitch: Angle,
+    pub on_ground: bool,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x30, state = Play, flow = Clientbound)]
+pub struct UpdateEntityRotation {
+    #[varint]
+    pub entity_id: i32,
+    pub yaw: Angle,
+    pub pitch: Angle,
+    pub on_ground: bool,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x33, state = Play, flow = Clientbound)]
+pub struct OpenScreen {
+    #[varint]
+    pub window_id: i32,
+    #[varint]
+    pub window_type: i32,
+    pub title: TextComponent,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x35, state = Play, flow = Clientbound)]
+pub struct Ping {
+    pub id: i32,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x36, state = Play, flow = Clientbound)]
+pub struct PingResponse {
+    pub payload: i64,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x38, state = Play, flow = Clientbound)]
+pub struct ClientboundPlayerAbilities {
+    /// Invulnerable, flying, allow flying and instant break, from the lowest
+    /// bit up.
+    pub flags: u8,
+    pub flying_speed: f32,
+    pub field_of_view_modifier: f32,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x39, state = Play, flow = Clientbound)]
+pub struct PlayerChatMessage {
+    pub sender: Uuid,
+    #[varint]
+    pub index: i32,
+    pub signature: Option<[u8; 256]>,
+    #[max_len = 256]
+    pub message: String,
+    pub timestamp: i64,
+    pub salt: i64,
+    #[prefixed(VarInt)]
+    pub previous_messages: Vec<PreviousMessage>,
+    pub unsigned_content: Option<TextComponent>,
+    pub filter: FilterMask,
+    pub chat_type: ChatTypeBound,
+}
+
+/// A message seen before the one it is sent with, that its signature covers.
+#[derive(Encoder, Decoder, Debug, Clone, PartialEq, Eq)]
+pub struct PreviousMessage {
+    /// The id of the message's signature plus one, or `0` if the signature
+    /// is sent in full.
+    #[varint]
+    pub id: i32,
+    #[present_if = "self.id == 0"]
+    pub signature: Option<[u8; 256]>,
+}
+
+/// Which parts of a chat message were hidden by the server's filter.
+#[derive(Encoder, Decoder, Debug, Clone, PartialEq, Eq)]
+pub enum FilterMask {
+    PassThrough,
+    FullyFiltered,
+    /// One bit per character that was filtered.
+    PartiallyFiltered(BitSet),
+}
+
+/// The chat type a message is decorated with, and the names it is decorated
+/// with.
+#[derive(Encoder, Decoder, Debug, Clone, PartialEq, Eq)]
+pub struct ChatTypeBound {
+    pub chat_type: IdOr<ChatType>,
+    pub sender_name: TextComponent,
+    pub target_name: Option<TextComponent>,
+}
+
+#[derive(Encoder, Decoder, Debug, Clone, PartialEq, Eq)]
+pub struct ChatType {
+    pub chat: ChatDecoration,
+    pub narration: ChatDecoration,
+}
+
+#[derive(Encoder, Decoder, Debug, Clone, PartialEq, Eq)]
+pub struct ChatDecoration {
+    pub translation_key: String,
+    /// What is passed to the translation, in order.
+    #[prefixed(VarInt)]
+    pub parameters: Vec<ChatParameter>,
+    pub style: Style,
+}
+
+#[derive(Encoder, Decoder, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatParameter {
+    Sender,
+    Target,
+    Content,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x3C, state = Play, flow = Clientbound)]
+pub struct CombatDeath {
+    #[varint]
+    pub player_id: i32,
+    pub message: TextComponent,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x3D, state = Play, flow = Clientbound)]
+pub struct PlayerInfoRemove {
+    #[prefixed(VarInt)]
+    pub players: Vec<Uuid>,
+}
+
+/// Adds players to the tab list or changes what it shows about them. Every
+/// entry carries the data of each action set in `actions`.
+pub struct PlayerInfoUpdate {
+    pub actions: u8,
+    pub players: Vec<PlayerInfoEntry>,
+}
+
+impl PlayerInfoUpdate {
+    pub const ADD_PLAYER: u8 = 0x01;
+    pub const INITIALIZE_CHAT: u8 = 0x02;
+    pub const UPDATE_GAME_MODE: u8 = 0x04;
+    pub const UPDATE_LISTED: u8 = 0x08;
+    pub const UPDATE_LATENCY: u8 = 0x10;
+    pub const UPDATE_DISPLAY_NAME: u8 = 0x20;
+}
+
+impl Packet for PlayerInfoUpdate {
+    const ID: i32 = 0x3E;
+    const STATE: ConnectionState = ConnectionState::Play;
+    const FLOW: PacketFlow = PacketFlow::Clientbound;
+}
+
+// what an entry holds depends on the actions of the packet, which the
+// derives can't express, so the codec is written by hand
+
+impl Encoder for PlayerInfoUpdate {
+    fn byte_len(&self) -> usize {
+        let players = self
+            .players
+            .iter()
+            .map(|player| player.byte_len(self.actions));
+        1 + VarInt::from_len(self.players.len()).byte_len() + players.sum::<usize>()
+    }
+
+    fn encode<W: Write>(&self, buf: &mut W) -> Result<()> {
+        self.actions.encode(buf)?;
+        VarInt::from_len(self.players.len()).encode(buf)?;
+
+        for (i, player) in self.players.iter().enumerate() {
+            player
+                .encode(self.actions, buf)
+                .map_err(|e| e.at_index(i).in_field("PlayerInfoUpdate", "players"))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Decoder for PlayerInfoUpdate {
+    fn decode<R: Read>(buf: &mut R) -> Result<Self> {
+        let actions = u8::decode(buf)?;
+        let len = decode_length::<VarInt, _>(buf)?;
+
+        let mut players = Vec::with_capacity(len);
+        for i in 0..len {
+            let player = PlayerInfoEntry::decode(actions, buf)
+                .map_err(|e| e.at_index(i).in_field("PlayerInfoUpdate", "players"))?;
+            players.push(player);
+        }
+
+        Ok(Self { actions, players })
+    }
+}
+
+/// A player in a [`PlayerInfoUpdate`]. The fields of the actions the packet
+/// doesn't carry are `None`, and the fields of those it does have to be set,
+/// apart from the chat session and display name, which may be absent.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PlayerInfoEntry {
+    pub uuid: Uuid,
+    pub profile: Option<PlayerProfile>,
+    pub chat_session: Option<ChatSession>,
+    /// The id of a [`GameMode`].
+    pub game_mode: Option<i32>,
+    /// Whether the player is shown in the tab list.
+    pub listed: Option<bool>,
+    /// The ping of the player in milliseconds.
+    pub latency: Option<i32>,
+    pub display_name: Option<TextComponent>,
+}
+
+impl PlayerInfoEntry {
+    fn byte_len(&self, actions: u8) -> usize {
+        let has = |action| actions & action != 0;
+
+        let mut len = self.uuid.byte_len();
+        if has(PlayerInfoUpdate::ADD_PLAYER) {
+            len += self.profile.as_ref().map_or(0, PlayerProfile::byte_len);
+        }
+        if has(PlayerInfoUpdate::INITIALIZE_CHAT) {
+            len += self.chat_session.byte_len();
+        }
+        if has(PlayerInfoUpdate::UPDATE_GAME_MODE) {
+            len += self.game_mode.map_or(0, |mode| VarInt(mode).byte_len());
+        }
+        if has(PlayerInfoUpdate::UPDATE_LISTED) {
+            len += 1;
+        }
+        if has(PlayerInfoUpdate::UPDATE_LATENCY) {
+            len += self.latency.map_or(0, |latency| VarInt(latency).byte_len());
+        }
+        if has(PlayerInfoUpdate::UPDATE_DISPLAY_NAME) {
+            len += self.display_name.byte_len();
+        }
+        len
+    }
+
+    fn encode<W: Write>(&self, actions: u8, buf: &mut W) -> Result<()> {
+        let has = |action| actions & action != 0;
+
+        // the actions the packet carries have to be there, the others must not
+        fn expect<T>(value: Option<T>, present: bool, field: &'static str) -> Result<Option<T>> {
+            match (value, present) {
+                (Some(value), true) => Ok(Some(value)),
+                (None, false) => Ok(None),
+                _ => Err(ProtocolError::PresenceMismatch.in_field("PlayerInfoEntry", field)),
+            }
+        }
+
+        let profile = expect(
+            self.profile.as_ref(),
+            has(PlayerInfoUpdate::ADD_PLAYER),
+            "profile",
+        )?;
+        let game_mode = expect(
+            self.game_mode,
+            has(PlayerInfoUpdate::UPDATE_GAME_MODE),
+            "game_mode",
+        )?;
+        let listed = expect(self.listed, has(PlayerInfoUpdate::UPDATE_LISTED), "listed")?;
+        let latency = expect(
+            self.latency,
+            has(PlayerInfoUpdate::UPDATE_LATENCY),
+            "latency",
+        )?;
+        if !has(PlayerInfoUpdate::INITIALIZE_CHAT) && self.chat_session.is_some() {
+            return Err(ProtocolError::PresenceMismatch.in_field("PlayerInfoEntry", "chat_session"));
+        }
+        if !has(PlayerInfoUpdate::UPDATE_DISPLAY_NAME) && self.display_name.is_some() {
+            return Err(ProtocolError::PresenceMismatch.in_field("PlayerInfoEntry", "display_name"));
+        }
+
+        self.uuid.encode(buf)?;
+        if let Some(profile) = profile {
+            profile.encode(buf)?;
+        }
+        if has(PlayerInfoUpdate::INITIALIZE_CHAT) {
+            self.chat_session.encode(buf)?;
+        }
+        if let Some(game_mode) = game_mode {
+            VarInt(game_mode).encode(buf)?;
+        }
+        if let Some(listed) = listed {
+            listed.encode(buf)?;
+        }
+        if let Some(latency) = latency {
+            VarInt(latency).encode(buf)?;
+        }
+        if has(PlayerInfoUpdate::UPDATE_DISPLAY_NAME) {
+            self.display_name.encode(buf)?;
+        }
+
+        Ok(())
+    }
+
+    fn decode<R: Read>(actions: u8, buf: &mut R) -> Result<Self> {
+        let has = |action| actions & action != 0;
+
+        let mut entry = Self {
+            uuid: Uuid::decode(buf)?,
+            ..Self::default()
+        };
+        if has(PlayerInfoUpdate::ADD_PLAYER) {
+            entry.profile = Some(PlayerProfile::decode(buf)?);
+        }
+        if has(PlayerInfoUpdate::INITIALIZE_CHAT) {
+            entry.chat_session = Option::decode(buf)?;
+        }
+        if has(PlayerInfoUpdate::UPDATE_GAME_MODE) {
+            entry.game_mode = Some(VarInt::decode(buf)?.0);
+        }
+        if has(PlayerInfoUpdate::UPDATE_LISTED) {
+            entry.listed = Some(bool::decode(buf)?);
+        }
+        if has(PlayerInfoUpdate::UPDATE_LATENCY) {
+            entry.latency = Some(VarInt::decode(buf)?.0);
+        }
+        if has(PlayerInfoUpdate::UPDATE_DISPLAY_NAME) {
+            entry.display_name = Option::decode(buf)?;
+        }
+
+        Ok(entry)
+    }
+}
+
+/// The name and skin of a player added to the tab list.
+#[derive(Encoder, Decoder, Debug, Clone, PartialEq, Eq)]
+pub struct PlayerProfile {
+    #[max_len = 16]
+    pub name: String,
+    #[prefixed(VarInt)]
+    pub properties: Vec<LoginSuccessProperty>,
+}
+
+/// The key a player signs their chat messages with.
+#[derive(Encoder, Decoder, Debug, Clone, PartialEq, Eq)]
+pub struct ChatSession {
+    pub session_id: Uuid,
+    /// When the key expires, in milliseconds since the Unix epoch.
+    pub expires_at: i64,
+    #[prefixed(VarInt)]
+    pub public_key: Vec<u8>,
+    #[prefixed(VarInt)]
+    pub key_signature: Vec<u8>,
+}
+
+/// Moves the player, which must be confirmed with a [`ConfirmTeleportation`].
+#[derive(Packet)]
+#[packet(id = 0x40, state = Play, flow = Clientbound)]
+pub struct SynchronizePlayerPosition {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub yaw: f32,
+    pub pitch: f32,
+    /// Which of the fields are relative to the current position rather than
+    /// absolute, one bit each for x, y, z, yaw and pitch from the lowest bit.
+    pub flags: u8,
+    #[varint]
+    pub teleport_id: i32,
+}
+
+impl SynchronizePlayerPosition {
+    pub const RELATIVE_X: u8 = 0x01;
+    pub const RELATIVE_Y: u8 = 0x02;
+    pub const RELATIVE_Z: u8 = 0x04;
+    pub const RELATIVE_YAW: u8 = 0x08;
+    pub const RELATIVE_PITCH: u8 = 0x10;
+
+    /// The response the server expects once the player has been moved.
+    pub fn confirm(&self) -> ConfirmTeleportation {
+        ConfirmTeleportation {
+            teleport_id: self.teleport_id,
+        }
+    }
+}
+
+#[derive(Packet)]
+#[packet(id = 0x42, state = Play, flow = Clientbound)]
+pub struct RemoveEntities {
+    #[prefixed(VarInt)]
+    #[varint]
+    pub entity_ids: Vec<i32>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x45, state = Play, flow = Clientbound)]
+pub struct RemoveResourcePack {
+    /// The pack to remove, or every pack if `None`.
+    pub uuid: Option<Uuid>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x46, state = Play, flow = Clientbound)]
+pub struct AddResourcePack {
+    pub uuid: Uuid,
+    pub url: String,
+    #[max_len = 40]
+    pub hash: String,
+    pub forced: bool,
+    pub prompt_message: Option<TextComponent>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x47, state = Play, flow = Clientbound)]
+pub struct Respawn {
+    pub spawn_info: SpawnInfo,
+    /// Whether the attributes and metadata of the player are kept, in the
+    /// lowest two bits.
+    pub data_kept: u8,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x48, state = Play, flow = Clientbound)]
+pub struct SetHeadRotation {
+    #[varint]
+    pub entity_id: i32,
+    pub head_yaw: Angle,
+}
+
+/// Changes several blocks of one chunk section at once.
+#[derive(Packet)]
+#[packet(id = 0x49, state = Play, flow = Clientbound)]
+pub struct UpdateSectionBlocks {
+    /// The x, z and y of the section, in 22, 22 and 20 bits.
+    pub section: i64,
+    /// The block state of each block shifted up 12 bits, above its x, z and
+    /// y within the section.
+    #[prefixed(VarInt)]
+    #[varlong]
+    pub blocks: Vec<i64>,
+}
+
+impl UpdateSectionBlocks {
+    /// The x, y and z of the section, in sections rather than blocks.
+    pub fn section_position(&self) -> (i32, i32, i32) {
+        let x = (self.section >> 42) as i32;
+        let y = (self.section << 44 >> 44) as i32;
+        let z = (self.section << 22 >> 42) as i32;
+        (x, y, z)
+    }
+
+    /// Each changed block with its new block state.
+    pub fn blocks(&self) -> impl Iterator<Item = (BlockPosition, i32)> + '_ {
+        let (x, y, z) = self.section_position();
+
+        self.blocks.iter().map(move |&block| {
+            let position = BlockPosition {
+                x: x * 16 + (block >> 8 & 0xF) as i32,
+                y: y * 16 + (block & 0xF) as i32,
+                z: z * 16 + (block >> 4 & 0xF) as i32,
+            };
+            (position, (block >> 12) as i32)
+        })
+    }
+}
+
+#[derive(Packet)]
+#[packet(id = 0x4B, state = Play, flow = Clientbound)]
+pub struct ServerData {
+    pub motd: TextComponent,
+    /// The server icon as a PNG.
+    #[prefixed(VarInt)]
+    pub icon: Option<Vec<u8>>,
+    pub enforces_secure_chat: bool,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x4C, state = Play, flow = Clientbound)]
+pub struct SetActionBarText {
+    pub text: TextComponent,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x53, state = Play, flow = Clientbound)]
+pub struct ClientboundSetHeldItem {
+    pub slot: i8,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x54, state = Play, flow = Clientbound)]
+pub struct SetCenterChunk {
+    #[varint]
+    pub chunk_x: i32,
+    #[varint]
+    pub chunk_z: i32,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x55, state = Play, flow = Clientbound)]
+pub struct SetRenderDistance {
+    #[varint]
+    pub view_distance: i32,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x56, state = Play, flow = Clientbound)]
+pub struct SetDefaultSpawnPosition {
+    pub location: BlockPosition,
+    pub angle: f32,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x58, state = Play, flow = Clientbound)]
+pub struct SetEntityMetadata {
+    #[varint]
+    pub entity_id: i32,
+    #[rest]
+    pub metadata: Vec<u8>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x5A, state = Play, flow = Clientbound)]
+pub struct SetEntityVelocity {
+    #[varint]
+    pub entity_id: i32,
+    pub velocity_x: i16,
+    pub velocity_y: i16,
+    pub velocity_z: i16,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x5B, state = Play, flow = Clientbound)]
+pub struct SetEquipment {
+    #[varint]
+    pub entity_id: i32,
+    /// Each changed slot followed by its item, with the top bit of the slot
+    /// set on all but the last one.
+    #[rest]
+    pub equipment: Vec<u8>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x5C, state = Play, flow = Clientbound)]
+pub struct SetExperience {
+    /// How full the experience bar is, from 0 to 1.
+    pub experience_bar: f32,
+    #[varint]
+    pub level: i32,
+    #[varint]
+    pub total_experience: i32,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x5D, state = Play, flow = Clientbound)]
+pub struct SetHealth {
+    /// `0` or less means the player is dead.
+    pub health: f32,
+    #[varint]
+    pub food: i32,
+    pub food_saturation: f32,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x62, state = Play, flow = Clientbound)]
+pub struct SetSimulationDistance {
+    #[varint]
+    pub simulation_distance: i32,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x64, state = Play, flow = Clientbound)]
+pub struct UpdateTime {
+    pub world_age: i64,
+    /// Negative when the daylight cycle is stopped.
+    pub time_of_day: i64,
+}
+
+/// Sends the client back to the configuration state, once it answers with
+/// [`AcknowledgeConfiguration`].
+#[derive(Packet)]
+#[packet(id = 0x69, state = Play, flow = Clientbound)]
+pub struct StartConfiguration;
+
+#[derive(Packet)]
+#[packet(id = 0x6B, state = Play, flow = Clientbound)]
+pub struct StoreCookie {
+    pub key: ResourceLocation,
+    #[prefixed(VarInt)]
+    pub payload: Vec<u8>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x6C, state = Play, flow = Clientbound)]
+pub struct SystemChatMessage {
+    pub content: TextComponent,
+    /// Whether the message is shown above the hotbar rather than in chat.
+    pub overlay: bool,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x6F, state = Play, flow = Clientbound)]
+pub struct PickupItem {
+    #[varint]
+    pub collected_entity_id: i32,
+    #[varint]
+    pub collector_entity_id: i32,
+    #[varint]
+    pub count: i32,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x70, state = Play, flow = Clientbound)]
+pub struct TeleportEntity {
+    #[varint]
+    pub entity_id: i32,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub yaw: Angle,
+    pub pitch: Angle,
+    pub on_ground: bool,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x73, state = Play, flow = Clientbound)]
+pub struct Transfer {
+    pub host: String,
+    #[varint]
+    pub port: i32,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x75, state = Play, flow = Clientbound)]
+pub struct UpdateAttributes {
+    #[varint]
+    pub entity_id: i32,
+    #[prefixed(VarInt)]
+    pub attributes: Vec<Attribute>,
+}
+
+#[derive(Encoder, Decoder, Debug, Clone, PartialEq)]
+pub struct Attribute {
+    /// The id of the attribute in its registry.
+    #[varint]
+    pub id: i32,
+    /// The value before the modifiers are applied.
+    pub base: f64,
+    #[prefixed(VarInt)]
+    pub modifiers: Vec<AttributeModifier>,
+}
+
+/// A change to an attribute, such as from an item or an effect. Modifiers
+/// are told apart by a UUID before 1.21 and by a resource location after.
+#[derive(Encoder, Decoder, Debug, Clone, PartialEq)]
+pub struct AttributeModifier {
+    #[versions(..V1_21)]
+    pub uuid: Option<Uuid>,
+    #[versions(V1_21..)]
+    pub id: Option<ResourceLocation>,
+    pub amount: f64,
+    pub operation: AttributeOperation,
+}
+
+#[derive(Encoder, Decoder, Debug, Clone, Copy, PartialEq, Eq)]
+#[discriminant(i8)]
+pub enum AttributeOperation {
+    /// Adds the amount to the base value.
+    Add,
+    /// Adds the base value times the amount.
+    MultiplyBase,
+    /// Multiplies the value by one plus the amount, after the other two.
+    MultiplyTotal,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x76, state = Play, flow = Clientbound)]
+pub struct EntityEffect {
+    #[varint]
+    pub entity_id: i32,
+    #[varint]
+    pub effect_id: i32,
+    /// The level of the effect minus one.
+    #[varint]
+    pub amplifier: i32,
+    /// In ticks, `-1` for an effect that doesn't run out.
+    #[varint]
+    pub duration: i32,
+    pub flags: u8,
+}
+
+impl EntityEffect {
+    pub const AMBIENT: u8 = 0x01;
+    pub const SHOW_PARTICLES: u8 = 0x02;
+    pub const SHOW_ICON: u8 = 0x04;
+    pub const BLEND: u8 = 0x08;
+}
+
+/// Every recipe the player can craft.
+#[derive(Packet)]
+#[packet(id = 0x77, state = Play, flow = Clientbound)]
+pub struct UpdateRecipes {
+    /// Kept as bytes, since the ingredients and results are item slots.
+    #[rest]
+    pub data: Vec<u8>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x78, state = Play, flow = Clientbound)]
+pub struct UpdateTags {
+    #[prefixed(VarInt)]
+    pub registries: Vec<RegistryTags>,
+}
+
+// serverbound
+
+#[derive(Packet)]
+#[packet(id = 0x00, state = Play, flow = Serverbound)]
+pub struct ConfirmTeleportation {
+    #[varint]
+    pub teleport_id: i32,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x03, state = Play, flow = Serverbound)]
+pub struct AcknowledgeMessage {
+    #[varint]
+    pub message_count: i32,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x04, state = Play, flow = Serverbound)]
+pub struct ChatCommand {
+    /// The command without the leading `/`.
+    pub command: String,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x06, state = Play, flow = Serverbound)]
+pub struct ChatMessage {
+    #[max_len = 256]
+    pub message: String,
+    pub timestamp: i64,
+    pub salt: i64,
+    pub signature: Option<[u8; 256]>,
+    #[varint]
+    pub message_count: i32,
+    /// Which of the last 20 messages were seen, one bit each.
+    pub acknowledged: [u8; 3],
+}
+
+#[derive(Packet)]
+#[packet(id = 0x08, state = Play, flow = Serverbound)]
+pub struct ChunkBatchReceived {
+    pub chunks_per_tick: f32,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x09, state = Play, flow = Serverbound)]
+pub struct ClientStatus {
+    pub action: ClientStatusAction,
+}
+
+#[derive(Encoder, Decoder, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientStatusAction {
+    PerformRespawn,
+    RequestStats,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x0A, state = Play, flow = Serverbound)]
+pub struct ClientInformation {
+    pub information: Information,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x0C, state = Play, flow = Serverbound, next_state = Configuration)]
+pub struct AcknowledgeConfiguration;
+
+#[derive(Packet)]
+#[packet(id = 0x0D, state = Play, flow = Serverbound)]
+pub struct ClickContainerButton {
+    pub window_id: i8,
+    pub button_id: i8,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x0E, state = Play, flow = Serverbound)]
+pub struct ClickContainer {
+    pub window_id: u8,
+    #[varint]
+    pub state_id: i32,
+    pub slot: i16,
+    pub button: i8,
+    #[varint]
+    pub mode: i32,
+    /// The slots that changed followed by the item held by the cursor.
+    #[rest]
+    pub slot_data: Vec<u8>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x0F, state = Play, flow = Serverbound)]
+pub struct ServerboundCloseContainer {
+    pub window_id: u8,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x11, state = Play, flow = Serverbound)]
+pub struct CookieResponse {
+    pub key: ResourceLocation,
+    #[prefixed(VarInt)]
+    pub payload: Option<Vec<u8>>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x12, state = Play, flow = Serverbound)]
+pub struct ServerboundPluginMessage {
+    pub channel: ResourceLocation,
+    #[rest]
+    pub data: Vec<u8>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x16, state = Play, flow = Serverbound)]
+pub struct Interact {
+    #[varint]
+    pub entity_id: i32,
+    pub action: InteractAction,
+    pub sneaking: bool,
+}
+
+#[derive(Encoder, Decoder, Debug, Clone, Copy, PartialEq)]
+pub enum InteractAction {
+    Interact(Hand),
+    Attack,
+    InteractAt {
+        target_x: f32,
+        target_y: f32,
+        target_z: f32,
+        hand: Hand,
+    },
+}
+
+#[derive(Encoder, Decoder, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Hand {
+    #[default]
+    MainHand,
+    OffHand,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x18, state = Play, flow = Serverbound)]
+pub struct ServerboundKeepAlive {
+    pub id: i64,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x1A, state = Play, flow = Serverbound)]
+pub struct SetPlayerPosition {
+    pub x: f64,
+    pub feet_y: f64,
+    pub z: f64,
+    pub on_ground: bool,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x1B, state = Play, flow = Serverbound)]
+pub struct SetPlayerPositionAndRotation {
+    pub x: f64,
+    pub feet_y: f64,
+    pub z: f64,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub on_ground: bool,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x1C, state = Play, flow = Serverbound)]
+pub struct SetPlayerRotation {
+    pub yaw: f32,
+    pub pitch: f32,
+    pub on_ground: bool,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x1D, state = Play, flow = Serverbound)]
+pub struct SetPlayerOnGround {
+    pub on_ground: bool,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x21, state = Play, flow = Serverbound)]
+pub struct PingRequest {
+    pub payload: i64,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x24, state = Play, flow = Serverbound)]
+pub struct PlayerAction {
+    pub status: PlayerActionStatus,
+    pub location: BlockPosition,
+    // sent as a byte, which is the same as a VarInt for the values it has
+    pub face: BlockFace,
+    #[varint]
+    pub sequence: i32,
+}
+
+#[derive(Encoder, Decoder, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerActionStatus {
+    StartedDigging,
+    CancelledDigging,
+    FinishedDigging,
+    DropItemStack,
+    DropItem,
+    /// Also used for finishing eating and other items that are used by
+    /// holding them.
+    ShootArrow,
+    SwapItemInHand,
+}
+
+#[derive(Encoder, Decoder, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockFace {
+    Bottom,
+    Top,
+    North,
+    South,
+    West,
+    East,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x25, state = Play, flow = Serverbound)]
+pub struct PlayerCommand {
+    #[varint]
+    pub entity_id: i32,
+    pub action: PlayerCommandAction,
+    /// From 0 to 100 when starting to jump with a horse, otherwise 0.
+    #[varint]
+    pub jump_boost: i32,
+}
+
+#[derive(Encoder, Decoder, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerCommandAction {
+    StartSneaking,
+    StopSneaking,
+    LeaveBed,
+    StartSprinting,
+    StopSprinting,
+    StartJumpWithHorse,
+    StopJumpWithHorse,
+    OpenVehicleInventory,
+    StartFlyingWithElytra,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x27, state = Play, flow = Serverbound)]
+pub struct Pong {
+    pub id: i32,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x2B, state = Play, flow = Serverbound)]
+pub struct ResourcePackResponse {
+    pub uuid: Uuid,
+    pub result: ResourcePackResult,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x2F, state = Play, flow = Serverbound)]
+pub struct ServerboundSetHeldItem {
+    pub slot: i16,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x32, state = Play, flow = Serverbound)]
+pub struct SetCreativeModeSlot {
+    pub slot: i16,
+    #[rest]
+    pub slot_data: Vec<u8>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x36, state = Play, flow = Serverbound)]
+pub struct SwingArm {
+    pub hand: Hand,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x38, state = Play, flow = Serverbound)]
+pub struct UseItemOn {
+    pub hand: Hand,
+    pub location: BlockPosition,
+    pub face: BlockFace,
+    /// Where on the face the block was clicked, from 0 to 1.
+    pub cursor_x: f32,
+    pub cursor_y: f32,
+    pub cursor_z: f32,
+    pub inside_block: bool,
+    #[varint]
+    pub sequence: i32,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x39, state = Play, flow = Serverbound)]
+pub struct UseItem {
+    pub hand: Hand,
+    #[varint]
+    pub sequence: i32,
+    #[versions(V1_21..)]
+    pub yaw: Option<f32>,
+    #[versions(V1_21..)]
+    pub pitch: Option<f32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use blocky_net::{
+        encoder::Encoder, frame::Frame, packet::Packet, registry::PacketGroup,
+        version::ProtocolVersion,
+    };
+
+    use blocky_nbt::NbtTag;
+
+    use super::*;
+
+    /// Encodes and decodes a packet, checking its length on the way.
+    fn round_trip<P: Packet>(packet: &P) -> P {
+        let frame = Frame::from_packet(packet, ProtocolVersion::LATEST).unwrap();
+        assert_eq!(frame.id, P::ID);
+        assert_eq!(frame.body.len(), packet.byte_len());
+        frame.decode::<P>(ProtocolVersion::LATEST).unwrap()
+    }
+
+    #[test]
+    fn test_play_registries() {
+        assert_eq!(ClientboundPlayPacket::registry().len(), 72);
+        assert_eq!(ServerboundPlayPacket::registry().len(), 29);
+
+        let registry = ServerboundPlayPacket::registry_for(ProtocolVersion::V1_20_6).unwrap();
+        assert_eq!(registry.len(), 29);
+        assert!(ClientboundPlayPacket::registry_for(ProtocolVersion::V1_20_4).is_none());
+    }
+
+    #[test]
+    fn test_unknown_packet_kept() {
+        // award statistics, which has no packet here
+        let frame = Frame::new(0x04, vec![0x00]);
+        match ClientboundPlayPacket::decode_frame(&frame).unwrap() {
+            ClientboundPlayPacket::Unknown(unknown) => assert_eq!(unknown, frame),
+            _ => panic!("decoded the wrong packet"),
+        }
+
+        assert!(ServerboundPlayPacket::decode_frame(&Frame::new(0x7F, Vec::new())).is_err());
+    }
+
+    #[test]
+    fn test_player_info_update() {
+        let packet = PlayerInfoUpdate {
+            actions: PlayerInfoUpdate::ADD_PLAYER
+                | PlayerInfoUpdate::INITIALIZE_CHAT
+                | PlayerInfoUpdate::UPDATE_LISTED
+                | PlayerInfoUpdate::UPDATE_LATENCY,
+            players: vec![PlayerInfoEntry {
+                uuid: Uuid::from_u128(7),
+                profile: Some(PlayerProfile {
+                    name: "bot".to_string(),
+                    properties: vec![LoginSuccessProperty {
+                        name: "textures".to_string(),
+                        value: "e30=".to_string(),
+                        signature: None,
+                    }],
+                }),
+                chat_session: None,
+                listed: Some(true),
+                latency: Some(150),
+                ..PlayerInfoEntry::default()
+            }],
+        };
+
+        let frame = Frame::from_packet(&packet, ProtocolVersion::LATEST).unwrap();
+        assert_eq!(frame.id, 0x3E);
+        assert_eq!(frame.body.len(), packet.byte_len());
+        assert_eq!(frame.body[..2], [0x1B, 0x01]);
+        // no chat session, listed and a latency of 150
+        assert_eq!(frame.body[frame.body.len() - 4..], [0x00, 0x01, 0x96, 0x01]);
+
+        let decoded = frame
+            .decode::<PlayerInfoUpdate>(ProtocolVersion::LATEST)
+            .unwrap();
+        assert_eq!(decoded.actions, packet.actions);
+        assert_eq!(decoded.players, packet.players);
+
+        // an update only carries what its actions say
+        let update = Frame::new(
+            0x3E,
+            vec![
+                0x20, 0x01, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 7, 0x00,
+            ],
+        );
+        match ClientboundPlayPacket::decode_frame(&update).unwrap() {
+            ClientboundPlayPacket::PlayerInfoUpdate(decoded) => {
+                assert_eq!(decoded.players[0].uuid, Uuid::from_u128(7));
+                assert_eq!(decoded.players[0].display_name, None);
+                assert_eq!(decoded.players[0].profile, None);
+            }
+            _ => panic!("decoded the wrong packet"),
+        }
+
+        let missing = PlayerInfoUpdate {
+            actions: PlayerInfoUpdate::UPDATE_GAME_MODE,
+            players: vec![PlayerInfoEntry::default()],
+        };
+        let err = Frame::from_packet(&missing, ProtocolVersion::LATEST).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "PlayerInfoUpdate.players[0].game_mode: field is set but its condition says it is absent, or the other way around"
+        );
+    }
+
+    #[test]
+    fn test_boss_bar() {
+        let packet = round_trip(&BossBar {
+            uuid: Uuid::from_u128(1),
+            action: BossBarAction::Add {
+                title: TextComponent::text("Ender Dragon"),
+                health: 1.0,
+                color: BossBarColor::Pink,
+                division: BossBarDivision::None,
+                flags: BossBar::DRAGON_BAR | BossBar::CREATE_FOG,
+            },
+        });
+        match packet.action {
+            BossBarAction::Add { title, flags, .. } => {
+                assert_eq!(title.to_plain(), "Ender Dragon");
+                assert_eq!(flags, 0x06);
+            }
+            _ => panic!("decoded the wrong action"),
+        }
+
+        let frame = Frame::from_packet(
+            &BossBar {
+                uuid: Uuid::from_u128(1),
+                action: BossBarAction::UpdateHealth(0.5),
+            },
+            ProtocolVersion::LATEST,
+        )
+        .unwrap();
+        assert_eq!(frame.body[16..], [0x02, 0x3F, 0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_update_attributes_versions() {
+        let modifier = |uuid, id| AttributeModifier {
+            uuid,
+            id,
+            amount: 0.5,
+            operation: AttributeOperation::MultiplyBase,
+        };
+        let packet = |modifier| UpdateAttributes {
+            entity_id: 1,
+            attributes: vec![Attribute {
+                id: 17,
+                base: 0.1,
+                modifiers: vec![modifier],
+            }],
+        };
+
+        // modifiers were told apart by a UUID until 1.21
+        let old = packet(modifier(Some(Uuid::from_u128(2)), None));
+        let frame = Frame::from_packet(&old, ProtocolVersion::V1_20_6).unwrap();
+        assert_eq!(frame.body.len(), 1 + 1 + 1 + 8 + 1 + 16 + 8 + 1);
+        let decoded = frame
+            .decode::<UpdateAttributes>(ProtocolVersion::V1_20_6)
+            .unwrap();
+        assert_eq!(decoded.attributes, old.attributes);
+        assert!(Frame::from_packet(&old, ProtocolVersion::V1_21).is_err());
+
+        let new = packet(modifier(None, Some("minecraft:sprinting".parse().unwrap())));
+        let decoded = round_trip(&new);
+        assert_eq!(decoded.attributes, new.attributes);
+    }
+
+    #[test]
+    fn test_update_tags() {
+        let packet = round_trip(&UpdateTags {
+            registries: vec![RegistryTags {
+                registry: "minecraft:block".parse().unwrap(),
+                tags: Vec::new(),
+            }],
+        });
+        assert_eq!(packet.registries[0].registry.to_string(), "minecraft:block");
+    }
+
+    #[test]
+    fn test_use_item_versions() {
+        let packet = UseItem {
+            hand: Hand::MainHand,
+            sequence: 3,
+            yaw: None,
+            pitch: None,
+        };
+
+        // the rotation was only added in 1.21
+        let frame = Frame::from_packet(&packet, ProtocolVersion::V1_20_6).unwrap();
+        assert_eq!(frame, Frame::new(0x39, vec![0x00, 0x03]));
+        assert!(Frame::from_packet(&packet, ProtocolVersion::V1_21).is_err());
+
+        match ServerboundPlayPacket::decode_frame_for(ProtocolVersion::V1_20_6, &frame).unwrap() {
+            ServerboundPlayPacket::UseItem(decoded) => assert_eq!(decoded.yaw, None),
+            _ => panic!("decoded the wrong packet"),
+        }
+
+        let with_rotation = UseItem {
+            yaw: Some(90.0),
+            pitch: Some(0.0),
+            ..packet
+        };
+        let frame = Frame::from_packet(&with_rotation, ProtocolVersion::V1_21).unwrap();
+        assert_eq!(frame.body.len(), 10);
+        assert_eq!(
+            frame.decode::<UseItem>(ProtocolVersion::V1_21).unwrap().yaw,
+            Some(90.0)
+        );
+        // the same body read as 1.20.6 leaves the rotation over
+        assert!(frame.decode::<UseItem>(ProtocolVersion::V1_20_6).is_err());
+    }
+
+    #[test]
+    fn test_login() {
+        let spawn_info = SpawnInfo {
+            dimension_type: 0,
+            dimension_name: "minecraft:overworld".parse().unwrap(),
+            hashed_seed: -42,
+            game_mode: GameMode::Creative,
+            previous_game_mode: -1,
+            is_debug: false,
+            is_flat: true,
+            death_location: Some(GlobalPosition {
+                dimension: "minecraft:the_nether".parse().unwrap(),
+                location: BlockPosition { x: -5, y: 64, z: 9 },
+            }),
+            portal_cooldown: 0,
+        };
+
+        let login = Login {
+            entity_id: 7,
+            is_hardcore: false,
+            dimension_names: vec!["minecraft:overworld".parse().unwrap()],
+            max_players: 20,
+            view_distance: 10,
+            simulation_distance: 8,
+            reduced_debug_info: false,
+            enable_respawn_screen: true,
+            do_limited_crafting: false,
+            spawn_info: spawn_info.clone(),
+            enforces_secure_chat: true,
+        };
+
+        let decoded = round_trip(&login);
+        assert_eq!(decoded.entity_id, 7);
+        assert_eq!(decoded.dimension_names, login.dimension_names);
+        assert_eq!(decoded.spawn_info, spawn_info);
+        assert!(decoded.enforces_secure_chat);
+    }
+
+    #[test]
+    fn test_chunk_data() {
+        let mut sky_light_mask = BitSet::default();
+        sky_light_mask.set(1, true);
+
+        let packet = ChunkDataAndUpdateLight {
+            chunk_x: -3,
+            chunk_z: 4,
+            data: ChunkData {
+                heightmaps: [(
+                    "MOTION_BLOCKING".to_string(),
+                    NbtTag::LongArray(vec![0; 37]),
+                )]
+                .into_iter()
+                .collect(),
+                data: vec![1, 2, 3, 4],
+                block_entities: vec![ChunkBlockEntity {
+                    packed_xz: 0x3F,
+                    y: -60,
+                    kind: 7,
+                    data: OptionalNbt(None),
+                }],
+            },
+            light: LightData {
+                sky_light_mask,
+                sky_light: vec![LightArray(vec![0xFF; 2048])],
+                ..Default::default()
+            },
+        };
+
+        let decoded = round_trip(&packet);
+        assert_eq!((decoded.chunk_x, decoded.chunk_z), (-3, 4));
+        assert_eq!(decoded.data, packet.data);
+        assert_eq!(decoded.light, packet.light);
+    }
+
+    #[test]
+    fn test_player_chat_message() {
+        let packet = PlayerChatMessage {
+            sender: Uuid::from_u128(1),
+            index: 0,
+            signature: Some([7; 256]),
+            message: "hello".to_string(),
+            timestamp: 1_700_000_000_000,
+            salt: 99,
+            previous_messages: vec![
+                PreviousMessage {
+                    id: 3,
+                    signature: None,
+                },
+                PreviousMessage {
+                    id: 0,
+                    signature: Some([1; 256]),
+                },
+            ],
+            unsigned_content: None,
+            filter: FilterMask::PartiallyFiltered(BitSet(vec![0b101])),
+            chat_type: ChatTypeBound {
+                chat_type: IdOr::Id(0),
+                sender_name: TextComponent::text("bot"),
+                target_name: None,
+            },
+        };
+
+        let decoded = round_trip(&packet);
+        assert_eq!(decoded.signature, packet.signature);
+        assert_eq!(decoded.message, "hello");
+        assert_eq!(decoded.previous_messages, packet.previous_messages);
+        assert_eq!(decoded.filter, packet.filter);
+        assert_eq!(decoded.chat_type, packet.chat_type);
+
+        // the registry id is sent plus one
+        let frame = Frame::from_packet(&packet, ProtocolVersion::LATEST).unwrap();
+        let chat_type = packet.chat_type.to_bytes().unwrap();
+        assert_eq!(chat_type[0], 0x01);
+        assert!(frame.body.ends_with(&chat_type));
+    }
+
+    #[test]
+    fn test_interact() {
+        let packet = Interact {
+            entity_id: 12,
+            action: InteractAction::InteractAt {
+                target_x: 0.5,
+                target_y: 1.0,
+                target_z: 0.25,
+                hand: Hand::OffHand,
+            },
+            sneaking: true,
+        };
+
+        let frame = Frame::from_packet(&packet, ProtocolVersion::LATEST).unwrap();
+        assert_eq!(frame.body[..2], [12, 2]);
+        assert_eq!(frame.body[14], 1);
+
+        let decoded = frame.decode::<Interact>(ProtocolVersion::LATEST).unwrap();
+        assert_eq!(decoded.action, packet.action);
+
+        let attack = round_trip(&Interact {
+            entity_id: 12,
+            action: InteractAction::Attack,
+            sneaking: false,
+        });
+        assert_eq!(attack.action, InteractAction::Attack);
+    }
+
+    #[test]
+    fn test_synchronize_player_position() {
+        let packet = SynchronizePlayerPosition {
+            x: 1.5,
+            y: 70.0,
+            z: -3.5,
+            yaw: 90.0,
+            pitch: 0.0,
+            flags: SynchronizePlayerPosition::RELATIVE_YAW,
+            teleport_id: 300,
+        };
+
+        match ClientboundPlayPacket::decode_frame(
+            &Frame::from_packet(&packet, ProtocolVersion::LATEST).unwrap(),
+        )
+        .unwrap()
+        {
+            ClientboundPlayPacket::SynchronizePlayerPosition(decoded) => {
+                assert_eq!(decoded.confirm().teleport_id, 300);
+                assert_eq!(decoded.flags, SynchronizePlayerPosition::RELATIVE_YAW);
+            }
+            _ => panic!("decoded the wrong packet"),
+        }
+    }
+
+    #[test]
+    fn test_update_section_blocks() {
+        // section (-1, -4, 2) with stone at (3, 5, 7) within it
+        let (x, y, z) = (-1i64, -4i64, 2i64);
+        let section = ((x & 0x3FFFFF) << 42) | ((z & 0x3FFFFF) << 20) | (y & 0xFFFFF);
+        let packet = round_trip(&UpdateSectionBlocks {
+            section,
+            blocks: vec![1 << 12 | 3 << 8 | 7 << 4 | 5],
+        });
+
+        assert_eq!(packet.section_position(), (-1, -4, 2));
+        assert_eq!(
+            packet.blocks().collect::<Vec<_>>(),
+            [(
+                BlockPosition {
+                    x: -13,
+                    y: -59,
+                    z: 39
+                },
+                1
+            )]
+        );
+    }
+
+    #[test]
+    fn test_slot_data_kept() {
+        let packet = round_trip(&SetContainerSlot {
+            window_id: -1,
+            state_id: 5,
+            slot: 36,
+            slot_data: vec![0x01, 0x8A, 0x01, 0x00, 0x00],
+        });
+
+        assert_eq!(packet.window_id, -1);
+        assert_eq!(packet.slot_data, [0x01, 0x8A, 0x01, 0x00, 0x00]);
+    }
+}
@@ -0,0 +1,564 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use blocky_derive::Packet;
+use blocky_net::{
+    decoder::Decoder, error::ProtocolError, packet_group, types::VarInt, version::ProtocolVersion,
+};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+use crate::text::TextComponent;
+
+packet_group! {
+    pub enum ClientboundStatusPacket(Status, Clientbound) {
+        StatusResponse,
+        PongResponse,
+    }
+}
+
+packet_group! {
+    pub enum ServerboundStatusPacket(Status, Serverbound) {
+        StatusRequest,
+        PingRequest,
+    }
+}
+
+// clientbound
+
+#[derive(Packet)]
+#[packet(id = 0x00, state = Status, flow = Clientbound)]
+pub struct StatusResponse {
+    #[json]
+    pub status: ServerStatus,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x01, state = Status, flow = Clientbound)]
+pub struct PongResponse {
+    pub time: u64,
+}
+
+// serverbound
+
+#[derive(Packet)]
+#[packet(id = 0x00, state = Status, flow = Serverbound)]
+pub struct StatusRequest;
+
+#[derive(Packet)]
+#[packet(id = 0x01, state = Status, flow = Serverbound)]
+pub struct PingRequest {
+    pub time: u64,
+}
+
+/// What a server shows in the server list.
+///
+/// Servers, proxies and mod loaders all add their own fields or leave out
+/// standard ones, so every field is optional or defaulted and a field of the
+/// wrong type is treated as missing. Fields this type doesn't know about are
+/// kept in `other`.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerStatus {
+    #[serde(default, deserialize_with = "lenient")]
+    pub version: Option<StatusVersion>,
+    #[serde(default, deserialize_with = "lenient")]
+    pub players: Option<StatusPlayers>,
+    #[serde(default, deserialize_with = "lenient_description")]
+    pub description: TextComponent,
+    /// A PNG as a `data:image/png;base64,` URI, see [`Self::favicon_png`].
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub favicon: Option<String>,
+    #[serde(default, deserialize_with = "lenient")]
+    pub enforces_secure_chat: bool,
+    /// The mods of a Forge or NeoForge server since 1.13.
+    #[serde(
+        default,
+        deserialize_with = "lenient_forge_data",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub forge_data: Option<ForgeData>,
+    /// The mods of a Forge server before 1.13.
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        rename = "modinfo",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub mod_info: Option<LegacyModInfo>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct StatusVersion {
+    /// The name of the version, which servers often use for their own text.
+    #[serde(default, deserialize_with = "lenient")]
+    pub name: String,
+    /// The protocol version the server speaks, or `-1` and such when it
+    /// wants the version shown as incompatible.
+    #[serde(default, deserialize_with = "lenient")]
+    pub protocol: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct StatusPlayers {
+    #[serde(default, deserialize_with = "lenient")]
+    pub max: i32,
+    #[serde(default, deserialize_with = "lenient")]
+    pub online: i32,
+    /// Some of the players online, or any lines the server wants shown when
+    /// hovering over the player count.
+    #[serde(
+        default,
+        deserialize_with = "lenient_list",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub sample: Vec<StatusPlayer>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusPlayer {
+    pub name: String,
+    /// Often the nil UUID when the entry is only a line of text.
+    #[serde(default, deserialize_with = "lenient")]
+    pub id: Uuid,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForgeData {
+    #[serde(default, deserialize_with = "lenient_list")]
+    pub channels: Vec<ForgeChannel>,
+    #[serde(default, deserialize_with = "lenient_list")]
+    pub mods: Vec<ForgeMod>,
+    #[serde(default, deserialize_with = "lenient")]
+    pub fml_network_version: i32,
+    /// Whether the server left out mods to keep the response small.
+    #[serde(default, deserialize_with = "lenient")]
+    pub truncated: bool,
+    /// The channels and mods packed into a string, sent instead of the lists
+    /// above since Forge 1.18.2. It is unpacked into them when the status is
+    /// read, and only kept if that fails.
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub d: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForgeChannel {
+    pub res: String,
+    #[serde(default, deserialize_with = "lenient")]
+    pub version: String,
+    #[serde(default, deserialize_with = "lenient")]
+    pub required: bool,
+}
+
+impl ForgeData {
+    /// Reads the mods and channels packed into `d`. The string holds 15 bits
+    /// in each UTF-16 unit, after two units with the number of bytes.
+    fn unpack(d: &str) -> Result<Self, ProtocolError> {
+        let mut units = d.encode_utf16();
+        let (Some(low), Some(high)) = (units.next(), units.next()) else {
+            return Err(ProtocolError::UnexpectedEof);
+        };
+        let len = low as usize | (high as usize) << 15;
+
+        let mut bytes = Vec::new();
+        let (mut bits, mut buffer) = (0, 0u32);
+        for unit in units {
+            buffer |= ((unit & 0x7FFF) as u32) << bits;
+            bits += 15;
+
+            while bits >= 8 {
+                bytes.push(buffer as u8);
+                buffer >>= 8;
+                bits -= 8;
+            }
+        }
+
+        if bytes.len() < len {
+            return Err(ProtocolError::UnexpectedEof);
+        }
+        bytes.truncate(len);
+
+        let mut buf = &bytes[..];
+        let truncated = bool::decode(&mut buf)?;
+
+        let mut channels = Vec::new();
+        let mod_count = u16::decode(&mut buf)?;
+        let mut mods = Vec::with_capacity(mod_count.into());
+        for _ in 0..mod_count {
+            // the channels of the mod, and whether it has no version
+            let flags = VarInt::decode(&mut buf)?.0;
+            let id = String::decode(&mut buf)?;
+            let version = match flags & 1 {
+                0 => String::decode(&mut buf)?,
+                _ => ForgeMod::IGNORE_SERVER_ONLY.to_string(),
+            };
+
+            for _ in 0..flags >> 1 {
+                let path = String::decode(&mut buf)?;
+                channels.push(ForgeChannel {
+                    res: format!("{id}:{path}"),
+                    version: String::decode(&mut buf)?,
+                    required: bool::decode(&mut buf)?,
+                });
+            }
+
+            mods.push(ForgeMod { id, version });
+        }
+
+        // the channels that don't belong to any mod
+        for _ in 0..VarInt::decode(&mut buf)?.0 {
+            channels.push(ForgeChannel {
+                res: String::decode(&mut buf)?,
+                version: String::decode(&mut buf)?,
+                required: bool::decode(&mut buf)?,
+            });
+        }
+
+        Ok(Self {
+            channels,
+            mods,
+            truncated,
+            ..Default::default()
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForgeMod {
+    #[serde(rename = "modId")]
+    pub id: String,
+    /// The version of the mod, or `ANY` and such for mods without one.
+    #[serde(rename = "modmarker", default, deserialize_with = "lenient")]
+    pub version: String,
+}
+
+impl ForgeMod {
+    /// The version Forge gives mods that don't have to be installed on the
+    /// client, which the packed format only has a flag for.
+    pub const IGNORE_SERVER_ONLY: &'static str = "OHNOES😱😱😱😱";
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct LegacyModInfo {
+    #[serde(rename = "type", default, deserialize_with = "lenient")]
+    pub kind: String,
+    #[serde(rename = "modList", default, deserialize_with = "lenient_list")]
+    pub mods: Vec<LegacyMod>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LegacyMod {
+    #[serde(rename = "modid")]
+    pub id: String,
+    #[serde(default, deserialize_with = "lenient")]
+    pub version: String,
+}
+
+impl ServerStatus {
+    /// The protocol version of the server, if it sent one.
+    pub fn protocol_version(&self) -> Option<i32> {
+        self.version.as_ref().map(|version| version.protocol)
+    }
+
+    /// The version to connect to the server with, if it speaks one this
+    /// crate has packets for.
+    pub fn supported_version(&self) -> Option<ProtocolVersion> {
+        ProtocolVersion::from_protocol(self.protocol_version()?)
+    }
+
+    /// Decodes the favicon into the bytes of its PNG. Line breaks, which
+    /// some older servers put in the base64, are skipped.
+    pub fn favicon_png(&self) -> Option<Vec<u8>> {
+        let data = self
+            .favicon
+            .as_deref()?
+            .strip_prefix("data:image/png;base64,")?;
+        let data = data
+            .chars()
+            .filter(|c| !c.is_ascii_whitespace())
+            .collect::<String>();
+
+        STANDARD.decode(data).ok()
+    }
+
+    /// Whether the server says it runs mods.
+    pub fn is_modded(&self) -> bool {
+        self.forge_data.is_some() || self.mod_info.is_some()
+    }
+
+    /// The id and version of each mod the server listed, for both Forge
+    /// formats.
+    pub fn mods(&self) -> Vec<(&str, &str)> {
+        let forge = self
+            .forge_data
+            .iter()
+            .flat_map(|data| &data.mods)
+            .map(|m| (m.id.as_str(), m.version.as_str()));
+        let legacy = self
+            .mod_info
+            .iter()
+            .flat_map(|info| &info.mods)
+            .map(|m| (m.id.as_str(), m.version.as_str()));
+
+        forge.chain(legacy).collect()
+    }
+}
+
+/// Deserializes a field, falling back to its default when it has the wrong
+/// type rather than failing the whole status.
+fn lenient<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    let value = Value::deserialize(deserializer)?;
+    Ok(T::deserialize(value).unwrap_or_default())
+}
+
+/// Like [`lenient`], but skips just the elements that can't be read.
+fn lenient_list<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    let Value::Array(values) = Value::deserialize(deserializer)? else {
+        return Ok(Vec::new());
+    };
+
+    Ok(values
+        .into_iter()
+        .filter_map(|value| T::deserialize(value).ok())
+        .collect())
+}
+
+/// Like [`lenient`], unpacking the mods and channels Forge packs into `d`.
+fn lenient_forge_data<'de, D>(deserializer: D) -> Result<Option<ForgeData>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let Some(mut data) = lenient::<D, Option<ForgeData>>(deserializer)? else {
+        return Ok(None);
+    };
+
+    if let Some(unpacked) = data.d.as_deref().and_then(|d| ForgeData::unpack(d).ok()) {
+        data = ForgeData {
+            fml_network_version: data.fml_network_version,
+            ..unpacked
+        };
+    }
+
+    Ok(Some(data))
+}
+
+/// A description that isn't a valid component is shown as its JSON, the
+/// same as the client does.
+fn lenient_description<'de, D>(deserializer: D) -> Result<TextComponent, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Value::deserialize(deserializer)?;
+    Ok(TextComponent::from_json(&value).unwrap_or_else(|_| TextComponent::text(value.to_string())))
+}
+
+#[cfg(test)]
+mod tests {
+    use blocky_net::{encoder::Encoder, frame::Frame};
+
+    use super::*;
+
+    #[test]
+    fn test_status_response() {
+        let json = r#"{
+            "version": {"name": "1.21", "protocol": 767},
+            "players": {
+                "max": 100,
+                "online": 2,
+                "sample": [
+                    {"name": "Steve", "id": "4566e69f-c907-48ee-8d71-d7ba5aa00d20"},
+                    {"name": "broken"}
+                ]
+            },
+            "description": {"text": "A ", "extra": [{"text": "server", "bold": true}]},
+            "favicon": "data:image/png;base64,iVBO\nRw0K",
+            "enforcesSecureChat": true,
+            "preventsChatReports": true
+        }"#;
+
+        let mut body = Vec::new();
+        json.encode(&mut body).unwrap();
+        let status = Frame::new(0x00, body)
+            .decode::<StatusResponse>(ProtocolVersion::LATEST)
+            .unwrap()
+            .status;
+
+        assert_eq!(status.protocol_version(), Some(767));
+        assert_eq!(status.supported_version(), Some(ProtocolVersion::V1_21));
+        let players = status.players.as_ref().unwrap();
+        assert_eq!((players.max, players.online), (100, 2));
+        assert_eq!(players.sample[0].name, "Steve");
+        assert_eq!(players.sample[1].id, Uuid::nil());
+        assert_eq!(status.description.to_plain(), "A server");
+        assert_eq!(status.favicon_png().unwrap(), b"\x89PNG\r\n");
+        assert!(status.enforces_secure_chat);
+        assert_eq!(status.other["preventsChatReports"], Value::Bool(true));
+        assert!(!status.is_modded());
+    }
+
+    #[test]
+    fn test_status_lenient() {
+        // wrong types everywhere and a description the client would show raw
+        let status: ServerStatus = serde_json::from_str(
+            r#"{"version": "1.8", "players": {"max": "lots", "online": 3, "sample": [1, {"name": "a"}]},
+                "description": {"colour": "red"}, "favicon": 5}"#,
+        )
+        .unwrap();
+
+        assert_eq!(status.version, None);
+        let players = status.players.unwrap();
+        assert_eq!((players.max, players.online), (0, 3));
+        assert_eq!(players.sample.len(), 1);
+        assert_eq!(status.description.to_plain(), r#"{"colour":"red"}"#);
+        assert_eq!(status.favicon, None);
+
+        let status: ServerStatus = serde_json::from_str(r#"{"description": "§aHi"}"#).unwrap();
+        assert_eq!(status.players, None);
+        assert_eq!(status.description.to_plain(), "§aHi");
+    }
+
+    #[test]
+    fn test_status_mods() {
+        let status: ServerStatus = serde_json::from_str(
+            r#"{
+                "forgeData": {
+                    "channels": [{"res": "fml:handshake", "version": "1.2.3.4", "required": true}],
+                    "mods": [{"modId": "forge", "modmarker": "ANY"}, {"modId": "jei", "modmarker": "19.0"}],
+                    "fmlNetworkVersion": 3
+                },
+                "modinfo": {"type": "FML", "modList": [{"modid": "mcp", "version": "9.42"}]}
+            }"#,
+        )
+        .unwrap();
+
+        assert!(status.is_modded());
+        assert_eq!(status.forge_data.as_ref().unwrap().channels.len(), 1);
+        assert_eq!(
+            status.mods(),
+            [("forge", "ANY"), ("jei", "19.0"), ("mcp", "9.42")]
+        );
+    }
+
+    /// Packs `bytes` the way Forge's `ServerStatusPing` does, 15 bits to a
+    /// UTF-16 unit after the byte count.
+    fn pack(bytes: &[u8]) -> String {
+        let mut units = vec![bytes.len() as u16 & 0x7FFF, (bytes.len() >> 15) as u16];
+        let (mut bits, mut buffer) = (0, 0u32);
+        for &byte in bytes {
+            if bits >= 15 {
+                units.push(buffer as u16 & 0x7FFF);
+                buffer >>= 15;
+                bits -= 15;
+            }
+            buffer |= (byte as u32) << bits;
+            bits += 8;
+        }
+        if bits > 0 {
+            units.push(buffer as u16 & 0x7FFF);
+        }
+
+        String::from_utf16(&units).unwrap()
+    }
+
+    #[test]
+    fn test_status_packed_mods() {
+        // laid out like the forgeData of a 1.20.1 Forge server, built here
+        // rather than captured from one
+        let mut bytes = Vec::new();
+        true.encode(&mut bytes).unwrap();
+        2u16.encode(&mut bytes).unwrap();
+        // forge with one channel
+        VarInt(1 << 1).encode(&mut bytes).unwrap();
+        "forge".encode(&mut bytes).unwrap();
+        "47.3.0".encode(&mut bytes).unwrap();
+        "login".encode(&mut bytes).unwrap();
+        "2.0".encode(&mut bytes).unwrap();
+        true.encode(&mut bytes).unwrap();
+        // a server only mod, without a version
+        VarInt(1).encode(&mut bytes).unwrap();
+        "spark".encode(&mut bytes).unwrap();
+        // a channel of no mod
+        VarInt(1).encode(&mut bytes).unwrap();
+        "minecraft:register".encode(&mut bytes).unwrap();
+        "FML3".encode(&mut bytes).unwrap();
+        false.encode(&mut bytes).unwrap();
+
+        let json = serde_json::json!({
+            "description": "A modded server",
+            "forgeData": {
+                "channels": [],
+                "mods": [],
+                "truncated": false,
+                "fmlNetworkVersion": 3,
+                "d": pack(&bytes),
+            }
+        });
+        let status: ServerStatus = serde_json::from_value(json).unwrap();
+
+        let data = status.forge_data.as_ref().unwrap();
+        assert!(data.truncated);
+        assert_eq!(data.fml_network_version, 3);
+        assert_eq!(data.d, None);
+        assert_eq!(
+            status.mods(),
+            [("forge", "47.3.0"), ("spark", ForgeMod::IGNORE_SERVER_ONLY)]
+        );
+        assert_eq!(
+            data.channels,
+            [
+                ForgeChannel {
+                    res: "forge:login".into(),
+                    version: "2.0".into(),
+                    required: true,
+                },
+                ForgeChannel {
+                    res: "minecraft:register".into(),
+                    version: "FML3".into(),
+                    required: false,
+                },
+            ]
+        );
+
+        // a string that can't be unpacked is kept as it is
+        let status: ServerStatus =
+            serde_json::from_str(r#"{"forgeData": {"d": "\u0040\u0000"}}"#).unwrap();
+        assert_eq!(status.forge_data.unwrap().d.as_deref(), Some("\u{40}\0"));
+    }
+
+    #[test]
+    fn test_status_round_trip() {
+        let status = ServerStatus {
+            version: Some(StatusVersion {
+                name: "1.21".into(),
+                protocol: 767,
+            }),
+            description: TextComponent::text("hello"),
+            ..Default::default()
+        };
+
+        let json = serde_json::to_value(&status).unwrap();
+        assert_eq!(json["description"], "hello");
+        assert_eq!(
+            serde_json::from_value::<ServerStatus>(json).unwrap(),
+            status
+        );
+    }
+}
@@ -0,0 +1,1348 @@
+//! Text components, the formatted text of chat messages, titles, item names
+//! and disconnect reasons.
+//!
+//! The login and status states send components as JSON, every other state as
+//! network NBT since 1.20.3. [`TextComponent`] implements `serde` for the
+//! JSON form, used with `#[json]`, and [`Encoder`]/[`Decoder`] for the NBT
+//! form. Both forms have the same structure, so a component is converted to
+//! a JSON value first and from there to NBT.
+
+use std::{
+    fmt,
+    io::{Read, Write},
+};
+
+use blocky_nbt::{NbtCompound, NbtTag, OptionalNbt};
+use blocky_net::{
+    decoder::Decoder,
+    encoder::Encoder,
+    error::{ProtocolError, Result},
+};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Number, Value};
+use uuid::Uuid;
+
+/// A piece of formatted text, with the components in `extra` following it
+/// and inheriting its style.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TextComponent {
+    pub content: TextContent,
+    pub style: Style,
+    pub extra: Vec<TextComponent>,
+}
+
+/// What a component shows before its children.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TextContent {
+    Text(String),
+    /// A translation key, shown in the client's language with `args` in
+    /// place of its `%s`s.
+    Translatable {
+        key: String,
+        fallback: Option<String>,
+        args: Vec<TextComponent>,
+    },
+    /// The key bound to a control, such as `key.jump`.
+    Keybind(String),
+    /// The score of an entity in an objective, filled in by the server.
+    Score {
+        name: String,
+        objective: String,
+    },
+    /// The names of the entities an entity selector matches, filled in by
+    /// the server.
+    Selector {
+        pattern: String,
+        separator: Option<Box<TextComponent>>,
+    },
+    /// Values from the NBT of a block, entity or storage, filled in by the
+    /// server.
+    Nbt {
+        path: String,
+        interpret: bool,
+        separator: Option<Box<TextComponent>>,
+        source: NbtSource,
+    },
+}
+
+impl Default for TextContent {
+    fn default() -> Self {
+        Self::Text(String::new())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NbtSource {
+    /// The coordinates of a block, such as `~ ~-1 ~`.
+    Block(String),
+    /// An entity selector.
+    Entity(String),
+    /// The resource location of a command storage.
+    Storage(String),
+}
+
+/// How a component is formatted. Everything left as `None` is inherited from
+/// the parent component.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Style {
+    pub color: Option<TextColor>,
+    pub bold: Option<bool>,
+    pub italic: Option<bool>,
+    pub underlined: Option<bool>,
+    pub strikethrough: Option<bool>,
+    pub obfuscated: Option<bool>,
+    pub font: Option<String>,
+    /// Text inserted into the chat box when the component is shift-clicked.
+    pub insertion: Option<String>,
+    pub click_event: Option<ClickEvent>,
+    pub hover_event: Option<HoverEvent>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextColor {
+    Named(NamedColor),
+    Rgb(u8, u8, u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NamedColor {
+    Black,
+    DarkBlue,
+    DarkGreen,
+    DarkAqua,
+    DarkRed,
+    DarkPurple,
+    Gold,
+    Gray,
+    DarkGray,
+    Blue,
+    Green,
+    Aqua,
+    Red,
+    LightPurple,
+    Yellow,
+    White,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClickEvent {
+    OpenUrl(String),
+    OpenFile(String),
+    RunCommand(String),
+    SuggestCommand(String),
+    ChangePage(i32),
+    CopyToClipboard(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HoverEvent {
+    ShowText(Box<TextComponent>),
+    ShowItem {
+        id: String,
+        count: i32,
+        /// The item components that differ from the item's defaults.
+        components: Option<Value>,
+    },
+    ShowEntity {
+        kind: String,
+        id: Uuid,
+        name: Option<Box<TextComponent>>,
+    },
+}
+
+impl TextComponent {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::from_content(TextContent::Text(text.into()))
+    }
+
+    pub fn translatable(key: impl Into<String>, args: Vec<TextComponent>) -> Self {
+        Self::from_content(TextContent::Translatable {
+            key: key.into(),
+            fallback: None,
+            args,
+        })
+    }
+
+    pub fn keybind(key: impl Into<String>) -> Self {
+        Self::from_content(TextContent::Keybind(key.into()))
+    }
+
+    pub fn from_content(content: TextContent) -> Self {
+        Self {
+            content,
+            style: Style::default(),
+            extra: Vec::new(),
+        }
+    }
+
+    pub fn color(mut self, color: impl Into<TextColor>) -> Self {
+        self.style.color = Some(color.into());
+        self
+    }
+
+    pub fn bold(mut self, bold: bool) -> Self {
+        self.style.bold = Some(bold);
+        self
+    }
+
+    pub fn italic(mut self, italic: bool) -> Self {
+        self.style.italic = Some(italic);
+        self
+    }
+
+    pub fn underlined(mut self, underlined: bool) -> Self {
+        self.style.underlined = Some(underlined);
+        self
+    }
+
+    pub fn strikethrough(mut self, strikethrough: bool) -> Self {
+        self.style.strikethrough = Some(strikethrough);
+        self
+    }
+
+    pub fn obfuscated(mut self, obfuscated: bool) -> Self {
+        self.style.obfuscated = Some(obfuscated);
+        self
+    }
+
+    pub fn click_event(mut self, event: ClickEvent) -> Self {
+        self.style.click_event = Some(event);
+        self
+    }
+
+    pub fn hover_event(mut self, event: HoverEvent) -> Self {
+        self.style.hover_event = Some(event);
+        self
+    }
+
+    /// Adds a child component after this one and its other children.
+    pub fn append(mut self, child: impl Into<TextComponent>) -> Self {
+        self.extra.push(child.into());
+        self
+    }
+
+    /// The text without any formatting, as shown by the client. Translation
+    /// keys are only known for common chat messages, other keys are shown
+    /// with their arguments in brackets.
+    pub fn to_plain(&self) -> String {
+        let mut out = String::new();
+        self.write_plain(&mut out);
+        out
+    }
+
+    fn write_plain(&self, out: &mut String) {
+        self.content.write_plain(out);
+        for child in &self.extra {
+            child.write_plain(out);
+        }
+    }
+
+    /// The text with its colors and formatting as ANSI escape codes, for
+    /// printing to a terminal.
+    pub fn to_ansi(&self) -> String {
+        let mut out = String::new();
+        self.write_ansi(&Style::default(), &mut out);
+        out.push_str("\x1b[0m");
+        out
+    }
+
+    fn write_ansi(&self, parent: &Style, out: &mut String) {
+        let style = self.style.inherit(parent);
+
+        let mut text = String::new();
+        self.content.write_plain(&mut text);
+        if !text.is_empty() {
+            out.push_str(&style.ansi_codes());
+            out.push_str(&text);
+        }
+
+        for child in &self.extra {
+            child.write_ansi(&style, out);
+        }
+    }
+
+    /// The JSON form of the component.
+    pub fn to_json(&self) -> Value {
+        let mut object = Map::new();
+        match &self.content {
+            TextContent::Text(text) => {
+                if self.style == Style::default() && self.extra.is_empty() {
+                    return Value::String(text.clone());
+                }
+                object.insert("text".into(), text.clone().into());
+            }
+            TextContent::Translatable {
+                key,
+                fallback,
+                args,
+            } => {
+                object.insert("translate".into(), key.clone().into());
+                if let Some(fallback) = fallback {
+                    object.insert("fallback".into(), fallback.clone().into());
+                }
+                if !args.is_empty() {
+                    let args = args.iter().map(Self::to_json).collect();
+                    object.insert("with".into(), Value::Array(args));
+                }
+            }
+            TextContent::Keybind(key) => {
+                object.insert("keybind".into(), key.clone().into());
+            }
+            TextContent::Score { name, objective } => {
+                let score = Map::from_iter([
+                    ("name".to_string(), name.clone().into()),
+                    ("objective".to_string(), objective.clone().into()),
+                ]);
+                object.insert("score".into(), Value::Object(score));
+            }
+            TextContent::Selector { pattern, separator } => {
+                object.insert("selector".into(), pattern.clone().into());
+                if let Some(separator) = separator {
+                    object.insert("separator".into(), separator.to_json());
+                }
+            }
+            TextContent::Nbt {
+                path,
+                interpret,
+                separator,
+                source,
+            } => {
+                object.insert("nbt".into(), path.clone().into());
+                if *interpret {
+                    object.insert("interpret".into(), true.into());
+                }
+                if let Some(separator) = separator {
+                    object.insert("separator".into(), separator.to_json());
+                }
+                let (kind, value) = match source {
+                    NbtSource::Block(value) => ("block", value),
+                    NbtSource::Entity(value) => ("entity", value),
+                    NbtSource::Storage(value) => ("storage", value),
+                };
+                object.insert(kind.into(), value.clone().into());
+            }
+        }
+
+        self.style.write_json(&mut object);
+        if !self.extra.is_empty() {
+            let extra = self.extra.iter().map(Self::to_json).collect();
+            object.insert("extra".into(), Value::Array(extra));
+        }
+
+        Value::Object(object)
+    }
+
+    /// Reads the JSON form of a component, which may also be a plain string,
+    /// or an array of components whose first element is the parent of the
+    /// rest.
+    pub fn from_json(value: &Value) -> std::result::Result<Self, String> {
+        match value {
+            Value::String(text) => Ok(Self::text(text.as_str())),
+            Value::Number(number) => Ok(Self::text(number.to_string())),
+            Value::Bool(value) => Ok(Self::text(value.to_string())),
+            Value::Array(values) => {
+                let Some((first, rest)) = values.split_first() else {
+                    return Err("text component array is empty".into());
+                };
+
+                let mut component = Self::from_json(first)?;
+                for value in rest {
+                    component.extra.push(Self::from_json(value)?);
+                }
+                Ok(component)
+            }
+            Value::Object(object) => {
+                // NBT lists holding strings and compounds wrap the strings
+                if let (1, Some(value)) = (object.len(), object.get("")) {
+                    return Self::from_json(value);
+                }
+
+                let content = TextContent::from_json(object)?;
+                let style = Style::from_json(object)?;
+                let extra = match object.get("extra") {
+                    Some(Value::Array(values)) => values
+                        .iter()
+                        .map(Self::from_json)
+                        .collect::<std::result::Result<_, _>>()?,
+                    Some(_) => return Err("`extra` is not an array".into()),
+                    None => Vec::new(),
+                };
+
+                Ok(Self {
+                    content,
+                    style,
+                    extra,
+                })
+            }
+            Value::Null => Err("text component is null".into()),
+        }
+    }
+
+    /// The NBT form of the component.
+    pub fn to_nbt(&self) -> NbtTag {
+        json_to_nbt(&self.to_json())
+    }
+
+    pub fn from_nbt(tag: &NbtTag) -> Result<Self> {
+        Self::from_json(&nbt_to_json(tag)).map_err(ProtocolError::custom)
+    }
+}
+
+impl TextContent {
+    fn from_json(object: &Map<String, Value>) -> std::result::Result<Self, String> {
+        if let Some(text) = object.get("text") {
+            return Ok(Self::Text(primitive_string(text, "text")?));
+        }
+
+        if let Some(key) = object.get("translate") {
+            let args = match object.get("with") {
+                Some(Value::Array(values)) => values
+                    .iter()
+                    .map(TextComponent::from_json)
+                    .collect::<std::result::Result<_, _>>()?,
+                Some(_) => return Err("`with` is not an array".into()),
+                None => Vec::new(),
+            };
+
+            return Ok(Self::Translatable {
+                key: string(key, "translate")?,
+                fallback: optional_string(object, "fallback")?,
+                args,
+            });
+        }
+
+        if let Some(score) = object.get("score") {
+            let Value::Object(score) = score else {
+                return Err("`score` is not an object".into());
+            };
+
+            return Ok(Self::Score {
+                name: optional_string(score, "name")?.unwrap_or_default(),
+                objective: optional_string(score, "objective")?.unwrap_or_default(),
+            });
+        }
+
+        if let Some(pattern) = object.get("selector") {
+            return Ok(Self::Selector {
+                pattern: string(pattern, "selector")?,
+                separator: separator(object)?,
+            });
+        }
+
+        if let Some(key) = object.get("keybind") {
+            return Ok(Self::Keybind(string(key, "keybind")?));
+        }
+
+        if let Some(path) = object.get("nbt") {
+            let source = if let Some(block) = optional_string(object, "block")? {
+                NbtSource::Block(block)
+            } else if let Some(entity) = optional_string(object, "entity")? {
+                NbtSource::Entity(entity)
+            } else if let Some(storage) = optional_string(object, "storage")? {
+                NbtSource::Storage(storage)
+            } else {
+                return Err("nbt component has no source".into());
+            };
+
+            return Ok(Self::Nbt {
+                path: string(path, "nbt")?,
+                interpret: optional_bool(object, "interpret")?.unwrap_or(false),
+                separator: separator(object)?,
+                source,
+            });
+        }
+
+        Err("text component has no content".into())
+    }
+
+    fn write_plain(&self, out: &mut String) {
+        match self {
+            Self::Text(text) => out.push_str(text),
+            Self::Translatable {
+                key,
+                fallback,
+                args,
+            } => {
+                let format = translation(key).or(fallback.as_deref());
+                match format {
+                    Some(format) => write_translation(format, args, out),
+                    None => {
+                        out.push_str(key);
+                        if !args.is_empty() {
+                            out.push('[');
+                            for (i, arg) in args.iter().enumerate() {
+                                if i > 0 {
+                                    out.push_str(", ");
+                                }
+                                arg.write_plain(out);
+                            }
+                            out.push(']');
+                        }
+                    }
+                }
+            }
+            Self::Keybind(key) => out.push_str(key),
+            Self::Score { .. } => {}
+            Self::Selector { pattern, .. } => out.push_str(pattern),
+            Self::Nbt { path, .. } => out.push_str(path),
+        }
+    }
+}
+
+/// The English text of the translation keys a bot most often sees in chat.
+fn translation(key: &str) -> Option<&'static str> {
+    Some(match key {
+        "chat.type.text" => "<%s> %s",
+        "chat.type.emote" => "* %s %s",
+        "chat.type.announcement" => "[%s] %s",
+        "chat.type.admin" => "[%s: %s]",
+        "chat.type.team.text" => "%s <%s> %s",
+        "chat.type.team.sent" => "-> %s <%s> %s",
+        "commands.message.display.incoming" => "%s whispers to you: %s",
+        "commands.message.display.outgoing" => "You whisper to %s: %s",
+        "multiplayer.player.joined" => "%s joined the game",
+        "multiplayer.player.joined.renamed" => "%s (formerly known as %s) joined the game",
+        "multiplayer.player.left" => "%s left the game",
+        "multiplayer.disconnect.kicked" => "Kicked by an operator",
+        "multiplayer.disconnect.server_shutdown" => "Server closed",
+        "death.attack.generic" => "%s died",
+        _ => return None,
+    })
+}
+
+/// Fills in the `%s` and `%1$s` placeholders of a translation.
+fn write_translation(format: &str, args: &[TextComponent], out: &mut String) {
+    let mut next = 0;
+    let mut chars = format.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+
+        let rest = &format[i + 1..];
+        if rest.starts_with('%') {
+            chars.next();
+            out.push('%');
+        } else if rest.starts_with('s') {
+            chars.next();
+            if let Some(arg) = args.get(next) {
+                arg.write_plain(out);
+            }
+            next += 1;
+        } else if let Some((index, len)) = positional(rest) {
+            for _ in 0..len {
+                chars.next();
+            }
+            if let Some(arg) = index.checked_sub(1).and_then(|index| args.get(index)) {
+                arg.write_plain(out);
+            }
+        } else {
+            out.push(c);
+        }
+    }
+}
+
+/// Parses the `1$s` after a `%`, returning the index and how many characters
+/// it took up.
+fn positional(rest: &str) -> Option<(usize, usize)> {
+    let digits = rest.chars().take_while(char::is_ascii_digit).count();
+    let index = rest[..digits].parse().ok()?;
+    rest[digits..]
+        .starts_with("$s")
+        .then_some((index, digits + 2))
+}
+
+impl Style {
+    /// This style with everything it leaves unset taken from `parent`.
+    pub fn inherit(&self, parent: &Style) -> Style {
+        Style {
+            color: self.color.or(parent.color),
+            bold: self.bold.or(parent.bold),
+            italic: self.italic.or(parent.italic),
+            underlined: self.underlined.or(parent.underlined),
+            strikethrough: self.strikethrough.or(parent.strikethrough),
+            obfuscated: self.obfuscated.or(parent.obfuscated),
+            font: self.font.clone().or_else(|| parent.font.clone()),
+            insertion: self.insertion.clone().or_else(|| parent.insertion.clone()),
+            click_event: self
+                .click_event
+                .clone()
+                .or_else(|| parent.click_event.clone()),
+            hover_event: self
+                .hover_event
+                .clone()
+                .or_else(|| parent.hover_event.clone()),
+        }
+    }
+
+    /// The escape codes resetting the terminal to this style.
+    fn ansi_codes(&self) -> String {
+        let mut codes = String::from("\x1b[0");
+        if let Some(color) = self.color {
+            let (r, g, b) = color.rgb();
+            codes.push_str(&format!(";38;2;{r};{g};{b}"));
+        }
+
+        let flags = [
+            (self.bold, "1"),
+            (self.italic, "3"),
+            (self.underlined, "4"),
+            (self.strikethrough, "9"),
+        ];
+        for (flag, code) in flags {
+            if flag == Some(true) {
+                codes.push(';');
+                codes.push_str(code);
+            }
+        }
+
+        codes.push('m');
+        codes
+    }
+
+    fn write_json(&self, object: &mut Map<String, Value>) {
+        if let Some(color) = self.color {
+            object.insert("color".into(), color.to_string().into());
+        }
+
+        let flags = [
+            ("bold", self.bold),
+            ("italic", self.italic),
+            ("underlined", self.underlined),
+            ("strikethrough", self.strikethrough),
+            ("obfuscated", self.obfuscated),
+        ];
+        for (name, flag) in flags {
+            if let Some(flag) = flag {
+                object.insert(name.into(), flag.into());
+            }
+        }
+
+        if let Some(font) = &self.font {
+            object.insert("font".into(), font.clone().into());
+        }
+        if let Some(insertion) = &self.insertion {
+            object.insert("insertion".into(), insertion.clone().into());
+        }
+        if let Some(event) = &self.click_event {
+            object.insert("clickEvent".into(), event.to_json());
+        }
+        if let Some(event) = &self.hover_event {
+            object.insert("hoverEvent".into(), event.to_json());
+        }
+    }
+
+    fn from_json(object: &Map<String, Value>) -> std::result::Result<Self, String> {
+        let color = optional_string(object, "color")?
+            .map(|color| color.parse())
+            .transpose()?;
+
+        Ok(Self {
+            color,
+            bold: optional_bool(object, "bold")?,
+            italic: optional_bool(object, "italic")?,
+            underlined: optional_bool(object, "underlined")?,
+            strikethrough: optional_bool(object, "strikethrough")?,
+            obfuscated: optional_bool(object, "obfuscated")?,
+            font: optional_string(object, "font")?,
+            insertion: optional_string(object, "insertion")?,
+            click_event: object
+                .get("clickEvent")
+                .map(ClickEvent::from_json)
+                .transpose()?,
+            hover_event: object
+                .get("hoverEvent")
+                .map(HoverEvent::from_json)
+                .transpose()?,
+        })
+    }
+
+    /// The NBT form of the style on its own, as sent for chat decorations.
+    pub fn to_nbt(&self) -> NbtTag {
+        let mut object = Map::new();
+        self.write_json(&mut object);
+        json_to_nbt(&Value::Object(object))
+    }
+
+    pub fn from_nbt(tag: &NbtTag) -> Result<Self> {
+        match nbt_to_json(tag) {
+            Value::Object(object) => Self::from_json(&object).map_err(ProtocolError::custom),
+            _ => Err(ProtocolError::custom("style is not a compound")),
+        }
+    }
+}
+
+impl NamedColor {
+    pub const ALL: [NamedColor; 16] = [
+        Self::Black,
+        Self::DarkBlue,
+        Self::DarkGreen,
+        Self::DarkAqua,
+        Self::DarkRed,
+        Self::DarkPurple,
+        Self::Gold,
+        Self::Gray,
+        Self::DarkGray,
+        Self::Blue,
+        Self::Green,
+        Self::Aqua,
+        Self::Red,
+        Self::LightPurple,
+        Self::Yellow,
+        Self::White,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Black => "black",
+            Self::DarkBlue => "dark_blue",
+            Self::DarkGreen => "dark_green",
+            Self::DarkAqua => "dark_aqua",
+            Self::DarkRed => "dark_red",
+            Self::DarkPurple => "dark_purple",
+            Self::Gold => "gold",
+            Self::Gray => "gray",
+            Self::DarkGray => "dark_gray",
+            Self::Blue => "blue",
+            Self::Green => "green",
+            Self::Aqua => "aqua",
+            Self::Red => "red",
+            Self::LightPurple => "light_purple",
+            Self::Yellow => "yellow",
+            Self::White => "white",
+        }
+    }
+
+    /// The character after the `§` of the legacy formatting code.
+    pub fn code(self) -> char {
+        char::from_digit(self as u32, 16).unwrap()
+    }
+
+    pub fn rgb(self) -> (u8, u8, u8) {
+        match self {
+            Self::Black => (0x00, 0x00, 0x00),
+            Self::DarkBlue => (0x00, 0x00, 0xAA),
+            Self::DarkGreen => (0x00, 0xAA, 0x00),
+            Self::DarkAqua => (0x00, 0xAA, 0xAA),
+            Self::DarkRed => (0xAA, 0x00, 0x00),
+            Self::DarkPurple => (0xAA, 0x00, 0xAA),
+            Self::Gold => (0xFF, 0xAA, 0x00),
+            Self::Gray => (0xAA, 0xAA, 0xAA),
+            Self::DarkGray => (0x55, 0x55, 0x55),
+            Self::Blue => (0x55, 0x55, 0xFF),
+            Self::Green => (0x55, 0xFF, 0x55),
+            Self::Aqua => (0x55, 0xFF, 0xFF),
+            Self::Red => (0xFF, 0x55, 0x55),
+            Self::LightPurple => (0xFF, 0x55, 0xFF),
+            Self::Yellow => (0xFF, 0xFF, 0x55),
+            Self::White => (0xFF, 0xFF, 0xFF),
+        }
+    }
+}
+
+impl TextColor {
+    pub fn rgb(self) -> (u8, u8, u8) {
+        match self {
+            Self::Named(color) => color.rgb(),
+            Self::Rgb(r, g, b) => (r, g, b),
+        }
+    }
+}
+
+impl From<NamedColor> for TextColor {
+    fn from(value: NamedColor) -> Self {
+        Self::Named(value)
+    }
+}
+
+impl fmt::Display for TextColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Named(color) => f.write_str(color.name()),
+            Self::Rgb(r, g, b) => write!(f, "#{r:02X}{g:02X}{b:02X}"),
+        }
+    }
+}
+
+impl std::str::FromStr for TextColor {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        if let Some(hex) = s.strip_prefix('#') {
+            let rgb = u32::from_str_radix(hex, 16)
+                .ok()
+                .filter(|_| hex.len() == 6)
+                .ok_or_else(|| format!("invalid color {s}"))?;
+            let [_, r, g, b] = rgb.to_be_bytes();
+            return Ok(Self::Rgb(r, g, b));
+        }
+
+        NamedColor::ALL
+            .into_iter()
+            .find(|color| color.name() == s)
+            .map(Self::Named)
+            .ok_or_else(|| format!("unknown color {s}"))
+    }
+}
+
+impl ClickEvent {
+    fn to_json(&self) -> Value {
+        let (action, value) = match self {
+            Self::OpenUrl(value) => ("open_url", value.clone()),
+            Self::OpenFile(value) => ("open_file", value.clone()),
+            Self::RunCommand(value) => ("run_command", value.clone()),
+            Self::SuggestCommand(value) => ("suggest_command", value.clone()),
+            Self::ChangePage(page) => ("change_page", page.to_string()),
+            Self::CopyToClipboard(value) => ("copy_to_clipboard", value.clone()),
+        };
+
+        Value::Object(Map::from_iter([
+            ("action".to_string(), action.into()),
+            ("value".to_string(), value.into()),
+        ]))
+    }
+
+    fn from_json(value: &Value) -> std::result::Result<Self, String> {
+        let Value::Object(object) = value else {
+            return Err("`clickEvent` is not an object".into());
+        };
+
+        let action = optional_string(object, "action")?.unwrap_or_default();
+        let value = object
+            .get("value")
+            .map(|value| primitive_string(value, "value"))
+            .transpose()?
+            .unwrap_or_default();
+
+        Ok(match action.as_str() {
+            "open_url" => Self::OpenUrl(value),
+            "open_file" => Self::OpenFile(value),
+            "run_command" => Self::RunCommand(value),
+            "suggest_command" => Self::SuggestCommand(value),
+            "change_page" => {
+                Self::ChangePage(value.parse().map_err(|_| format!("invalid page {value}"))?)
+            }
+            "copy_to_clipboard" => Self::CopyToClipboard(value),
+            action => return Err(format!("unknown click action {action}")),
+        })
+    }
+}
+
+impl HoverEvent {
+    fn to_json(&self) -> Value {
+        let (action, contents) = match self {
+            Self::ShowText(text) => ("show_text", text.to_json()),
+            Self::ShowItem {
+                id,
+                count,
+                components,
+            } => {
+                let mut item = Map::new();
+                item.insert("id".into(), id.clone().into());
+                item.insert("count".into(), (*count).into());
+                if let Some(components) = components {
+                    item.insert("components".into(), components.clone());
+                }
+                ("show_item", Value::Object(item))
+            }
+            Self::ShowEntity { kind, id, name } => {
+                let mut entity = Map::new();
+                entity.insert("type".into(), kind.clone().into());
+                entity.insert("id".into(), id.to_string().into());
+                if let Some(name) = name {
+                    entity.insert("name".into(), name.to_json());
+                }
+                ("show_entity", Value::Object(entity))
+            }
+        };
+
+        Value::Object(Map::from_iter([
+            ("action".to_string(), action.into()),
+            ("contents".to_string(), contents),
+        ]))
+    }
+
+    fn from_json(value: &Value) -> std::result::Result<Self, String> {
+        let Value::Object(object) = value else {
+            return Err("`hoverEvent` is not an object".into());
+        };
+
+        let action = optional_string(object, "action")?.unwrap_or_default();
+        // `value` is how events were sent before 1.16
+        let contents = object
+            .get("contents")
+            .or_else(|| object.get("value"))
+            .ok_or("hover event has no contents")?;
+
+        Ok(match action.as_str() {
+            "show_text" => Self::ShowText(Box::new(TextComponent::from_json(contents)?)),
+            "show_item" => match contents {
+                Value::String(id) => Self::ShowItem {
+                    id: id.clone(),
+                    count: 1,
+                    components: None,
+                },
+                Value::Object(item) => Self::ShowItem {
+                    id: optional_string(item, "id")?.ok_or("item has no id")?,
+                    count: match item.get("count") {
+                        Some(count) => count
+                            .as_i64()
+                            .and_then(|count| i32::try_from(count).ok())
+                            .ok_or("invalid item count")?,
+                        None => 1,
+                    },
+                    components: item.get("components").cloned(),
+                },
+                _ => return Err("invalid item in hover event".into()),
+            },
+            "show_entity" => {
+                let Value::Object(entity) = contents else {
+                    return Err("invalid entity in hover event".into());
+                };
+
+                Self::ShowEntity {
+                    kind: optional_string(entity, "type")?.ok_or("entity has no type")?,
+                    id: entity.get("id").map(uuid).ok_or("entity has no id")??,
+                    name: entity
+                        .get("name")
+                        .map(|name| TextComponent::from_json(name).map(Box::new))
+                        .transpose()?,
+                }
+            }
+            action => return Err(format!("unknown hover action {action}")),
+        })
+    }
+}
+
+fn string(value: &Value, name: &str) -> std::result::Result<String, String> {
+    value
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| format!("`{name}` is not a string"))
+}
+
+/// A string that may also have been sent as a number or boolean, which
+/// components allow for their text.
+fn primitive_string(value: &Value, name: &str) -> std::result::Result<String, String> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Number(number) => Ok(number.to_string()),
+        Value::Bool(value) => Ok(value.to_string()),
+        _ => Err(format!("`{name}` is not a string")),
+    }
+}
+
+fn optional_string(
+    object: &Map<String, Value>,
+    name: &str,
+) -> std::result::Result<Option<String>, String> {
+    object
+        .get(name)
+        .map(|value| string(value, name))
+        .transpose()
+}
+
+/// A boolean, which NBT sends as a byte.
+fn optional_bool(
+    object: &Map<String, Value>,
+    name: &str,
+) -> std::result::Result<Option<bool>, String> {
+    object
+        .get(name)
+        .map(|value| match value {
+            Value::Bool(value) => Ok(*value),
+            Value::Number(number) => Ok(number.as_f64() != Some(0.0)),
+            _ => Err(format!("`{name}` is not a boolean")),
+        })
+        .transpose()
+}
+
+fn separator(
+    object: &Map<String, Value>,
+) -> std::result::Result<Option<Box<TextComponent>>, String> {
+    object
+        .get("separator")
+        .map(|separator| TextComponent::from_json(separator).map(Box::new))
+        .transpose()
+}
+
+/// A UUID as a string or, as NBT sends it, four integers.
+fn uuid(value: &Value) -> std::result::Result<Uuid, String> {
+    match value {
+        Value::String(s) => s.parse().map_err(|_| format!("invalid uuid {s}")),
+        Value::Array(values) if values.len() == 4 => {
+            let mut bits = 0u128;
+            for value in values {
+                let part = value
+                    .as_i64()
+                    .and_then(|part| i32::try_from(part).ok())
+                    .ok_or("invalid uuid")?;
+                bits = bits << 32 | part as u32 as u128;
+            }
+            Ok(Uuid::from_u128(bits))
+        }
+        _ => Err("invalid uuid".into()),
+    }
+}
+
+/// Converts JSON to NBT the way the server does for components. Lists
+/// holding different types have each element wrapped in a compound under an
+/// empty name, as NBT lists can only hold one type.
+fn json_to_nbt(value: &Value) -> NbtTag {
+    match value {
+        Value::Null => NbtTag::Compound(NbtCompound::new()),
+        Value::Bool(value) => NbtTag::from(*value),
+        Value::Number(number) => match (number.as_i64(), number.as_f64()) {
+            (Some(value), _) => match i32::try_from(value) {
+                Ok(value) => NbtTag::Int(value),
+                Err(_) => NbtTag::Long(value),
+            },
+            (None, Some(value)) => NbtTag::Double(value),
+            (None, None) => NbtTag::Double(f64::NAN),
+        },
+        Value::String(s) => NbtTag::String(s.clone()),
+        Value::Array(values) => {
+            let tags = values.iter().map(json_to_nbt).collect::<Vec<_>>();
+            let id = tags.first().map_or(0, NbtTag::id);
+            if tags.iter().all(|tag| tag.id() == id) {
+                return NbtTag::List(tags.into());
+            }
+
+            let wrapped = tags.into_iter().map(|tag| match tag {
+                NbtTag::Compound(compound) => NbtTag::Compound(compound),
+                tag => NbtTag::Compound(NbtCompound::from_iter([(String::new(), tag)])),
+            });
+            NbtTag::List(wrapped.collect())
+        }
+        Value::Object(object) => NbtTag::Compound(
+            object
+                .iter()
+                .map(|(name, value)| (name.clone(), json_to_nbt(value)))
+                .collect(),
+        ),
+    }
+}
+
+/// The id of the tag [`json_to_nbt`] turns `value` into.
+fn json_nbt_id(value: &Value) -> u8 {
+    match value {
+        Value::Null | Value::Object(_) => 10,
+        Value::Bool(_) => 1,
+        Value::Number(number) => match number.as_i64() {
+            Some(value) if i32::try_from(value).is_ok() => 3,
+            Some(_) => 4,
+            None => 6,
+        },
+        Value::String(_) => 8,
+        Value::Array(_) => 9,
+    }
+}
+
+/// How many bytes the payload of [`json_to_nbt`]'s tag is written as,
+/// without building the tag.
+fn json_nbt_len(value: &Value) -> usize {
+    match value {
+        Value::Null => 1,
+        Value::Bool(_) => 1,
+        Value::Number(_) => match json_nbt_id(value) {
+            3 => 4,
+            _ => 8,
+        },
+        Value::String(s) => blocky_nbt::string_len(s),
+        Value::Array(values) => {
+            let id = values.first().map_or(0, json_nbt_id);
+            let mixed = values.iter().any(|value| json_nbt_id(value) != id);
+            let payloads = values.iter().map(|value| match json_nbt_id(value) {
+                // wrapped in a compound: id, empty name and `TAG_End`
+                id if mixed && id != 10 => json_nbt_len(value) + 4,
+                _ => json_nbt_len(value),
+            });
+            5 + payloads.sum::<usize>()
+        }
+        Value::Object(object) => {
+            let entries = object
+                .iter()
+                .map(|(name, value)| 1 + blocky_nbt::string_len(name) + json_nbt_len(value));
+            entries.sum::<usize>() + 1
+        }
+    }
+}
+
+fn nbt_to_json(tag: &NbtTag) -> Value {
+    match tag {
+        NbtTag::Byte(value) => (*value).into(),
+        NbtTag::Short(value) => (*value).into(),
+        NbtTag::Int(value) => (*value).into(),
+        NbtTag::Long(value) => (*value).into(),
+        NbtTag::Float(value) => number(*value as f64),
+        NbtTag::Double(value) => number(*value),
+        NbtTag::ByteArray(values) => values.iter().map(|&value| Value::from(value)).collect(),
+        NbtTag::String(s) => s.clone().into(),
+        NbtTag::List(list) => Value::Array(list.iter().map(nbt_to_json).collect()),
+        NbtTag::Compound(compound) => Value::Object(
+            compound
+                .iter()
+                .map(|(name, tag)| (name.clone(), nbt_to_json(tag)))
+                .collect(),
+        ),
+        NbtTag::IntArray(values) => values.iter().map(|&value| Value::from(value)).collect(),
+        NbtTag::LongArray(values) => values.iter().map(|&value| Value::from(value)).collect(),
+    }
+}
+
+fn number(value: f64) -> Value {
+    Number::from_f64(value).map_or(Value::Null, Value::Number)
+}
+
+impl fmt::Display for TextComponent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_plain())
+    }
+}
+
+impl From<&str> for TextComponent {
+    fn from(value: &str) -> Self {
+        Self::text(value)
+    }
+}
+
+impl From<String> for TextComponent {
+    fn from(value: String) -> Self {
+        Self::text(value)
+    }
+}
+
+impl Serialize for TextComponent {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        self.to_json().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TextComponent {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        Self::from_json(&value).map_err(de::Error::custom)
+    }
+}
+
+impl Encoder for TextComponent {
+    fn byte_len(&self) -> usize {
+        1 + json_nbt_len(&self.to_json())
+    }
+
+    fn encode<W: Write>(&self, buf: &mut W) -> Result<()> {
+        self.to_nbt().encode(buf)
+    }
+}
+
+impl Decoder for TextComponent {
+    fn decode<R: Read>(buf: &mut R) -> Result<Self> {
+        Self::from_nbt(&NbtTag::decode(buf)?)
+    }
+}
+
+impl Encoder for Style {
+    fn byte_len(&self) -> usize {
+        let mut object = Map::new();
+        self.write_json(&mut object);
+        1 + json_nbt_len(&Value::Object(object))
+    }
+
+    fn encode<W: Write>(&self, buf: &mut W) -> Result<()> {
+        self.to_nbt().encode(buf)
+    }
+}
+
+impl Decoder for Style {
+    fn decode<R: Read>(buf: &mut R) -> Result<Self> {
+        // an empty style may be sent as a lone TAG_End
+        match OptionalNbt::<NbtTag>::decode(buf)?.0 {
+            Some(tag) => Self::from_nbt(&tag),
+            None => Ok(Self::default()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_round_trip() {
+        let component = TextComponent::translatable(
+            "chat.type.text",
+            vec![
+                TextComponent::text("Steve")
+                    .click_event(ClickEvent::SuggestCommand("/msg Steve ".into()))
+                    .hover_event(HoverEvent::ShowEntity {
+                        kind: "minecraft:player".into(),
+                        id: Uuid::from_u128(42),
+                        name: Some(Box::new("Steve".into())),
+                    }),
+                TextComponent::text("hi").color(TextColor::Rgb(0x12, 0xAB, 0xEF)),
+            ],
+        )
+        .append(TextComponent::text("!").bold(true).color(NamedColor::Gold));
+
+        let json = serde_json::to_string(&component).unwrap();
+        assert!(json.contains(r##""color":"#12ABEF""##));
+
+        let decoded: TextComponent = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, component);
+        assert_eq!(decoded.to_plain(), "<Steve> hi!");
+    }
+
+    #[test]
+    fn test_json_shorthands() {
+        let component: TextComponent = serde_json::from_str(r#""plain""#).unwrap();
+        assert_eq!(component, TextComponent::text("plain"));
+        assert_eq!(component.to_json(), Value::String("plain".into()));
+
+        let component: TextComponent =
+            serde_json::from_str(r#"["a", {"text": "b", "color": "red"}, 3]"#).unwrap();
+        assert_eq!(component.to_plain(), "ab3");
+        assert_eq!(
+            component.extra[0].style.color,
+            Some(TextColor::Named(NamedColor::Red))
+        );
+
+        let component: TextComponent = serde_json::from_str(
+            r#"{"translate": "custom.key", "with": [1, "x"], "hoverEvent": {"action": "show_text", "value": "old"}}"#,
+        )
+        .unwrap();
+        assert_eq!(component.to_plain(), "custom.key[1, x]");
+
+        assert!(serde_json::from_str::<TextComponent>(r#"{"color": "red"}"#).is_err());
+        assert!(serde_json::from_str::<TextComponent>(r#"{"text": "", "color": "pink"}"#).is_err());
+    }
+
+    #[test]
+    fn test_nbt_round_trip() {
+        let component = TextComponent::text("Hello ")
+            .color(NamedColor::Green)
+            .append(TextComponent::keybind("key.jump").italic(true))
+            .append("plain")
+            .hover_event(HoverEvent::ShowItem {
+                id: "minecraft:stone".into(),
+                count: 3,
+                components: None,
+            });
+
+        let bytes = component.to_bytes().unwrap();
+        assert_eq!(bytes[0], 0x0A);
+        assert_eq!(bytes.len(), component.byte_len());
+        assert_eq!(TextComponent::from_bytes(&bytes).unwrap(), component);
+
+        // a component without style is a string tag
+        let plain = TextComponent::text("hi");
+        assert_eq!(plain.to_bytes().unwrap(), [0x08, 0x00, 0x02, b'h', b'i']);
+        assert_eq!(
+            TextComponent::from_bytes(&[0x08, 0x00, 0x00]).unwrap(),
+            "".into()
+        );
+    }
+
+    #[test]
+    fn test_nbt_booleans_and_uuids() {
+        // {"text": "x", "bold": 1b, "hoverEvent": {"action": "show_entity",
+        //   "contents": {"type": "minecraft:pig", "id": [I; 0, 0, 0, 7]}}}
+        let entity = NbtCompound::from_iter([
+            ("type".to_string(), NbtTag::from("minecraft:pig")),
+            ("id".to_string(), NbtTag::IntArray(vec![0, 0, 0, 7])),
+        ]);
+        let hover = NbtCompound::from_iter([
+            ("action".to_string(), NbtTag::from("show_entity")),
+            ("contents".to_string(), NbtTag::Compound(entity)),
+        ]);
+        let tag = NbtTag::Compound(NbtCompound::from_iter([
+            ("text".to_string(), NbtTag::from("x")),
+            ("bold".to_string(), NbtTag::Byte(1)),
+            ("hoverEvent".to_string(), NbtTag::Compound(hover)),
+        ]));
+
+        let component = TextComponent::from_nbt(&tag).unwrap();
+        assert_eq!(component.style.bold, Some(true));
+        assert_eq!(
+            component.style.hover_event,
+            Some(HoverEvent::ShowEntity {
+                kind: "minecraft:pig".into(),
+                id: Uuid::from_u128(7),
+                name: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_nbt_mixed_list() {
+        let component = TextComponent::text("")
+            .append("a")
+            .append(TextComponent::text("b").bold(true));
+
+        // the string child is wrapped so the list holds only compounds
+        let NbtTag::Compound(compound) = component.to_nbt() else {
+            panic!("expected a compound");
+        };
+        let Some(NbtTag::List(extra)) = compound.get("extra") else {
+            panic!("expected a list");
+        };
+        assert!(extra.iter().all(|tag| tag.id() == 0x0A));
+
+        assert_eq!(
+            TextComponent::from_nbt(&component.to_nbt()).unwrap(),
+            component
+        );
+    }
+
+    #[test]
+    fn test_nbt_byte_len() {
+        let component = TextComponent::text("\0héllo 😱")
+            .append("a")
+            .append(TextComponent::text("b").bold(true))
+            .append(
+                TextComponent::keybind("key.jump").hover_event(HoverEvent::ShowItem {
+                    id: "minecraft:stone".into(),
+                    count: 64,
+                    components: Some(serde_json::json!({
+                        "damage": 1_i64 << 40,
+                        "weight": 0.5,
+                        "lore": [1, "two", [], null],
+                    })),
+                }),
+            );
+
+        assert_eq!(component.byte_len(), Encoder::byte_len(&component.to_nbt()));
+        assert_eq!(component.to_bytes().unwrap().len(), component.byte_len());
+
+        let style = Style {
+            italic: Some(true),
+            font: Some("minecraft:uniform".into()),
+            ..Style::default()
+        };
+        assert_eq!(style.byte_len(), Encoder::byte_len(&style.to_nbt()));
+    }
+
+    #[test]
+    fn test_translation_placeholders() {
+        let component = TextComponent::from_content(TextContent::Translatable {
+            key: "unknown".into(),
+            fallback: Some("%2$s then %1$s, 100%%".into()),
+            args: vec!["a".into(), "b".into()],
+        });
+        assert_eq!(component.to_plain(), "b then a, 100%");
+    }
+
+    #[test]
+    fn test_ansi() {
+        let component = TextComponent::text("red ")
+            .color(NamedColor::Red)
+            .append(TextComponent::text("bold").bold(true))
+            .append(TextComponent::text("white").color(NamedColor::White));
+
+        assert_eq!(
+            component.to_ansi(),
+            "\x1b[0;38;2;255;85;85mred \x1b[0;38;2;255;85;85;1mbold\x1b[0;38;2;255;255;255mwhite\x1b[0m"
+        );
+    }
+}
@@ -0,0 +1,173 @@
+//! Structures shared by the packets of more than one state.
+
+use std::io::{Read, Write};
+
+use blocky_derive::{Decoder, Encoder};
+use blocky_net::{decoder::Decoder, encoder::Encoder, error::Result, types::VarInt};
+use blocky_world::resources::ResourceLocation;
+
+use crate::text::TextComponent;
+
+/// Which chat messages the client wants to receive.
+#[derive(Encoder, Decoder, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChatMode {
+    #[default]
+    Enabled,
+    CommandsOnly,
+    Hidden,
+}
+
+#[derive(Encoder, Decoder, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MainHand {
+    Left,
+    #[default]
+    Right,
+}
+
+/// The settings the client tells the server about, sent while configuring
+/// and again whenever they change.
+#[derive(Encoder, Decoder, Debug, Clone, PartialEq, Eq)]
+pub struct ClientInformation {
+    #[max_len = 16]
+    pub locale: String,
+    pub view_distance: i8,
+    pub chat_mode: ChatMode,
+    pub chat_colors: bool,
+    /// Which parts of the skin are shown, one bit per part starting with the
+    /// cape.
+    pub displayed_skin_parts: u8,
+    pub main_hand: MainHand,
+    pub enable_text_filtering: bool,
+    pub allow_server_listings: bool,
+}
+
+impl Default for ClientInformation {
+    fn default() -> Self {
+        Self {
+            locale: "en_us".to_string(),
+            view_distance: 10,
+            chat_mode: ChatMode::Enabled,
+            chat_colors: true,
+            displayed_skin_parts: 0x7F,
+            main_hand: MainHand::Right,
+            enable_text_filtering: false,
+            allow_server_listings: true,
+        }
+    }
+}
+
+/// A data pack that both sides may already have, so its registry entries
+/// don't need to be sent.
+#[derive(Encoder, Decoder, Debug, Clone, PartialEq, Eq)]
+pub struct KnownPack {
+    pub namespace: String,
+    pub id: String,
+    pub version: String,
+}
+
+/// The tags of one registry, each a list of the numeric ids of its entries.
+#[derive(Encoder, Decoder, Debug, Clone, PartialEq, Eq)]
+pub struct RegistryTags {
+    pub registry: ResourceLocation,
+    #[prefixed(VarInt)]
+    pub tags: Vec<Tag>,
+}
+
+#[derive(Encoder, Decoder, Debug, Clone, PartialEq, Eq)]
+pub struct Tag {
+    pub name: ResourceLocation,
+    #[prefixed(VarInt)]
+    #[varint]
+    pub entries: Vec<i32>,
+}
+
+/// A section of the crash report the server wants added if the client
+/// crashes.
+#[derive(Encoder, Decoder, Debug, Clone, PartialEq, Eq)]
+pub struct CustomReportDetail {
+    #[max_len = 128]
+    pub title: String,
+    #[max_len = 4096]
+    pub description: String,
+}
+
+/// A link shown in the pause menu.
+#[derive(Encoder, Decoder, Debug, Clone, PartialEq, Eq)]
+pub struct ServerLink {
+    pub label: ServerLinkLabel,
+    pub url: String,
+}
+
+#[derive(Encoder, Decoder, Debug, Clone, PartialEq, Eq)]
+#[discriminant(bool)]
+pub enum ServerLinkLabel {
+    #[discriminant = true]
+    BuiltIn(BuiltInLink),
+    #[discriminant = false]
+    Custom(Box<TextComponent>),
+}
+
+/// The labels the client knows how to translate.
+#[derive(Encoder, Decoder, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuiltInLink {
+    BugReport,
+    CommunityGuidelines,
+    Support,
+    Status,
+    Feedback,
+    Community,
+    Website,
+    Forums,
+    News,
+    Announcements,
+}
+
+/// How far the client got with a resource pack the server asked for.
+#[derive(Encoder, Decoder, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourcePackResult {
+    SuccessfullyDownloaded,
+    Declined,
+    FailedDownload,
+    Accepted,
+    Downloaded,
+    InvalidUrl,
+    FailedReload,
+    Discarded,
+}
+
+/// A registry entry sent either as its id or in full, for registries the
+/// server may add entries to on the fly. On the wire it is the id plus one,
+/// with `0` meaning the value follows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdOr<T> {
+    Id(i32),
+    Inline(T),
+}
+
+impl<T: Encoder> Encoder for IdOr<T> {
+    fn byte_len(&self) -> usize {
+        match self {
+            Self::Id(id) => VarInt(id + 1).byte_len(),
+            Self::Inline(value) => VarInt(0).byte_len() + value.byte_len(),
+        }
+    }
+
+    fn encode<W: Write>(&self, buf: &mut W) -> Result<()> {
+        match self {
+            Self::Id(id) => VarInt(id + 1).encode(buf),
+            Self::Inline(value) => {
+                VarInt(0).encode(buf)?;
+                value.encode(buf)
+            }
+        }
+    }
+}
+
+impl<T: Decoder> Decoder for IdOr<T> {
+    fn decode<R: Read>(buf: &mut R) -> Result<Self> {
+        match VarInt::decode(buf)?.0 {
+            0 => Ok(Self::Inline(T::decode(buf)?)),
+            id => Ok(Self::Id(id - 1)),
+        }
+    }
+}
@@ -1,4 +1,12 @@
-use std::str::FromStr;
+use std::{fmt, str::FromStr};
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ResourceLocationError {
+    #[error("invalid namespace: {0}")]
+    InvalidNamespace(String),
+    #[error("invalid path: {0}")]
+    InvalidPath(String),
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ResourceLocation {
@@ -6,14 +14,14 @@ pub struct ResourceLocation {
     pub path: String,
 }
 
-impl ToString for ResourceLocation {
-    fn to_string(&self) -> String {
-        format!("{}:{}", self.namespace, self.path)
+impl fmt::Display for ResourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.namespace, self.path)
     }
 }
 
 impl FromStr for ResourceLocation {
-    type Err = anyhow::Error;
+    type Err = ResourceLocationError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.split_once(":") {
@@ -26,15 +34,18 @@ impl FromStr for ResourceLocation {
 impl ResourceLocation {
     pub const DEFAULT_NAMESPACE: &str = "minecraft";
 
-    pub fn new(namespace: impl Into<String>, path: impl Into<String>) -> anyhow::Result<Self> {
+    pub fn new(
+        namespace: impl Into<String>,
+        path: impl Into<String>,
+    ) -> Result<Self, ResourceLocationError> {
         let namespace = namespace.into();
         if !Self::is_valid_namespace(&namespace) {
-            anyhow::bail!("Invalid namespace: {}", namespace);
+            return Err(ResourceLocationError::InvalidNamespace(namespace));
         }
 
         let path = path.into();
         if !Self::is_valid_path(&path) {
-            anyhow::bail!("Invalid path: {}", path);
+            return Err(ResourceLocationError::InvalidPath(path));
         }
 
         Ok(Self { namespace, path })